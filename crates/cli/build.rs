@@ -0,0 +1,92 @@
+// Copyright 2024 The Tari Project
+// SPDX-License-Identifier: BSD-3-Clause
+
+//! Embeds build details shown by `tari version --verbose` (see `src/cli/build_info.rs`).
+
+use std::path::Path;
+use std::process::Command;
+
+fn main() {
+    let manifest_dir = env("CARGO_MANIFEST_DIR");
+    let workspace_dir = Path::new(&manifest_dir).join("../..");
+
+    set_env("TARI_CLI_GIT_COMMIT", &git_commit(&workspace_dir));
+    set_env("TARI_CLI_RUSTC_VERSION", &rustc_version());
+    set_env("TARI_CLI_TARGET", &env("TARGET"));
+
+    let lock_file = workspace_dir.join("Cargo.lock");
+    let lock = std::fs::read_to_string(&lock_file).unwrap_or_default();
+    set_env(
+        "TARI_CLI_CARGO_GENERATE_VERSION",
+        &locked_version(&lock, "cargo-generate"),
+    );
+    set_env("TARI_CLI_GIT2_VERSION", &locked_version(&lock, "git2"));
+
+    println!("cargo:rerun-if-changed={}", lock_file.display());
+    if let Some(git_dir) = run(Command::new("git")
+        .arg("-C")
+        .arg(&workspace_dir)
+        .args(["rev-parse", "--git-dir"]))
+    {
+        let git_dir = workspace_dir.join(git_dir);
+        println!("cargo:rerun-if-changed={}", git_dir.join("HEAD").display());
+        println!("cargo:rerun-if-changed={}", git_dir.join("index").display());
+    }
+}
+
+fn env(name: &str) -> String {
+    std::env::var(name).unwrap_or_default()
+}
+
+fn set_env(name: &str, value: &str) {
+    let value = if value.is_empty() { "unknown" } else { value };
+    println!("cargo:rustc-env={name}={value}");
+}
+
+/// Short HEAD commit, with `-dirty` when the tree has uncommitted changes. Empty outside a git
+/// checkout (e.g. when built from a published crate).
+fn git_commit(workspace_dir: &Path) -> String {
+    let git = || {
+        let mut cmd = Command::new("git");
+        cmd.arg("-C").arg(workspace_dir);
+        cmd
+    };
+    let Some(commit) = run(git().args(["rev-parse", "--short=12", "HEAD"])) else {
+        return String::new();
+    };
+    match run(git().args(["status", "--porcelain", "--untracked-files=no"])) {
+        Some(status) if !status.is_empty() => format!("{commit}-dirty"),
+        _ => commit,
+    }
+}
+
+fn rustc_version() -> String {
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    run(Command::new(rustc).arg("--version")).unwrap_or_default()
+}
+
+/// Version of the first package called `name` in `Cargo.lock`.
+fn locked_version(lock: &str, name: &str) -> String {
+    let name_line = format!("name = \"{name}\"");
+    let mut lines = lock.lines();
+    while let Some(line) = lines.next() {
+        if line.trim() == name_line
+            && let Some(version) = lines
+                .next()
+                .and_then(|line| line.trim().strip_prefix("version = \""))
+                .and_then(|rest| rest.strip_suffix('"'))
+        {
+            return version.to_string();
+        }
+    }
+    String::new()
+}
+
+/// Runs `cmd` and returns its trimmed stdout, or `None` if it failed.
+fn run(cmd: &mut Command) -> Option<String> {
+    let output = cmd.output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
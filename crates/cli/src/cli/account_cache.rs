@@ -0,0 +1,85 @@
+// Copyright 2024 The Tari Project
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use ootle_network::Network;
+use serde::{Deserialize, Serialize};
+use tari_ootle_publish_lib::walletd_client::ComponentAddressOrName;
+use tokio::fs;
+
+const ACCOUNT_CACHE_FILE_NAME: &str = "last_used_accounts.toml";
+
+/// Accounts that were last used to successfully publish a template, keyed by network so that
+/// switching networks never reuses an account from another chain.
+/// Stored in the CLI base directory.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AccountCache {
+    #[serde(default)]
+    accounts: HashMap<Network, String>,
+}
+
+impl AccountCache {
+    pub fn path(base_dir: &Path) -> PathBuf {
+        base_dir.join(ACCOUNT_CACHE_FILE_NAME)
+    }
+
+    /// Loads the cache from `base_dir`. A missing file yields an empty cache.
+    pub async fn load(base_dir: &Path) -> anyhow::Result<Self> {
+        let path = Self::path(base_dir);
+        if !fs::try_exists(&path).await? {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("reading {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    pub async fn save(&self, base_dir: &Path) -> anyhow::Result<()> {
+        let path = Self::path(base_dir);
+        fs::write(&path, toml::to_string(self)?)
+            .await
+            .with_context(|| format!("writing {}", path.display()))
+    }
+
+    pub fn get(&self, network: Network) -> anyhow::Result<Option<ComponentAddressOrName>> {
+        let account = self.accounts.get(&network).map(|s| s.parse()).transpose()?;
+        Ok(account)
+    }
+
+    pub fn set(&mut self, network: Network, account: &ComponentAddressOrName) {
+        self.accounts.insert(network, account.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn missing_cache_is_empty() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = AccountCache::load(temp_dir.path()).await.unwrap();
+        assert!(cache.get(Network::Esmeralda).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn accounts_roundtrip_per_network() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut cache = AccountCache::default();
+        cache.set(Network::LocalNet, &"dev".parse().unwrap());
+        cache.save(temp_dir.path()).await.unwrap();
+
+        let loaded = AccountCache::load(temp_dir.path()).await.unwrap();
+        assert_eq!(
+            loaded.get(Network::LocalNet).unwrap().map(|a| a.to_string()),
+            Some("dev".to_string())
+        );
+        assert!(loaded.get(Network::Esmeralda).unwrap().is_none());
+    }
+}
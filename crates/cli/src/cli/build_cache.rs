@@ -0,0 +1,371 @@
+// Copyright 2024 The Tari Project
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::fs;
+
+const BUILD_CACHE_FOLDER_NAME: &str = "build_cache";
+const ENTRY_FILE_NAME: &str = "entry.toml";
+const CACHED_BINARY_FILE_NAME: &str = "template.wasm";
+const CACHED_METADATA_FILE_NAME: &str = "template_metadata.cbor";
+
+/// Built template binaries keyed by [`source_hash`], so an unchanged crate is not rebuilt.
+/// Stored in the CLI base directory.
+pub struct BuildCache {
+    dir: PathBuf,
+}
+
+/// Where a cached build's metadata CBOR was written by cargo, so a restore puts it back there.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct CacheEntry {
+    metadata_path: Option<PathBuf>,
+}
+
+impl BuildCache {
+    pub fn new(base_dir: &Path) -> Self {
+        Self {
+            dir: base_dir.join(BUILD_CACHE_FOLDER_NAME),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn entry_dir(&self, key: &str) -> PathBuf {
+        self.dir.join(key)
+    }
+
+    /// Copies the build cached under `key` back into the target directory: the binary to
+    /// `wasm_path` and the metadata CBOR (if any) to where cargo originally wrote it.
+    /// Returns false if nothing is cached under `key`.
+    pub async fn restore(&self, key: &str, wasm_path: &Path) -> anyhow::Result<bool> {
+        let entry_dir = self.entry_dir(key);
+        let entry_path = entry_dir.join(ENTRY_FILE_NAME);
+        if !fs::try_exists(&entry_path).await? {
+            return Ok(false);
+        }
+        let entry: CacheEntry = toml::from_str(&fs::read_to_string(&entry_path).await?)
+            .with_context(|| format!("parsing {}", entry_path.display()))?;
+
+        copy_file(&entry_dir.join(CACHED_BINARY_FILE_NAME), wasm_path).await?;
+        if let Some(metadata_path) = &entry.metadata_path {
+            copy_file(&entry_dir.join(CACHED_METADATA_FILE_NAME), metadata_path).await?;
+        }
+        Ok(true)
+    }
+
+    /// Caches a fresh build under `key`.
+    pub async fn store(&self, key: &str, wasm_path: &Path, metadata_path: Option<&Path>) -> anyhow::Result<()> {
+        let entry_dir = self.entry_dir(key);
+        fs::create_dir_all(&entry_dir)
+            .await
+            .with_context(|| format!("creating {}", entry_dir.display()))?;
+
+        copy_file(wasm_path, &entry_dir.join(CACHED_BINARY_FILE_NAME)).await?;
+        if let Some(metadata_path) = metadata_path {
+            copy_file(metadata_path, &entry_dir.join(CACHED_METADATA_FILE_NAME)).await?;
+        }
+        // Written last: an entry without this file is incomplete and ignored by `restore`.
+        let entry = CacheEntry {
+            metadata_path: metadata_path.map(Path::to_path_buf),
+        };
+        fs::write(entry_dir.join(ENTRY_FILE_NAME), toml::to_string(&entry)?).await?;
+        Ok(())
+    }
+}
+
+async fn copy_file(from: &Path, to: &Path) -> anyhow::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    fs::copy(from, to)
+        .await
+        .with_context(|| format!("copying {} to {}", from.display(), to.display()))?;
+    Ok(())
+}
+
+/// Environment variables that change what cargo builds without changing any file.
+const HASHED_ENV_VARS: &[&str] = &["RUSTFLAGS", "CARGO_ENCODED_RUSTFLAGS", "RUSTC", "RUSTC_WRAPPER"];
+/// Prefixes of cargo's config environment variables that change the build. `CARGO_TARGET_DIR`
+/// only moves the output, so it is left out.
+const HASHED_ENV_PREFIXES: &[&str] = &["CARGO_BUILD_", "CARGO_PROFILE_", "CARGO_TARGET_"];
+/// Manifest keys whose tables list dependencies that may have a `path`.
+const DEPENDENCY_TABLES: &[&str] = &["dependencies", "build-dependencies"];
+
+/// Hashes everything that goes into a build into a cache key: `build_flags`; the crate's files
+/// (except `target` and hidden directories); path dependencies outside the crate, followed
+/// transitively; the workspace root manifest and nearest `Cargo.lock`; cargo config files; the
+/// rustc version; and the environment variables cargo reads flags from. Each input is hashed with
+/// its length, so moving bytes between neighbouring inputs changes the key.
+pub async fn source_hash(crate_dir: &Path, build_flags: &str) -> anyhow::Result<String> {
+    let crate_dir = crate_dir.to_path_buf();
+    let build_flags = build_flags.to_string();
+    tokio::task::spawn_blocking(move || -> anyhow::Result<String> {
+        let mut hasher = KeyHasher::default();
+        hasher.field("flags", build_flags.as_bytes());
+        hasher.field("rustc", &rustc_version(&crate_dir));
+        let mut env_vars = std::env::vars_os()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value)))
+            .filter(|(name, _)| is_hashed_env_var(name))
+            .collect::<Vec<_>>();
+        env_vars.sort();
+        for (name, value) in &env_vars {
+            hasher.field(name, value.as_encoded_bytes());
+        }
+
+        let crate_dir = crate_dir
+            .canonicalize()
+            .with_context(|| format!("reading {}", crate_dir.display()))?;
+        let mut hashed_dirs = Vec::new();
+        let mut pending = vec![crate_dir.clone()];
+        if let Some(workspace_manifest) = workspace_root_manifest(&crate_dir) {
+            hasher.file("workspace manifest", &workspace_manifest)?;
+            pending.extend(local_dependency_dirs(&workspace_manifest)?);
+        }
+        while let Some(dir) = pending.pop() {
+            let dir = dir
+                .canonicalize()
+                .with_context(|| format!("reading {}", dir.display()))?;
+            if hashed_dirs.iter().any(|hashed: &PathBuf| dir.starts_with(hashed)) {
+                continue;
+            }
+            // The crate's own files are keyed by their relative path, so moving it keeps its key.
+            let dir_name = dir
+                .strip_prefix(&crate_dir)
+                .unwrap_or(&dir)
+                .to_string_lossy()
+                .into_owned();
+            hasher.field("dir", dir_name.as_bytes());
+            hash_dir(&dir, &dir, &mut hasher)?;
+            let manifest = dir.join("Cargo.toml");
+            if manifest.is_file() {
+                pending.extend(local_dependency_dirs(&manifest)?);
+            }
+            hashed_dirs.push(dir);
+        }
+
+        if let Some(lock_file) = crate_dir
+            .ancestors()
+            .map(|dir| dir.join("Cargo.lock"))
+            .find(|p| p.is_file())
+        {
+            hasher.file("Cargo.lock", &lock_file)?;
+        }
+        for config in cargo_config_files(&crate_dir) {
+            hasher.file("cargo config", &config)?;
+        }
+        Ok(hasher.finish())
+    })
+    .await?
+}
+
+/// SHA-256 over length-prefixed fields, so the key is stable across Rust releases.
+#[derive(Default)]
+struct KeyHasher(Sha256);
+
+impl KeyHasher {
+    fn field(&mut self, name: &str, value: &[u8]) {
+        for bytes in [name.as_bytes(), value] {
+            self.0.update((bytes.len() as u64).to_le_bytes());
+            self.0.update(bytes);
+        }
+    }
+
+    fn file(&mut self, name: &str, path: &Path) -> anyhow::Result<()> {
+        let content = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        self.field(name, &content);
+        Ok(())
+    }
+
+    /// The first 128 bits, in hex.
+    fn finish(self) -> String {
+        self.0.finalize()[..16].iter().map(|b| format!("{b:02x}")).collect()
+    }
+}
+
+fn is_hashed_env_var(name: &str) -> bool {
+    HASHED_ENV_VARS.contains(&name)
+        || (name != "CARGO_TARGET_DIR" && HASHED_ENV_PREFIXES.iter().any(|prefix| name.starts_with(prefix)))
+}
+
+/// `rustc -vV` as run in `crate_dir`, so a `rust-toolchain.toml` there is honored. Empty if rustc
+/// can't be run; the build itself will then fail.
+fn rustc_version(crate_dir: &Path) -> Vec<u8> {
+    std::process::Command::new("rustc")
+        .arg("-vV")
+        .current_dir(crate_dir)
+        .output()
+        .map(|output| output.stdout)
+        .unwrap_or_default()
+}
+
+/// The manifest of the workspace `crate_dir` belongs to, if it lives above the crate.
+fn workspace_root_manifest(crate_dir: &Path) -> Option<PathBuf> {
+    crate_dir
+        .ancestors()
+        .skip(1)
+        .map(|dir| dir.join("Cargo.toml"))
+        .find(|manifest| {
+            std::fs::read_to_string(manifest)
+                .ok()
+                .and_then(|content| content.parse::<toml::Table>().ok())
+                .is_some_and(|table| table.contains_key("workspace"))
+        })
+}
+
+/// Directories of the `path` dependencies and `[patch]` entries in `manifest`, including
+/// target-specific and `[workspace.dependencies]` ones. Dev-dependencies don't affect the binary.
+fn local_dependency_dirs(manifest: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let content = std::fs::read_to_string(manifest).with_context(|| format!("reading {}", manifest.display()))?;
+    let table: toml::Table = content
+        .parse()
+        .with_context(|| format!("parsing {}", manifest.display()))?;
+    let manifest_dir = manifest.parent().unwrap_or(Path::new("."));
+
+    let subtables = |table: &toml::Table, key: &str| -> Vec<toml::Table> {
+        let values = table
+            .get(key)
+            .and_then(|t| t.as_table())
+            .into_iter()
+            .flat_map(|t| t.values());
+        values.filter_map(|value| value.as_table().cloned()).collect()
+    };
+    let dependency_tables_of = |table: &toml::Table| -> Vec<toml::Table> {
+        let tables = DEPENDENCY_TABLES
+            .iter()
+            .filter_map(|key| table.get(*key)?.as_table().cloned());
+        tables.collect()
+    };
+
+    let mut dependency_tables = dependency_tables_of(&table);
+    for target in subtables(&table, "target") {
+        dependency_tables.extend(dependency_tables_of(&target));
+    }
+    // `[patch.<registry>]` tables list dependencies directly.
+    dependency_tables.extend(subtables(&table, "patch"));
+    if let Some(workspace) = table.get("workspace").and_then(|t| t.as_table()) {
+        dependency_tables.extend(dependency_tables_of(workspace));
+    }
+
+    Ok(dependency_tables
+        .iter()
+        .flat_map(|deps| deps.values())
+        .filter_map(|dep| dep.get("path")?.as_str())
+        .map(|path| manifest_dir.join(path))
+        .filter(|dir| dir.is_dir())
+        .collect())
+}
+
+/// Cargo config files that apply to a build in `crate_dir`: `.cargo/config.toml` (or the legacy
+/// `.cargo/config`) in the crate directory and each ancestor, then in `CARGO_HOME`.
+fn cargo_config_files(crate_dir: &Path) -> Vec<PathBuf> {
+    let cargo_home = std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs_next::home_dir().map(|home| home.join(".cargo")));
+    crate_dir
+        .ancestors()
+        .map(|dir| dir.join(".cargo"))
+        .chain(cargo_home)
+        .flat_map(|dir| [dir.join("config.toml"), dir.join("config")])
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+fn hash_dir(root: &Path, dir: &Path, hasher: &mut KeyHasher) -> anyhow::Result<()> {
+    let mut entries = std::fs::read_dir(dir)
+        .with_context(|| format!("reading {}", dir.display()))?
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let name = entry.file_name();
+        let path = entry.path();
+        if path.is_dir() {
+            if name == "target" || name.to_string_lossy().starts_with('.') {
+                continue;
+            }
+            hash_dir(root, &path, hasher)?;
+        } else {
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            hasher.field(&relative.to_string_lossy(), &std::fs::read(&path)?);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(path: &Path, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[tokio::test]
+    async fn source_hash_tracks_sources_and_flags_but_not_target() {
+        let temp_dir = TempDir::new().unwrap();
+        let crate_dir = temp_dir.path();
+        write(&crate_dir.join("Cargo.toml"), "[package]\nname = \"counter\"");
+        write(&crate_dir.join("src/lib.rs"), "fn a() {}");
+        let hash = source_hash(crate_dir, "opt").await.unwrap();
+
+        write(&crate_dir.join("target/release/counter.wasm"), "binary");
+        assert_eq!(source_hash(crate_dir, "opt").await.unwrap(), hash);
+        assert_ne!(source_hash(crate_dir, "no-opt").await.unwrap(), hash);
+
+        write(&crate_dir.join("src/lib.rs"), "fn b() {}");
+        assert_ne!(source_hash(crate_dir, "opt").await.unwrap(), hash);
+    }
+
+    #[tokio::test]
+    async fn source_hash_tracks_path_dependencies_and_workspace_manifest() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        write(&root.join("Cargo.toml"), "[workspace]\nmembers = [\"counter\"]");
+        write(
+            &root.join("counter/Cargo.toml"),
+            "[package]\nname = \"counter\"\n[dependencies]\nshared = { path = \"../shared\" }",
+        );
+        write(&root.join("counter/src/lib.rs"), "fn a() {}");
+        write(&root.join("shared/Cargo.toml"), "[package]\nname = \"shared\"");
+        write(&root.join("shared/src/lib.rs"), "fn s() {}");
+        let crate_dir = root.join("counter");
+        let hash = source_hash(&crate_dir, "opt").await.unwrap();
+
+        write(&root.join("shared/src/lib.rs"), "fn t() {}");
+        let after_dependency_change = source_hash(&crate_dir, "opt").await.unwrap();
+        assert_ne!(after_dependency_change, hash);
+
+        write(
+            &root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"counter\"]\n[profile.release]\nopt-level = 3",
+        );
+        assert_ne!(source_hash(&crate_dir, "opt").await.unwrap(), after_dependency_change);
+    }
+
+    #[tokio::test]
+    async fn restore_puts_back_binary_and_metadata() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = BuildCache::new(&temp_dir.path().join("base"));
+        let wasm_path = temp_dir.path().join("target/counter.wasm");
+        let metadata_path = temp_dir.path().join("target/build/out/template_metadata.cbor");
+        write(&wasm_path, "binary");
+        write(&metadata_path, "metadata");
+
+        assert!(!cache.restore("abc", &wasm_path).await.unwrap());
+        cache.store("abc", &wasm_path, Some(&metadata_path)).await.unwrap();
+        std::fs::remove_dir_all(temp_dir.path().join("target")).unwrap();
+
+        assert!(cache.restore("abc", &wasm_path).await.unwrap());
+        assert_eq!(std::fs::read_to_string(&wasm_path).unwrap(), "binary");
+        assert_eq!(std::fs::read_to_string(&metadata_path).unwrap(), "metadata");
+    }
+}
@@ -0,0 +1,49 @@
+// Copyright 2024 The Tari Project
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::fmt;
+
+/// Details of how this binary was built, embedded by `build.rs`. Fields are `unknown` when the
+/// build script could not determine them.
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub git_commit: &'static str,
+    pub rustc: &'static str,
+    pub target: &'static str,
+    pub cargo_generate: &'static str,
+    pub git2: &'static str,
+}
+
+pub const BUILD_INFO: BuildInfo = BuildInfo {
+    version: env!("CARGO_PKG_VERSION"),
+    git_commit: env!("TARI_CLI_GIT_COMMIT"),
+    rustc: env!("TARI_CLI_RUSTC_VERSION"),
+    target: env!("TARI_CLI_TARGET"),
+    cargo_generate: env!("TARI_CLI_CARGO_GENERATE_VERSION"),
+    git2: env!("TARI_CLI_GIT2_VERSION"),
+};
+
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "tari {}", self.version)?;
+        writeln!(f, "commit:         {}", self.git_commit)?;
+        writeln!(f, "rustc:          {}", self.rustc)?;
+        writeln!(f, "target:         {}", self.target)?;
+        writeln!(f, "cargo-generate: {}", self.cargo_generate)?;
+        write!(f, "git2:           {}", self.git2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_info_is_embedded() {
+        assert_eq!(BUILD_INFO.version, env!("CARGO_PKG_VERSION"));
+        assert!(!BUILD_INFO.target.is_empty());
+        let rendered = BUILD_INFO.to_string();
+        assert!(rendered.starts_with(&format!("tari {}\n", BUILD_INFO.version)));
+        assert!(rendered.contains("cargo-generate: "), "got: {rendered}");
+    }
+}
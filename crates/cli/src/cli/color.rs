@@ -0,0 +1,87 @@
+// Copyright 2024 The Tari Project
+// SPDX-License-Identifier: BSD-3-Clause
+
+//! Whether output is styled with ANSI colors, decided once from `--color` and consulted by the
+//! `loading!`/`md_println!` macros and everything else that prints markdown.
+
+use std::ffi::OsString;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use clap::ColorChoice;
+use termimad::MadSkin;
+use termimad::crossterm::style::Color;
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Applies `choice` to all later output. `auto` styles output when stdout is a terminal and
+/// `NO_COLOR` is not set.
+pub fn init(choice: ColorChoice) {
+    ENABLED.store(resolve(choice), Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+fn resolve(choice: ColorChoice) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()) && std::io::stdout().is_terminal()
+        },
+    }
+}
+
+/// The skin for inline markdown: bold text in magenta, or no styling at all when color is off.
+pub fn skin() -> MadSkin {
+    if !enabled() {
+        return MadSkin::no_style();
+    }
+    let mut skin = MadSkin::default();
+    skin.bold.set_fg(Color::Magenta);
+    skin
+}
+
+/// The `--color` value in the raw arguments. clap styles its own help and errors while parsing,
+/// before the parsed value is available, so it gets this up front. Unknown values are left for
+/// clap to reject.
+pub fn choice_from_args(args: impl IntoIterator<Item = OsString>) -> ColorChoice {
+    let mut args = args.into_iter();
+    let mut choice = ColorChoice::Auto;
+    while let Some(arg) = args.next() {
+        let value = match arg.to_str() {
+            Some("--") => break,
+            Some("--color") => args.next(),
+            Some(arg) => match arg.strip_prefix("--color=") {
+                Some(value) => Some(value.into()),
+                None => continue,
+            },
+            None => continue,
+        };
+        match value.as_ref().and_then(|value| value.to_str()) {
+            Some("always") => choice = ColorChoice::Always,
+            Some("never") => choice = ColorChoice::Never,
+            Some("auto") => choice = ColorChoice::Auto,
+            _ => {},
+        }
+    }
+    choice
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_choice_is_found_in_raw_arguments() {
+        let choice = |args: &[&str]| choice_from_args(args.iter().map(OsString::from));
+        assert_eq!(choice(&["tari", "build"]), ColorChoice::Auto);
+        assert_eq!(choice(&["tari", "--color", "never", "build"]), ColorChoice::Never);
+        assert_eq!(choice(&["tari", "build", "--color=always"]), ColorChoice::Always);
+        assert_eq!(choice(&["tari", "create", "--", "--color=never"]), ColorChoice::Auto);
+        assert!(resolve(ColorChoice::Always));
+        assert!(!resolve(ColorChoice::Never));
+    }
+}
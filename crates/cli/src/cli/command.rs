@@ -2,36 +2,60 @@
 // SPDX-License-Identifier: BSD-3-Clause
 
 use crate::cli::commands::build::BuildArgs;
+use crate::cli::commands::bundle::BundleArgs;
+use crate::cli::commands::clean::CleanArgs;
 use crate::cli::commands::config::ConfigCommand;
 use crate::cli::commands::create::CreateArgs;
+use crate::cli::commands::fee::FeeArgs;
 use crate::cli::commands::init::InitArgs;
+use crate::cli::commands::inspect::InspectArgs;
 use crate::cli::commands::metadata::MetadataCommand;
 use crate::cli::commands::publish;
 use crate::cli::commands::publish::PublishArgs;
+use crate::cli::commands::rename::RenameArgs;
 use crate::cli::commands::template::TemplateCommand;
+use crate::cli::commands::templates::TemplatesCommand;
+use crate::cli::commands::upgrade::UpgradeArgs;
+use crate::cli::commands::whoami::WhoamiArgs;
 use crate::{
     cli::{
-        commands::{build, config as config_cmd, create, init, metadata, template, wizard},
-        config::{Config, TemplateRepository},
+        build_info::BUILD_INFO,
+        color,
+        commands::{
+            build, bundle, clean, config as config_cmd, create, fee, init, inspect, metadata, rename, template,
+            templates, upgrade, whoami, wizard,
+        },
+        config::{CONFIG_VERSION, Config, TemplateRepository},
         util,
     },
-    git::repository::GitRepository,
+    git::repository::{GitRepository, MIN_TREE_HASH_PREFIX_LEN, TREE_HASH_LEN, TransferControl},
     loading,
 };
-use anyhow::anyhow;
+use anyhow::{Context, anyhow};
 use clap::{
-    Parser, Subcommand,
+    Args, ColorChoice, Parser, Subcommand,
     builder::{Styles, styling::AnsiColor},
 };
 use convert_case::{Case, Casing};
 use ootle_network::Network;
-use std::{convert::Infallible, env, path::PathBuf};
+use sha2::{Digest, Sha256};
+use std::{
+    convert::Infallible,
+    env,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tari_ootle_publish_lib::CancellationToken;
 use tari_ootle_publish_lib::PublisherError;
+use tari_ootle_publish_lib::walletd_client::ComponentAddressOrName;
 use tari_utilities::Hidden;
+use thiserror::Error;
 
 const DEFAULT_DATA_FOLDER_NAME: &str = "tari_cli";
-const TEMPLATE_REPOS_FOLDER_NAME: &str = "template_repositories";
+pub const TEMPLATE_REPOS_FOLDER_NAME: &str = "template_repositories";
 const DEFAULT_CONFIG_FILE_NAME: &str = "tari.config.toml";
+/// Default for `--git-timeout`.
+pub const DEFAULT_GIT_TIMEOUT_SECS: u64 = 120;
 
 pub fn cli_styles() -> Styles {
     Styles::styled()
@@ -80,10 +104,268 @@ pub fn config_override_parser(config_override: &str) -> Result<ConfigOverride, S
     })
 }
 
+/// The fee account, given either as `--account` (name or address, whichever it parses as) or
+/// explicitly as `--account-name` / `--account-address`. At most one of them may be set.
+#[derive(Clone, Args, Debug, Default)]
+#[group(multiple = false)]
+pub struct AccountArgs {
+    /// Account to be used for publishing fees (account name or component address).
+    #[arg(short = 'a', long, value_parser = account_parser)]
+    pub account: Option<ComponentAddressOrName>,
+
+    /// Account to be used for publishing fees, always treated as an account name.
+    #[arg(long, value_name = "NAME", value_parser = account_name_parser)]
+    pub account_name: Option<ComponentAddressOrName>,
+
+    /// Account to be used for publishing fees, always treated as a component address.
+    #[arg(long, value_name = "ADDRESS", value_parser = account_address_parser)]
+    pub account_address: Option<ComponentAddressOrName>,
+
+    /// Read the account (as for --account) from a file, keeping it out of process listings and
+    /// shell history.
+    #[arg(long, value_name = "PATH")]
+    pub account_file: Option<PathBuf>,
+
+    /// Read the account (as for --account) from standard input.
+    #[arg(long, default_value_t = false)]
+    pub account_stdin: bool,
+
+    /// The flag `account` was read through by [`Self::read_account_source`].
+    #[arg(skip)]
+    read_from: Option<&'static str>,
+}
+
+impl AccountArgs {
+    /// Reads `--account-file` or `--account-stdin` into `account`, so [`Self::selected`] sees it.
+    /// The source is consumed, so calling this again does not read it twice.
+    pub async fn read_account_source(&mut self) -> anyhow::Result<()> {
+        let (raw, flag) = if let Some(path) = self.account_file.take() {
+            (util::read_value_file(&path).await?, "--account-file")
+        } else if std::mem::take(&mut self.account_stdin) {
+            (util::read_value_stdin()?, "--account-stdin")
+        } else {
+            return Ok(());
+        };
+        self.account = Some(account_parser(&raw).map_err(|e| anyhow!(e))?);
+        self.read_from = Some(flag);
+        Ok(())
+    }
+
+    /// The account given on the command line, if any, and the flag it was given with.
+    pub fn selected(&self) -> Option<(&ComponentAddressOrName, &'static str)> {
+        self.account
+            .as_ref()
+            .map(|account| (account, self.read_from.unwrap_or("--account")))
+            .or(self.account_name.as_ref().map(|account| (account, "--account-name")))
+            .or(self
+                .account_address
+                .as_ref()
+                .map(|account| (account, "--account-address")))
+    }
+}
+
+/// Parses an `--account-name` value without trying to interpret it as an address.
+pub fn account_name_parser(name: &str) -> Result<ComponentAddressOrName, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(String::from("Account name cannot be empty!"));
+    }
+    Ok(ComponentAddressOrName::Name(name.to_string()))
+}
+
+/// Parses an `--account-address` value, rejecting anything that is not a component address.
+pub fn account_address_parser(address: &str) -> Result<ComponentAddressOrName, String> {
+    match account_parser(address)? {
+        account @ ComponentAddressOrName::ComponentAddress(_) => Ok(account),
+        ComponentAddressOrName::Name(_) => Err(format!(
+            "'{}' is not a component address. Expected `component_<64 hex characters>`; \
+             use --account-name for account names.",
+            address.trim()
+        )),
+    }
+}
+
+/// Parses an `--account` value, explaining the accepted formats when it is malformed.
+pub fn account_parser(account: &str) -> Result<ComponentAddressOrName, String> {
+    let account = account.trim();
+    if account.is_empty() {
+        return Err(String::from(
+            "Account cannot be empty! Expected an account name or a component address.",
+        ));
+    }
+
+    account.parse().map_err(|error| {
+        format!(
+            "Invalid account '{account}': {error}\n\
+             Expected either an account name (e.g. `myaccount`) or a component address \
+             (e.g. `component_<64 hex characters>`)."
+        )
+    })
+}
+
+/// Parses a `--fee-multiplier` value, which must be a finite number of at least 1.0.
+pub fn fee_multiplier_parser(value: &str) -> Result<f64, String> {
+    let multiplier = value
+        .trim()
+        .parse::<f64>()
+        .map_err(|error| format!("Invalid fee multiplier '{value}': {error}"))?;
+    if !multiplier.is_finite() || multiplier < 1.0 {
+        return Err(format!(
+            "Invalid fee multiplier '{value}': must be at least 1.0 (the estimated fee is the minimum)"
+        ));
+    }
+    Ok(multiplier)
+}
+
+/// Parses an `--expect-tree` value: a tree hash, or a prefix of at least
+/// [`MIN_TREE_HASH_PREFIX_LEN`] hex digits. Returned in lowercase.
+pub fn tree_hash_parser(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    if !value.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid tree hash '{value}': must be hex digits"));
+    }
+    if !(MIN_TREE_HASH_PREFIX_LEN..=TREE_HASH_LEN).contains(&value.len()) {
+        return Err(format!(
+            "Invalid tree hash '{value}': must be the full {TREE_HASH_LEN}-digit hash or a prefix of at least \
+             {MIN_TREE_HASH_PREFIX_LEN} digits (see `tari templates info`)"
+        ));
+    }
+    Ok(value.to_ascii_lowercase())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn account_parser_rejects_empty_value() {
+        let err = account_parser("  ").unwrap_err();
+        assert!(err.contains("account name"), "got: {err}");
+    }
+
+    #[test]
+    fn same_named_repositories_on_different_hosts_get_separate_dirs() {
+        let repos_dir = Path::new("/repos");
+        let github = template_repository_dir(repos_dir, "https://github.com/foo/bar").unwrap();
+        let gitlab = template_repository_dir(repos_dir, "https://gitlab.com/foo/bar").unwrap();
+        assert!(github.starts_with("/repos/github.com/foo"), "got: {}", github.display());
+        assert!(gitlab.starts_with("/repos/gitlab.com/foo"), "got: {}", gitlab.display());
+        assert!(gitlab.file_name().unwrap().to_str().unwrap().starts_with("bar-"));
+        assert_eq!(
+            template_repository_dir(repos_dir, "git@gitlab.com:foo/bar").unwrap(),
+            gitlab
+        );
+    }
+
+    #[test]
+    fn repositories_differing_only_in_port_or_local_path_get_separate_dirs() {
+        let repos_dir = Path::new("/repos");
+        let dir = |url| template_repository_dir(repos_dir, url).unwrap();
+        assert_ne!(dir("https://host:8443/owner/repo"), dir("https://host/owner/repo"));
+        assert_ne!(dir("/home/a/owner/repo"), dir("/home/b/owner/repo"));
+        assert_ne!(dir("file:///home/a/owner/repo"), dir("file:///home/b/owner/repo"));
+        assert!(dir("/home/a/owner/repo").starts_with("/repos/local/owner"));
+    }
+
+    #[test]
+    fn equivalent_repository_urls_share_a_dir() {
+        let repos_dir = Path::new("/repos");
+        let expected = template_repository_dir(repos_dir, "https://host/owner/repo").unwrap();
+        for url in [
+            "https://host/owner/repo",
+            "https://host/owner/repo/",
+            "https://host/owner/repo.git",
+            "https://host/owner/repo.git/",
+        ] {
+            assert_eq!(template_repository_dir(repos_dir, url).unwrap(), expected, "url: {url}");
+        }
+        assert!(template_repository_dir(repos_dir, "https://host/").is_err());
+    }
+
+    #[test]
+    fn git_timeout_error_says_whether_data_stalled() {
+        let error = GitTimeoutError {
+            url: "https://github.com/tari-project/wasm-template".to_string(),
+            timeout: Duration::from_secs(120),
+            idle: Some(Duration::from_secs(95)),
+        }
+        .to_string();
+        assert!(error.starts_with("Timed out after 120s"), "got: {error}");
+        assert!(error.contains("no data received for the last 95s"), "got: {error}");
+        assert!(error.contains("--git-timeout"), "got: {error}");
+    }
+
+    #[test]
+    fn config_load_error_names_file_and_reset_flag() {
+        let err = ConfigLoadError {
+            path: PathBuf::from("/home/me/.config/tari_cli/tari.config.toml"),
+            error: anyhow!("expected `=`, found newline"),
+        }
+        .to_string();
+        assert!(err.contains("/home/me/.config/tari_cli/tari.config.toml"), "got: {err}");
+        assert!(err.contains("expected `=`"), "got: {err}");
+        assert!(err.contains("--reset-config"), "got: {err}");
+    }
+
+    #[test]
+    fn account_parser_accepts_account_name() {
+        assert!(account_parser("myaccount").is_ok());
+    }
+
+    #[test]
+    fn explicit_account_parsers_pick_the_variant() {
+        assert!(matches!(
+            account_name_parser("component_abc"),
+            Ok(ComponentAddressOrName::Name(name)) if name == "component_abc"
+        ));
+        let err = account_address_parser("myaccount").unwrap_err();
+        assert!(err.contains("--account-name"), "got: {err}");
+    }
+
+    #[test]
+    fn account_flags_are_mutually_exclusive() {
+        #[derive(Parser)]
+        struct TestCli {
+            #[clap(flatten)]
+            account: AccountArgs,
+        }
+        let cli = TestCli::try_parse_from(["tari", "--account-name", "alice"]).unwrap();
+        assert!(matches!(
+            cli.account.selected(),
+            Some((ComponentAddressOrName::Name(name), "--account-name")) if name == "alice"
+        ));
+        assert!(TestCli::try_parse_from(["tari", "-a", "alice", "--account-name", "bob"]).is_err());
+    }
+
+    #[test]
+    fn api_key_file_conflicts_with_api_key() {
+        #[derive(Parser)]
+        struct TestCli {
+            #[clap(flatten)]
+            args: CommonArguments,
+        }
+        assert!(TestCli::try_parse_from(["tari", "--api-key-file", "key"]).is_ok());
+        assert!(TestCli::try_parse_from(["tari", "--api-key", "k", "--api-key-file", "key"]).is_err());
+    }
+
+    #[test]
+    fn tree_hash_parser_requires_a_hex_prefix_of_seven_digits() {
+        assert_eq!(tree_hash_parser("ABCDEF0"), Ok("abcdef0".to_string()));
+        assert!(tree_hash_parser(&"a".repeat(40)).is_ok());
+        assert!(tree_hash_parser("a").is_err());
+        assert!(tree_hash_parser("abcdef").is_err());
+        assert!(tree_hash_parser("abcdefg").is_err());
+        assert!(tree_hash_parser(&"a".repeat(41)).is_err());
+    }
+
+    #[test]
+    fn fee_multiplier_parser_rejects_values_below_one() {
+        assert_eq!(fee_multiplier_parser("1.5"), Ok(1.5));
+        assert!(fee_multiplier_parser("0.9").is_err());
+        assert!(fee_multiplier_parser("NaN").is_err());
+        assert!(fee_multiplier_parser("abc").is_err());
+    }
+
     #[test]
     fn override_parser_accepts_nested_network_keys() {
         let ov = config_override_parser("networks.esmeralda.wallet-daemon-url=http://localhost:5100/")
@@ -108,7 +390,6 @@ mod tests {
         assert_eq!(ov.value, "acc=ount");
     }
 
-    use anyhow::Context;
     use tari_ootle_publish_lib::walletd_client::error::WalletDaemonClientError;
 
     fn unauthorized_error() -> anyhow::Error {
@@ -151,7 +432,7 @@ pub fn project_name_parser(project_name: &str) -> Result<String, String> {
     Ok(project_name.to_case(Case::Snake))
 }
 
-fn parse_network(s: &str) -> Result<Network, String> {
+pub fn parse_network(s: &str) -> Result<Network, String> {
     s.parse().map_err(|e: ootle_network::NetworkParseError| e.to_string())
 }
 
@@ -161,6 +442,54 @@ fn parse_api_key(value: &str) -> Result<Hidden<String>, Infallible> {
     Ok(Hidden::hide(value.to_string()))
 }
 
+/// Updating the template repository took longer than `--git-timeout`.
+#[derive(Error, Debug)]
+#[error(
+    "Timed out after {}s updating the template repository {url} ({}). \
+     Check your connection, configure `template-repository.mirrors`, or raise --git-timeout.",
+    .timeout.as_secs(),
+    idle_description(.idle)
+)]
+pub struct GitTimeoutError {
+    url: String,
+    timeout: Duration,
+    idle: Option<Duration>,
+}
+
+fn idle_description(idle: &Option<Duration>) -> String {
+    match idle {
+        Some(idle) => format!("no data received for the last {}s", idle.as_secs()),
+        None => "no data was received".to_string(),
+    }
+}
+
+/// The global config file exists but could not be read or parsed (nor migrated).
+#[derive(Error, Debug)]
+#[error(
+    "Failed to load config file {}: {error:#}\n\
+     Fix the file, or run with --reset-config to replace it with the defaults (the current file is kept as a backup).",
+    .path.display()
+)]
+pub struct ConfigLoadError {
+    path: PathBuf,
+    error: anyhow::Error,
+}
+
+/// Upgrades a config file written by an older CLI in place, keeping a backup of the original.
+async fn migrate_config_file(path: &Path) -> anyhow::Result<Config> {
+    let content = tokio::fs::read_to_string(path).await?;
+    let migrated = Config::migrate(&content)?;
+    let config: Config = util::parse_toml_with_env(&migrated)?;
+    let backup = Config::backup_file(path).await?;
+    tokio::fs::write(path, migrated).await?;
+    println!(
+        "Migrated config at {} to version {CONFIG_VERSION} (backup at {})",
+        path.display(),
+        backup.display()
+    );
+    Ok(config)
+}
+
 /// Guidance shown when the wallet daemon rejects a request because of
 /// authentication. The opening line is tailored to whether the user supplied a
 /// key at all, so a missing key and a bad key get distinct, actionable advice.
@@ -194,12 +523,167 @@ fn explain_wallet_daemon_auth_error(err: anyhow::Error, had_api_key: bool) -> an
     });
 
     if unauthorized {
-        anyhow!("{err:#}\n\n{}", wallet_daemon_auth_help(had_api_key))
+        WalletDaemonAuthError(format!("{err:#}\n\n{}", wallet_daemon_auth_help(had_api_key))).into()
     } else {
         err
     }
 }
 
+/// A wallet daemon authentication failure, with setup guidance appended to the original error.
+#[derive(Error, Debug)]
+#[error("{0}")]
+pub struct WalletDaemonAuthError(String);
+
+/// Clones the template repository into `base_dir`, or fetches and checks out the configured
+/// reference (branch, tag or commit) when it has been cloned before. A clone already detached at a
+/// pinned commit is left as is, since that commit cannot change. When the repository URL can't be
+/// reached, the configured mirrors are tried in order. git2 is blocking, so the git work runs on
+/// tokio's blocking thread pool.
+pub async fn refresh_template_repository(
+    base_dir: &Path,
+    template_repo: &TemplateRepository,
+    verbosity: u8,
+    timeout: Duration,
+) -> anyhow::Result<GitRepository> {
+    let repos_dir = base_dir.join(TEMPLATE_REPOS_FOLDER_NAME);
+    util::create_dir(&repos_dir).await?;
+    let repo_folder_path = template_repository_dir(&repos_dir, &template_repo.url)?;
+    let already_cloned = util::dir_exists(&repo_folder_path).await?;
+    if verbosity >= 1 {
+        eprintln!(
+            "ℹ️  Template repository {} at {} ({} {})",
+            template_repo.url,
+            template_repo.reference,
+            if already_cloned { "updating" } else { "cloning into" },
+            repo_folder_path.display()
+        );
+    }
+
+    let primary_url = template_repo.url.clone();
+    let template_repo = template_repo.clone();
+    let transfer = TransferControl::default();
+    let task_transfer = transfer.clone();
+    let task = tokio::task::spawn_blocking(move || -> anyhow::Result<(GitRepository, String)> {
+        let open = |path: &Path| {
+            GitRepository::new(path.to_path_buf())
+                .with_verbosity(verbosity)
+                .with_transfer_control(task_transfer.clone())
+        };
+        let mut repo = open(&repo_folder_path);
+        if already_cloned {
+            repo.load()?;
+            if repo.head_state()?.is_detached_at(&template_repo.reference) {
+                return Ok((repo, template_repo.url));
+            }
+        }
+        // A first clone goes into a temporary directory beside its final place and is renamed
+        // there once checked out, so a clone that fails or times out never leaves a partial
+        // repository for the next run to load.
+        let parent = repo_folder_path
+            .parent()
+            .context("template repository path has no parent")?;
+        std::fs::create_dir_all(parent)?;
+        // The primary URL first, then each mirror, moving on only when the source is unreachable.
+        let mut last_error = None;
+        for url in std::iter::once(&template_repo.url).chain(&template_repo.mirrors) {
+            let staging = match already_cloned {
+                true => None,
+                false => Some(tempfile::Builder::new().prefix(".partial-").tempdir_in(parent)?),
+            };
+            let result = match &staging {
+                None => repo
+                    .set_origin_url(url)
+                    .and_then(|_| repo.pull_changes(Some(template_repo.reference.clone()))),
+                Some(staging) => {
+                    repo = open(staging.path());
+                    repo.clone_and_checkout(url, &template_repo.reference)
+                },
+            };
+            match result {
+                Ok(()) => {
+                    if let Some(staging) = staging {
+                        // Closes the staged repository before moving it.
+                        repo = open(&repo_folder_path);
+                        std::fs::rename(staging.keep(), &repo_folder_path)
+                            .with_context(|| format!("moving the clone to {}", repo_folder_path.display()))?;
+                        repo.load()?;
+                    }
+                    return Ok((repo, url.clone()));
+                },
+                Err(error) if error.is_network() => {
+                    eprintln!("⚠️  Template repository {url} is unreachable: {error}");
+                    last_error = Some(error);
+                },
+                Err(error) => return Err(error.into()),
+            }
+        }
+        Err(last_error.expect("at least the primary URL was tried").into())
+    });
+    let (repo, source) = match tokio::time::timeout(timeout, task).await {
+        Ok(result) => result??,
+        Err(_) => {
+            // Stops the blocking git work at its next progress report.
+            transfer.abort();
+            return Err(GitTimeoutError {
+                url: primary_url,
+                timeout,
+                idle: transfer.since_last_progress(),
+            }
+            .into());
+        },
+    };
+    if source != primary_url {
+        println!("🪞 Using template repository mirror {source}");
+    }
+    if verbosity >= 1 {
+        eprintln!("ℹ️  Template repository is at commit {}", repo.head_commit_hash()?);
+    }
+
+    Ok(repo)
+}
+
+/// Where the repository at `url` is cloned: `<host>/<owner>/<name>-<hash>` under `repos_dir`. URLs
+/// without a host (local paths and `file://`) go under `local`. The hash, of the host, port and
+/// full path, keeps repositories apart that share those directory names, such as local
+/// repositories in different places or servers on different ports of one host. Trailing slashes
+/// and a `.git` suffix are ignored, so equivalent URLs share one clone.
+fn template_repository_dir(repos_dir: &Path, url: &str) -> anyhow::Result<PathBuf> {
+    let (host, port, path) = split_repository_url(url);
+    let path = path.trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let normalized = match port {
+        Some(port) => format!("{host}:{port}/{}", path.trim_start_matches('/')),
+        None => format!("{host}/{}", path.trim_start_matches('/')),
+    };
+    let hash: String = Sha256::digest(normalized.as_bytes())[..4]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    let mut segments = path.rsplit('/');
+    let repo_name = segments
+        .next()
+        .filter(|name| !name.is_empty())
+        .ok_or(anyhow!("Failed to get repository name from URL!"))?;
+    let repo_user = segments
+        .next()
+        .filter(|user| !user.is_empty())
+        .ok_or(anyhow!("Failed to get repository owner from URL!"))?;
+    Ok(repos_dir.join(host).join(repo_user).join(format!("{repo_name}-{hash}")))
+}
+
+/// Splits a repository URL into its host, port and path, including scp-like `git@host:owner/repo`
+/// URLs.
+fn split_repository_url(url: &str) -> (String, Option<u16>, String) {
+    if let Ok(parsed) = url::Url::parse(url) {
+        let host = parsed.host_str().unwrap_or("local").to_string();
+        return (host, parsed.port(), parsed.path().to_string());
+    }
+    match url.split_once('@').and_then(|(_, rest)| rest.split_once(':')) {
+        Some((host, path)) => (host.to_string(), None, path.to_string()),
+        None => ("local".to_string(), None, url.to_string()),
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ConfigOverride {
     pub key: String,
@@ -208,18 +692,45 @@ pub struct ConfigOverride {
 
 #[derive(Clone, Parser, Debug)]
 pub struct CommonArguments {
-    /// Base directory, where all the CLI data will be saved
-    #[arg(short = 'b', long, value_name = "PATH", default_value = default_base_dir().into_os_string())]
+    /// Base directory, where all the CLI data will be saved.
+    /// Can also be set via the `TARI_CLI_BASE_DIR` environment variable.
+    #[arg(
+        short = 'b',
+        long,
+        value_name = "PATH",
+        env = "TARI_CLI_BASE_DIR",
+        default_value = default_base_dir().into_os_string()
+    )]
     base_dir: PathBuf,
 
-    /// Config file location
-    #[arg(short = 'c', long, value_name = "PATH", default_value = default_config_file().into_os_string())]
+    /// Config file location.
+    /// Can also be set via the `TARI_CLI_CONFIG_FILE` environment variable.
+    #[arg(
+        short = 'c',
+        long,
+        value_name = "PATH",
+        env = "TARI_CLI_CONFIG_FILE",
+        default_value = default_config_file().into_os_string()
+    )]
     config_file_path: PathBuf,
 
     /// Config file overrides
     #[arg(short = 'e', long, value_name = "KEY=VALUE", value_parser = config_override_parser)]
     config_overrides: Vec<ConfigOverride>,
 
+    /// Print more detail: full build logs on failure, cargo-generate output and template
+    /// repository details. Repeat (`-vv`) for git transfer details too.
+    #[arg(short = 'v', long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Seconds to wait for the template repository to be cloned or updated before giving up.
+    #[arg(long, value_name = "SECONDS", default_value_t = DEFAULT_GIT_TIMEOUT_SECS, global = true)]
+    git_timeout: u64,
+
+    /// Replace the config file with the defaults. The current file is kept as a backup.
+    #[arg(long, default_value_t = false)]
+    reset_config: bool,
+
     /// Network to use. Overrides the default set in project and global config.
     /// (e.g. `esmeralda`, `igor`, `localnet`, `mainnet`)
     #[arg(short = 'n', long, value_name = "NETWORK", value_parser = parse_network, global = true)]
@@ -239,6 +750,16 @@ pub struct CommonArguments {
         global = true
     )]
     api_key: Option<Hidden<String>>,
+
+    /// Read the wallet daemon API key from a file instead. Can't be combined with --api-key or
+    /// `TARI_WALLET_DAEMON_API_KEY`.
+    #[arg(long, value_name = "PATH", global = true, conflicts_with = "api_key")]
+    api_key_file: Option<PathBuf>,
+
+    /// When to style output with colors. `auto` uses colors only when stdout is a terminal and
+    /// `NO_COLOR` is not set.
+    #[arg(long, value_name = "WHEN", value_enum, default_value_t = ColorChoice::Auto, global = true)]
+    color: ColorChoice,
 }
 
 #[derive(Clone, Parser)]
@@ -269,16 +790,51 @@ pub enum Command {
         #[clap(flatten)]
         args: CreateArgs,
     },
+    /// Compare a crate with the latest version of the template it was created from. Changes nothing.
+    Upgrade {
+        #[clap(flatten)]
+        args: UpgradeArgs,
+    },
+    /// Rename a template crate (package name and directory).
+    Rename {
+        #[clap(flatten)]
+        args: RenameArgs,
+    },
     /// Build the template WASM binary.
     Build {
         #[clap(flatten)]
         args: BuildArgs,
     },
+    /// Build template crates and pack their WASM binaries into a tar archive for `publish --bundle`.
+    Bundle {
+        #[clap(flatten)]
+        args: BundleArgs,
+    },
+    /// Inspect a built template binary: size, hash, ABI functions, imports and exports. Works offline.
+    Inspect {
+        #[clap(flatten)]
+        args: InspectArgs,
+    },
+    /// Estimate the fee for publishing a template and check the account can afford it, without publishing.
+    Fee {
+        #[clap(flatten)]
+        args: FeeArgs,
+    },
     /// Publish a Tari template to a network.
     #[clap(alias = "deploy")]
     Publish {
         #[clap(flatten)]
-        args: PublishArgs,
+        args: Box<PublishArgs>,
+    },
+    /// Show the network, wallet daemon and account `publish` would use, without publishing.
+    Whoami {
+        #[clap(flatten)]
+        args: WhoamiArgs,
+    },
+    /// Browse the starter templates available to `create`.
+    Templates {
+        #[command(subcommand)]
+        command: TemplatesCommand,
     },
     /// Template metadata tooling (init, inspect, publish with metadata).
     Template {
@@ -290,11 +846,36 @@ pub enum Command {
         #[command(subcommand)]
         command: MetadataCommand,
     },
+    /// Remove cached template repositories and build artifacts from the base directory.
+    Clean {
+        #[clap(flatten)]
+        args: CleanArgs,
+    },
     /// Manage project configuration (tari.config.toml).
     Config {
         #[command(subcommand)]
         command: ConfigCommand,
     },
+    /// Print the CLI version. With --verbose, also the commit it was built from, the rustc
+    /// version, the target triple and key dependency versions (useful for bug reports).
+    Version,
+}
+
+impl Command {
+    /// Hands the global `--verbose` level to the commands that honour it.
+    fn set_verbosity(&mut self, level: u8) {
+        match self {
+            Command::Create { args } => args.verbose = level,
+            Command::Upgrade { args } => args.verbose = level,
+            Command::Build { args } => args.verbose = level,
+            Command::Fee { args } => args.verbose = level,
+            Command::Publish { args } => args.verbose = level,
+            Command::Template {
+                command: TemplateCommand::Publish { args },
+            } => args.verbose = level,
+            _ => {},
+        }
+    }
 }
 
 impl Cli {
@@ -304,12 +885,10 @@ impl Cli {
 
         // create config file dir if not exists
         util::create_dir(
-            &self
-                .args
+            self.args
                 .config_file_path
                 .parent()
-                .ok_or(anyhow!("Can't find folder of configuration file!"))?
-                .to_path_buf(),
+                .ok_or(anyhow!("Can't find folder of configuration file!"))?,
         )
         .await?;
 
@@ -320,15 +899,25 @@ impl Cli {
             let cfg = Config::default();
             cfg.write_to_file(path).await?;
             cfg
+        } else if self.args.reset_config {
+            let backup = Config::backup_file(path).await?;
+            println!(
+                "Resetting config at {} to defaults (backup at {})",
+                path.display(),
+                backup.display()
+            );
+            let cfg = Config::default();
+            cfg.write_to_file(path).await?;
+            cfg
         } else {
             match Config::open(path).await {
                 Ok(cfg) => cfg,
-                Err(error) => {
-                    println!("Failed to open config file: {error:?}, creating default...");
-                    let cfg = Config::default();
-                    cfg.write_to_file(path).await?;
-                    cfg
-                },
+                // Never overwrite the user's file here: a typo or a transient read error must not
+                // cost them their settings. Only `--reset-config` replaces it.
+                Err(error) => migrate_config_file(path).await.map_err(|_| ConfigLoadError {
+                    path: path.clone(),
+                    error,
+                })?,
             }
         };
 
@@ -340,42 +929,44 @@ impl Cli {
         Ok(config)
     }
 
-    async fn refresh_template_repository(&self, template_repo: &TemplateRepository) -> anyhow::Result<GitRepository> {
-        util::create_dir(&self.args.base_dir.join(TEMPLATE_REPOS_FOLDER_NAME)).await?;
-        let repo_url_splitted: Vec<&str> = template_repo.url.split("/").collect();
-        let repo_name = repo_url_splitted
-            .last()
-            .ok_or(anyhow!("Failed to get repository name from URL!"))?;
-        let repo_user = repo_url_splitted
-            .get(repo_url_splitted.len() - 2)
-            .ok_or(anyhow!("Failed to get repository owner from URL!"))?;
-        let repo_folder_path = self
-            .args
-            .base_dir
-            .join(TEMPLATE_REPOS_FOLDER_NAME)
-            .join(repo_user)
-            .join(repo_name);
-        let mut repo = GitRepository::new(repo_folder_path.clone());
-
-        if util::dir_exists(&repo_folder_path).await? {
-            repo.load()?;
-            let current_branch = repo.current_branch_name()?;
-            if current_branch != template_repo.branch {
-                repo.pull_changes(Some(template_repo.branch.clone()))?;
-            } else {
-                repo.pull_changes(None)?;
-            }
-        } else {
-            repo.clone_and_checkout(template_repo.url.as_str(), template_repo.branch.as_str())?;
+    /// Reports the command for opt-in telemetry. Reads the config as it is (with `-e` overrides),
+    /// without creating or migrating it; any problem just means nothing is reported.
+    #[cfg(feature = "telemetry")]
+    async fn report_usage(&self, command: &Command) -> Option<tokio::task::JoinHandle<()>> {
+        let mut config = Config::open(&self.args.config_file_path).await.ok()?;
+        for config_override in &self.args.config_overrides {
+            config
+                .override_data(config_override.key.as_str(), config_override.value.as_str())
+                .ok()?;
         }
-
-        Ok(repo)
+        crate::cli::telemetry::report_command(&config, &self.args.base_dir, command)
     }
 
-    pub async fn handle_command(mut self) -> anyhow::Result<()> {
-        let Some(command) = self.command.take() else {
+    /// Runs the parsed command. `cancel` is triggered on Ctrl-C so long waits can stop cleanly.
+    pub async fn handle_command(mut self, cancel: CancellationToken) -> anyhow::Result<()> {
+        color::init(self.args.color);
+        let Some(mut command) = self.command.take() else {
             return wizard::handle().await;
         };
+        command.set_verbosity(self.args.verbose);
+        #[cfg(feature = "telemetry")]
+        let report = self.report_usage(&command).await;
+
+        let result = self.run_command(command, cancel).await;
+        #[cfg(feature = "telemetry")]
+        crate::cli::telemetry::finish_report(report).await;
+        result
+    }
+
+    async fn run_command(&mut self, command: Command, cancel: CancellationToken) -> anyhow::Result<()> {
+        if let Command::Version = command {
+            if self.args.verbose > 0 {
+                println!("{BUILD_INFO}");
+            } else {
+                println!("tari {}", BUILD_INFO.version);
+            }
+            return Ok(());
+        }
 
         // Config command operates on project config, not CLI config
         if let Command::Config { command } = command {
@@ -387,7 +978,23 @@ impl Cli {
         }
 
         if let Command::Build { args } = command {
-            return build::handle(args).await;
+            return build::handle(args, &self.args.base_dir).await;
+        }
+
+        if let Command::Bundle { args } = command {
+            return bundle::handle(args, &self.args.base_dir).await;
+        }
+
+        if let Command::Inspect { args } = command {
+            return inspect::handle(args).await;
+        }
+
+        if let Command::Clean { args } = command {
+            return clean::handle(args, &self.args.base_dir).await;
+        }
+
+        if let Command::Rename { args } = command {
+            return rename::handle(args).await;
         }
 
         if let Command::Metadata {
@@ -397,28 +1004,57 @@ impl Cli {
             return template::inspect_metadata::handle(args).await;
         }
 
+        // keep stdout clean when `create` reports JSON
+        let progress = match &command {
+            Command::Create { args } => args.output_format.progress_stream(),
+            _ => spinners::Stream::Stdout,
+        };
+
         // init config and dirs
-        let config = loading!(
+        let mut config = loading!(
+            @stream progress,
             "Init configuration and directories",
             self.init_base_dir_and_config().await
         )?;
 
         // Commands that don't need template repository refresh
         match &command {
-            Command::Template { .. } | Command::Publish { .. } | Command::Metadata { .. } => {
+            Command::Template { .. }
+            | Command::Fee { .. }
+            | Command::Publish { .. }
+            | Command::Whoami { .. }
+            | Command::Metadata { .. } => {
                 let network_override = self.args.network;
                 // Move the key out rather than clone, so no extra plaintext copy lingers.
-                let api_key = self.args.api_key.take();
+                let api_key = match self.args.api_key_file.take() {
+                    Some(path) => Some(Hidden::hide(util::read_value_file(&path).await?)),
+                    None => self.args.api_key.take(),
+                };
                 let had_api_key = api_key.is_some();
                 let result = match command {
                     Command::Template { command } => match command {
                         TemplateCommand::Init { args } => template::init_metadata::handle(args).await,
                         TemplateCommand::Inspect { args } => template::inspect_metadata::handle(args).await,
-                        TemplateCommand::Publish { args } => {
-                            template::publish::handle(config, network_override, api_key, args).await
-                        },
+                        TemplateCommand::Publish { args } => template::publish::handle(
+                            config,
+                            &self.args.base_dir,
+                            network_override,
+                            api_key,
+                            *args,
+                            cancel,
+                        )
+                        .await
+                        .map(|_| ()),
+                    },
+                    Command::Fee { args } => {
+                        fee::handle(config, &self.args.base_dir, network_override, api_key, args, cancel).await
+                    },
+                    Command::Publish { args } => {
+                        publish::handle(config, &self.args.base_dir, network_override, api_key, *args, cancel).await
+                    },
+                    Command::Whoami { args } => {
+                        whoami::handle(config, &self.args.base_dir, network_override, api_key, args, cancel).await
                     },
-                    Command::Publish { args } => publish::handle(config, network_override, api_key, args).await,
                     Command::Metadata { command } => match command {
                         MetadataCommand::Publish { args } => {
                             metadata::publish::handle(config, network_override, api_key, args).await
@@ -433,15 +1069,52 @@ impl Cli {
             _ => {},
         }
 
-        // Refresh template repository (only needed for `create`)
+        if let Command::Create { args } = &command
+            && let Some(reference) = &args.template_repo_ref
+        {
+            config.template_repository.reference = reference.clone();
+        }
+
+        // `create --from-git/--from-path` doesn't use the template repository
+        if matches!(&command, Command::Create { args } if args.uses_ad_hoc_template()) {
+            let Command::Create { args } = command else {
+                unreachable!()
+            };
+            return run_create(config, PathBuf::new(), args).await;
+        }
+
+        // Refresh template repository (only needed for `create`, `upgrade` and `templates`)
         let template_repo = loading!(
+            @stream progress,
             "Refresh templates repository",
-            self.refresh_template_repository(&config.template_repository).await
+            refresh_template_repository(
+                &self.args.base_dir,
+                &config.template_repository,
+                self.args.verbose,
+                Duration::from_secs(self.args.git_timeout),
+            )
+            .await
         )?;
+        if let Command::Create { args } = &command
+            && let Some(expected) = &args.expect_tree
+        {
+            template_repo.verify_tree(expected)?;
+        }
 
         match command {
-            Command::Create { args } => create::handle(config, template_repo.local_folder().clone(), args).await,
+            Command::Create { args } => run_create(config, template_repo.local_folder().clone(), args).await,
+            Command::Templates { command } => templates::handle(config, &template_repo, command).await,
+            Command::Upgrade { args } => upgrade::handle(config, template_repo.local_folder().clone(), args).await,
             _ => unreachable!(),
         }
     }
 }
+
+async fn run_create(config: Config, template_repo_dir: PathBuf, args: CreateArgs) -> anyhow::Result<()> {
+    let output_format = args.output_format;
+    let crate_dir = create::handle(config, template_repo_dir, args).await?;
+    if !output_format.is_json() {
+        create::print_next_steps(&crate_dir);
+    }
+    Ok(())
+}
@@ -1,36 +1,206 @@
 // Copyright 2024 The Tari Project
 // SPDX-License-Identifier: BSD-3-Clause
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
 
+use anyhow::anyhow;
 use clap::Parser;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 
-use crate::cli::commands::publish::{build_template, find_metadata_cbor};
+use crate::cli::build_cache::BuildCache;
+use crate::cli::commands::publish::{
+    CargoBuildArgs, build_project, build_template, find_metadata_cbor, load_project_config, print_build_log,
+    template_crate_name, use_cargo_opts,
+};
 use crate::cli::util;
+use crate::loading;
 
 #[derive(Clone, Parser, Debug)]
 pub struct BuildArgs {
-    /// Path to the template crate directory.
-    /// Defaults to the current directory.
+    /// Path(s) to the template crate directories.
+    /// Defaults to the current directory. When several are given they are built concurrently.
     #[arg(default_value = ".")]
-    pub path: PathBuf,
+    pub paths: Vec<PathBuf>,
+
+    /// Maximum number of template crates built at the same time when several paths are given.
+    #[arg(short = 'j', long, default_value_t = 2)]
+    pub jobs: usize,
 
     /// Skip the size-optimizing release profile overrides passed to `cargo build`.
     /// By default the template is compiled with size optimizations.
     #[arg(long, default_value_t = false)]
     pub no_cargo_opts: bool,
+
+    /// Run a wasm-opt size optimization pass over the built binary (in place) and report the
+    /// size before and after. `tari publish` always applies this pass before publishing.
+    /// Also enabled by `wasm-opt = true` in the project's `[build]` section.
+    #[arg(long, default_value_t = false)]
+    pub optimize: bool,
+
+    /// Verbosity level, set from the global `--verbose` flag.
+    #[arg(skip)]
+    pub verbose: u8,
+
+    #[clap(flatten)]
+    pub cargo: CargoBuildArgs,
+}
+
+pub async fn handle(args: BuildArgs, base_dir: &Path) -> anyhow::Result<()> {
+    args.cargo.ensure_single_build_output(args.paths.len())?;
+    match args.paths.as_slice() {
+        [path] => build_single(path, &args, &BuildCache::new(base_dir))
+            .await
+            .inspect_err(|e| {
+                if args.verbose > 0 {
+                    print_build_log(e)
+                }
+            }),
+        _ => build_many(args).await,
+    }
 }
 
-pub async fn handle(args: BuildArgs) -> anyhow::Result<()> {
-    let wasm_path = build_template(&args.path, !args.no_cargo_opts).await?;
+/// How to build one crate: the flags, with the defaults from its project's `[build]` section.
+struct CrateBuild {
+    optimize: bool,
+    wasm_opt: bool,
+    cargo: CargoBuildArgs,
+}
+
+impl CrateBuild {
+    async fn resolve(path: &Path, args: &BuildArgs) -> anyhow::Result<Self> {
+        let project_config = load_project_config(path).await?;
+        let mut cargo = args.cargo.clone();
+        cargo.apply_project_defaults(&project_config);
+        Ok(Self {
+            optimize: use_cargo_opts(args.no_cargo_opts, &project_config),
+            wasm_opt: args.optimize || project_config.build_settings().is_some_and(|b| b.wasm_opt),
+            cargo,
+        })
+    }
+}
+
+async fn build_single(path: &Path, args: &BuildArgs, cache: &BuildCache) -> anyhow::Result<()> {
+    let CrateBuild {
+        optimize,
+        wasm_opt,
+        cargo,
+    } = CrateBuild::resolve(path, args).await?;
+    let wasm_path = build_template(path, optimize, &cargo, Some(cache)).await?;
+    if wasm_opt {
+        let (before, after) = loading!(
+            "Optimizing WASM binary with wasm-opt",
+            optimize_binary(&wasm_path).await
+        )?;
+        report_optimization(before, after);
+    }
     let size = tokio::fs::metadata(&wasm_path).await?.len() as usize;
 
     println!("✅ WASM binary: {} ({})", wasm_path.display(), util::human_bytes(size));
 
-    match find_metadata_cbor(&args.path).await {
+    match find_metadata_cbor(path, cargo.target()).await {
         Ok(path) => println!("📄 Metadata:    {}", path.display()),
         Err(e) => println!("📄 Metadata:    none ({e})"),
     }
 
     Ok(())
 }
+
+/// Builds several template crates with at most `jobs` `cargo build` invocations in flight.
+/// Each build's output is buffered and reported once that crate finishes.
+async fn build_many(args: BuildArgs) -> anyhow::Result<()> {
+    let started = Instant::now();
+    let total = args.paths.len();
+    let jobs = args.jobs.max(1);
+    let verbose = args.verbose > 0;
+    let args = Arc::new(args);
+    println!("🔨 Building {total} template crates ({jobs} at a time)...");
+
+    let semaphore = Arc::new(Semaphore::new(jobs));
+    let mut builds = JoinSet::new();
+    for path in args.paths.clone() {
+        let semaphore = semaphore.clone();
+        let args = args.clone();
+        builds.spawn(async move {
+            let _permit = semaphore
+                .acquire_owned()
+                .await
+                .expect("build semaphore is never closed");
+            let result = async {
+                let name = template_crate_name(&path)?;
+                let build = CrateBuild::resolve(&path, &args).await?;
+                let wasm_path = build_project(&path, &name, build.optimize, &build.cargo).await?;
+                if build.wasm_opt {
+                    optimize_binary(&wasm_path).await?;
+                }
+                anyhow::Ok(wasm_path)
+            }
+            .await;
+            (path, result)
+        });
+    }
+
+    let mut failed = 0;
+    while let Some(joined) = builds.join_next().await {
+        let (path, result) = joined?;
+        match result {
+            Ok(wasm_path) => {
+                let size = tokio::fs::metadata(&wasm_path).await?.len() as usize;
+                println!(
+                    "✅ {}: {} ({})",
+                    path.display(),
+                    wasm_path.display(),
+                    util::human_bytes(size)
+                );
+            },
+            Err(error) => {
+                failed += 1;
+                println!("❌ {}: {error:#}", path.display());
+                if verbose {
+                    print_build_log(&error);
+                }
+            },
+        }
+    }
+
+    println!(
+        "⏱️  Built {} of {total} template crates in {:.1}s",
+        total - failed,
+        started.elapsed().as_secs_f64()
+    );
+
+    if failed > 0 {
+        return Err(anyhow!("{failed} of {total} template builds failed"));
+    }
+    Ok(())
+}
+
+/// Optimizes the WASM binary at `wasm_path` in place, returning its size before and after.
+#[cfg(feature = "wasm-opt")]
+async fn optimize_binary(wasm_path: &Path) -> anyhow::Result<(usize, usize)> {
+    let original = tokio::fs::read(wasm_path).await?;
+    let optimized = tari_ootle_publish_lib::wasm_opt::optimize_wasm_template(&original).await?;
+    tokio::fs::write(wasm_path, &optimized).await?;
+    Ok((original.len(), optimized.len()))
+}
+
+#[cfg(not(feature = "wasm-opt"))]
+async fn optimize_binary(wasm_path: &Path) -> anyhow::Result<(usize, usize)> {
+    println!("⚠️  This CLI was built without the `wasm-opt` feature, skipping optimization");
+    let size = tokio::fs::metadata(wasm_path).await?.len() as usize;
+    Ok((size, size))
+}
+
+fn report_optimization(before: usize, after: usize) {
+    if before == after {
+        return;
+    }
+    println!(
+        "🗜️  wasm-opt: {} → {} ({} saved)",
+        util::human_bytes(before),
+        util::human_bytes(after),
+        util::human_bytes(before.saturating_sub(after))
+    );
+}
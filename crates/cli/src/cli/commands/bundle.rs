@@ -0,0 +1,215 @@
+// Copyright 2024 The Tari Project
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, anyhow};
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use tari_ootle_template_metadata::MetadataHash;
+
+use crate::cli::build_cache::BuildCache;
+use crate::cli::commands::publish::{
+    CargoBuildArgs, build_template, decode_metadata_cbor, find_metadata_cbor, load_project_config, template_crate_name,
+    use_cargo_opts,
+};
+
+/// Name of the manifest stored at the root of a bundle archive.
+pub const BUNDLE_MANIFEST_FILE_NAME: &str = "bundle.toml";
+
+#[derive(Clone, Parser, Debug)]
+pub struct BundleArgs {
+    /// Path(s) to the template crate directories to include.
+    /// Defaults to the current directory.
+    #[arg(default_value = ".")]
+    pub paths: Vec<PathBuf>,
+
+    /// Path of the tar archive to write.
+    #[arg(short = 'o', long, default_value = "templates.tar")]
+    pub out: PathBuf,
+
+    /// Skip the size-optimizing release profile overrides passed to `cargo build`.
+    /// By default the templates are compiled with size optimizations.
+    #[arg(long, default_value_t = false)]
+    pub no_cargo_opts: bool,
+
+    #[clap(flatten)]
+    pub cargo: CargoBuildArgs,
+}
+
+/// Lists the templates in a bundle archive.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BundleManifest {
+    #[serde(default, rename = "template")]
+    pub templates: Vec<BundledTemplate>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BundledTemplate {
+    /// Crate name of the template.
+    pub name: String,
+    /// File name of the WASM binary inside the archive.
+    pub file: String,
+    /// Hash of the template's metadata, published with the template. The crate isn't available
+    /// where the bundle is published, so it is recorded here at bundling time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata_hash: Option<MetadataHash>,
+}
+
+/// Builds the given template crates and packs their WASM binaries into a tar archive, so they
+/// can be published from another machine with `tari publish --bundle`.
+pub async fn handle(args: BundleArgs, base_dir: &Path) -> anyhow::Result<()> {
+    args.cargo.ensure_single_build_output(args.paths.len())?;
+    let cache = BuildCache::new(base_dir);
+    let mut manifest = BundleManifest::default();
+    let mut binaries = Vec::with_capacity(args.paths.len());
+    for path in &args.paths {
+        let name = template_crate_name(path)?;
+        let file = format!("{name}.wasm");
+        if manifest.templates.iter().any(|t| t.file == file) {
+            return Err(anyhow!("Template '{name}' is included more than once"));
+        }
+        let project_config = load_project_config(path).await?;
+        let mut cargo = args.cargo.clone();
+        cargo.apply_project_defaults(&project_config);
+        let optimize = use_cargo_opts(args.no_cargo_opts, &project_config);
+        let wasm_path = build_template(path, optimize, &cargo, Some(&cache)).await?;
+        let metadata_hash = read_metadata_hash(path, cargo.target()).await;
+        manifest.templates.push(BundledTemplate {
+            name,
+            file: file.clone(),
+            metadata_hash,
+        });
+        binaries.push((file, wasm_path));
+    }
+
+    let count = manifest.templates.len();
+    let out = args.out.clone();
+    tokio::task::spawn_blocking(move || write_bundle(&out, &manifest, &binaries)).await??;
+    println!("📦 Bundled {count} template(s) into {}", args.out.display());
+
+    Ok(())
+}
+
+/// The metadata hash from the crate's last build, if it produced metadata.
+async fn read_metadata_hash(crate_dir: &Path, target: &str) -> Option<MetadataHash> {
+    let hash = async {
+        let cbor_path = find_metadata_cbor(crate_dir, target).await?;
+        let bytes = tokio::fs::read(&cbor_path).await.context("reading metadata CBOR")?;
+        decode_metadata_cbor(&bytes)?.hash().context("computing metadata hash")
+    };
+    match hash.await {
+        Ok(hash) => Some(hash),
+        Err(e) => {
+            println!(
+                "⚠️  No metadata for {} ({e}), bundling without metadata hash",
+                crate_dir.display()
+            );
+            None
+        },
+    }
+}
+
+fn write_bundle(out: &Path, manifest: &BundleManifest, binaries: &[(String, PathBuf)]) -> anyhow::Result<()> {
+    let file = File::create(out).with_context(|| format!("creating {}", out.display()))?;
+    let mut builder = tar::Builder::new(file);
+
+    let manifest = toml::to_string(manifest)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, BUNDLE_MANIFEST_FILE_NAME, manifest.as_bytes())?;
+
+    for (file, path) in binaries {
+        builder
+            .append_path_with_name(path, file)
+            .with_context(|| format!("adding {} to bundle", path.display()))?;
+    }
+    builder.finish()?;
+    Ok(())
+}
+
+/// Unpacks a bundle into `dest` and returns each template's manifest entry with the path of its
+/// binary.
+pub fn extract_bundle(bundle: &Path, dest: &Path) -> anyhow::Result<Vec<(BundledTemplate, PathBuf)>> {
+    let file = File::open(bundle).with_context(|| format!("opening bundle {}", bundle.display()))?;
+    tar::Archive::new(file)
+        .unpack(dest)
+        .with_context(|| format!("unpacking bundle {}", bundle.display()))?;
+
+    let manifest_path = dest.join(BUNDLE_MANIFEST_FILE_NAME);
+    let manifest = std::fs::read_to_string(&manifest_path)
+        .with_context(|| format!("bundle {} has no {BUNDLE_MANIFEST_FILE_NAME}", bundle.display()))?;
+    let manifest: BundleManifest = toml::from_str(&manifest).context("parsing bundle manifest")?;
+
+    manifest
+        .templates
+        .into_iter()
+        .map(|template| {
+            // Only plain file names are allowed, so entries cannot point outside `dest`.
+            if Path::new(&template.file).file_name().and_then(|n| n.to_str()) != Some(template.file.as_str()) {
+                return Err(anyhow!("Invalid file name in bundle manifest: {}", template.file));
+            }
+            let path = dest.join(&template.file);
+            if !path.is_file() {
+                return Err(anyhow!(
+                    "Bundle is missing {} for template '{}'",
+                    template.file,
+                    template.name
+                ));
+            }
+            Ok((template, path))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn bundle_roundtrips_binaries_and_manifest() {
+        let temp_dir = TempDir::new().unwrap();
+        let wasm = temp_dir.path().join("counter.wasm");
+        std::fs::write(&wasm, b"\0asm fake").unwrap();
+        let manifest = BundleManifest {
+            templates: vec![BundledTemplate {
+                name: "counter".to_string(),
+                file: "counter.wasm".to_string(),
+                metadata_hash: None,
+            }],
+        };
+        let bundle = temp_dir.path().join("templates.tar");
+        write_bundle(&bundle, &manifest, &[("counter.wasm".to_string(), wasm)]).unwrap();
+
+        let dest = temp_dir.path().join("out");
+        let templates = extract_bundle(&bundle, &dest).unwrap();
+        assert_eq!(
+            templates,
+            vec![(manifest.templates[0].clone(), dest.join("counter.wasm"))]
+        );
+        assert_eq!(std::fs::read(dest.join("counter.wasm")).unwrap(), b"\0asm fake");
+    }
+
+    #[test]
+    fn extract_rejects_manifest_entries_outside_the_bundle() {
+        let temp_dir = TempDir::new().unwrap();
+        let manifest = BundleManifest {
+            templates: vec![BundledTemplate {
+                name: "evil".to_string(),
+                file: "../evil.wasm".to_string(),
+                metadata_hash: None,
+            }],
+        };
+        let bundle = temp_dir.path().join("templates.tar");
+        write_bundle(&bundle, &manifest, &[]).unwrap();
+
+        let err = extract_bundle(&bundle, &temp_dir.path().join("out")).unwrap_err();
+        assert!(err.to_string().contains("Invalid file name"), "got: {err}");
+    }
+}
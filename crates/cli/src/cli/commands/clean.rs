@@ -0,0 +1,196 @@
+// Copyright 2024 The Tari Project
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, anyhow};
+use clap::Parser;
+use dialoguer::Confirm;
+use tokio::fs;
+
+use crate::cli::account_cache::AccountCache;
+use crate::cli::build_cache::BuildCache;
+use crate::cli::command::TEMPLATE_REPOS_FOLDER_NAME;
+use crate::cli::util;
+
+#[derive(Clone, Parser, Debug)]
+pub struct CleanArgs {
+    /// List what would be removed and how much space it would free, without removing anything.
+    #[arg(long, default_value_t = false)]
+    pub dry_run: bool,
+
+    /// Also remove the last-used account cache and the telemetry notice marker, not only the
+    /// template repository and build caches. Asks for confirmation unless --yes is given. Other
+    /// files in the base directory, such as the CLI config, are never touched.
+    #[arg(long, default_value_t = false)]
+    pub all: bool,
+
+    /// Don't ask for confirmation with --all.
+    #[arg(short = 'y', long, default_value_t = false)]
+    pub yes: bool,
+}
+
+/// Removes cached template repositories and builds from the base directory. The next `create`
+/// clones the template repository afresh and the next build recompiles.
+pub async fn handle(args: CleanArgs, base_dir: &Path) -> anyhow::Result<()> {
+    let targets = clean_targets(base_dir, args.all).await?;
+    if targets.is_empty() {
+        println!("✨ Nothing to clean in {}", base_dir.display());
+        return Ok(());
+    }
+
+    if args.all && !args.dry_run && !args.yes {
+        println!("🗑️  About to remove:");
+        for path in &targets {
+            println!("   {}", path.display());
+        }
+        let proceed = Confirm::new().with_prompt("Remove these?").default(false).interact()?;
+        if !proceed {
+            return Err(anyhow!("Clean aborted"));
+        }
+    }
+
+    let mut total = 0;
+    for path in &targets {
+        let size = path_size(path).await?;
+        total += size;
+        if args.dry_run {
+            println!("🗑️  Would remove {} ({})", path.display(), util::human_bytes(size));
+        } else {
+            remove_path(path).await?;
+            println!("🗑️  Removed {} ({})", path.display(), util::human_bytes(size));
+        }
+    }
+
+    if args.dry_run {
+        println!("ℹ️  Dry run: {} would be freed", util::human_bytes(total));
+    } else {
+        println!("✅ Freed {}", util::human_bytes(total));
+    }
+    Ok(())
+}
+
+/// The existing paths under `base_dir` to remove, sorted: the template repository and build
+/// caches, plus with `all` the CLI's other files there. Only paths the CLI itself writes are
+/// listed, since the base directory can be set to any directory.
+async fn clean_targets(base_dir: &Path, all: bool) -> anyhow::Result<Vec<PathBuf>> {
+    let mut candidates = vec![
+        base_dir.join(TEMPLATE_REPOS_FOLDER_NAME),
+        BuildCache::new(base_dir).dir().to_path_buf(),
+    ];
+    if all {
+        candidates.push(AccountCache::path(base_dir));
+        #[cfg(feature = "telemetry")]
+        candidates.push(crate::cli::telemetry::notice_marker_path(base_dir));
+    }
+
+    let mut targets = vec![];
+    for path in candidates {
+        if fs::try_exists(&path).await? {
+            targets.push(path);
+        }
+    }
+    targets.sort();
+    Ok(targets)
+}
+
+/// Total size of the files at `path`, without following symlinks.
+async fn path_size(path: &Path) -> anyhow::Result<usize> {
+    let mut total = 0;
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(path) = pending.pop() {
+        let metadata = fs::symlink_metadata(&path)
+            .await
+            .with_context(|| format!("reading {}", path.display()))?;
+        if metadata.is_dir() {
+            let mut entries = fs::read_dir(&path)
+                .await
+                .with_context(|| format!("reading {}", path.display()))?;
+            while let Some(entry) = entries.next_entry().await? {
+                pending.push(entry.path());
+            }
+        } else {
+            total += metadata.len() as usize;
+        }
+    }
+    Ok(total)
+}
+
+async fn remove_path(path: &Path) -> anyhow::Result<()> {
+    let metadata = fs::symlink_metadata(path).await?;
+    if metadata.is_dir() {
+        fs::remove_dir_all(path).await
+    } else {
+        fs::remove_file(path).await
+    }
+    .with_context(|| format!("removing {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    async fn populated_base_dir() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+        fs::create_dir_all(base.join(TEMPLATE_REPOS_FOLDER_NAME).join("repo"))
+            .await
+            .unwrap();
+        fs::write(base.join(TEMPLATE_REPOS_FOLDER_NAME).join("repo/README.md"), "hello")
+            .await
+            .unwrap();
+        fs::create_dir_all(BuildCache::new(base).dir().join("abc"))
+            .await
+            .unwrap();
+        fs::write(BuildCache::new(base).dir().join("abc/template.wasm"), [0u8; 100])
+            .await
+            .unwrap();
+        fs::write(base.join("last_used_accounts.toml"), "").await.unwrap();
+        fs::write(base.join("tari.config.toml"), "").await.unwrap();
+        fs::write(base.join("tari.config.toml.bak"), "").await.unwrap();
+        temp_dir
+    }
+
+    #[tokio::test]
+    async fn default_clean_only_targets_caches() {
+        let temp_dir = populated_base_dir().await;
+        let base = temp_dir.path();
+        let targets = clean_targets(base, false).await.unwrap();
+        assert_eq!(
+            targets,
+            vec![
+                BuildCache::new(base).dir().to_path_buf(),
+                base.join(TEMPLATE_REPOS_FOLDER_NAME)
+            ]
+        );
+        let mut size = 0;
+        for path in &targets {
+            size += path_size(path).await.unwrap();
+        }
+        assert_eq!(size, 105);
+    }
+
+    #[tokio::test]
+    async fn clean_all_only_targets_cli_files() {
+        let temp_dir = populated_base_dir().await;
+        let base = temp_dir.path();
+        fs::write(base.join("notes.txt"), "mine").await.unwrap();
+        let targets = clean_targets(base, true).await.unwrap();
+        assert_eq!(
+            targets,
+            vec![
+                BuildCache::new(base).dir().to_path_buf(),
+                AccountCache::path(base),
+                base.join(TEMPLATE_REPOS_FOLDER_NAME)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_base_dir_has_nothing_to_clean() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path().join("missing");
+        assert!(clean_targets(&base, true).await.unwrap().is_empty());
+    }
+}
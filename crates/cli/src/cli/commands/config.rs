@@ -1,12 +1,13 @@
 // Copyright 2024 The Tari Project
 // SPDX-License-Identifier: BSD-3-Clause
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, anyhow};
 use clap::Subcommand;
 use tokio::fs;
 
+use crate::git::repository::ceiling_dirs;
 use crate::project::CONFIG_FILE_NAME;
 
 #[derive(Clone, Subcommand)]
@@ -122,16 +123,52 @@ fn find_existing_config() -> anyhow::Result<PathBuf> {
     ))
 }
 
+/// Finds the root of the git repository containing the current directory, see [`find_repo_root_from`].
 pub fn find_repo_root() -> Option<PathBuf> {
-    let mut dir = std::env::current_dir().ok()?;
+    find_repo_root_from(&std::env::current_dir().ok()?, &ceiling_dirs())
+}
+
+/// Walks up from `start` to the nearest directory with a valid `.git` (see [`resolve_git_dir`]).
+/// Like git, it never moves up into one of the `ceilings` or onto another filesystem.
+pub fn find_repo_root_from(start: &Path, ceilings: &[PathBuf]) -> Option<PathBuf> {
+    let mut dir = start.to_path_buf();
     loop {
-        if dir.join(".git").exists() {
+        if resolve_git_dir(&dir).is_some() {
             return Some(dir);
         }
-        if !dir.pop() {
+        let parent = dir.parent()?.to_path_buf();
+        if ceilings.contains(&parent) || !same_filesystem(&dir, &parent) {
             return None;
         }
+        dir = parent;
+    }
+}
+
+/// Returns the git directory of the working tree at `dir`. `.git` is either that directory, or a
+/// file (in worktrees and submodules) containing `gitdir: <path>`, relative to `dir` or absolute.
+/// A `.git` file pointing at a missing directory is ignored.
+pub fn resolve_git_dir(dir: &Path) -> Option<PathBuf> {
+    let dot_git = dir.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
     }
+    let content = std::fs::read_to_string(&dot_git).ok()?;
+    let git_dir = dir.join(content.trim().strip_prefix("gitdir:")?.trim());
+    git_dir.is_dir().then_some(git_dir)
+}
+
+#[cfg(unix)]
+fn same_filesystem(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (std::fs::metadata(a), std::fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev(),
+        _ => true,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_filesystem(_a: &Path, _b: &Path) -> bool {
+    true
 }
 
 /// Set an arbitrary dotted-path key in a TOML document.
@@ -199,3 +236,55 @@ fn get_dotted_key(doc: &toml_edit::DocumentMut, key: &str) -> anyhow::Result<Str
         None => Err(anyhow!("Key '{key}' not found")),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn repo_root_is_the_nearest_git_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let outer = temp_dir.path().join("outer");
+        let inner = outer.join("inner");
+        let start = inner.join("src");
+        std::fs::create_dir_all(outer.join(".git")).unwrap();
+        std::fs::create_dir_all(inner.join(".git")).unwrap();
+        std::fs::create_dir_all(&start).unwrap();
+
+        assert_eq!(find_repo_root_from(&start, &[]), Some(inner));
+    }
+
+    #[test]
+    fn repo_root_resolves_worktree_git_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let main = temp_dir.path().join("main");
+        let worktree = temp_dir.path().join("feature");
+        let worktree_git_dir = main.join(".git").join("worktrees").join("feature");
+        std::fs::create_dir_all(&worktree_git_dir).unwrap();
+        std::fs::create_dir_all(worktree.join("src")).unwrap();
+        std::fs::write(worktree.join(".git"), "gitdir: ../main/.git/worktrees/feature\n").unwrap();
+
+        assert_eq!(find_repo_root_from(&worktree.join("src"), &[]), Some(worktree.clone()));
+        assert_eq!(
+            resolve_git_dir(&worktree),
+            Some(worktree.join("../main/.git/worktrees/feature"))
+        );
+
+        // a dangling gitlink is not a repository
+        std::fs::remove_dir_all(&worktree_git_dir).unwrap();
+        assert_eq!(resolve_git_dir(&worktree), None);
+    }
+
+    #[test]
+    fn repo_root_search_stops_at_ceiling() {
+        let temp_dir = TempDir::new().unwrap();
+        let home = temp_dir.path().join("home");
+        let start = home.join("projects").join("token");
+        std::fs::create_dir_all(home.join(".git")).unwrap();
+        std::fs::create_dir_all(&start).unwrap();
+
+        assert_eq!(find_repo_root_from(&start, &[]), Some(home.clone()));
+        assert_eq!(find_repo_root_from(&start, &[home]), None);
+    }
+}
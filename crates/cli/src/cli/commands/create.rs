@@ -1,20 +1,30 @@
 // Copyright 2024 The Tari Project
 // SPDX-License-Identifier: BSD-3-Clause
 
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
-use anyhow::anyhow;
+use anyhow::{Context, anyhow};
 use cargo_generate::{GenerateArgs as CargoGenerateArgs, TemplatePath};
+use cargo_toml::Manifest;
 use clap::Parser;
-use dialoguer::Input;
+use dialoguer::{Confirm, Input};
+use serde::Serialize;
 use thiserror::Error;
 
+use crate::cli::commands::publish::builds_cdylib;
 use crate::cli::commands::template::init_metadata;
 use crate::{
-    cli::{command::project_name_parser, config::Config, util},
+    cli::{
+        command::{project_name_parser, tree_hash_parser},
+        commands::config::set_dotted_key,
+        config::Config,
+        util::{self, OutputFormat, Warnings},
+    },
     git::repository::GitRepository,
     loading,
-    templates::Collector,
+    project::{CONFIG_FILE_NAME, ProjectConfig, TemplateSource},
+    templates::{Collector, Template, TemplateVariable, TemplateVariableType},
 };
 
 #[derive(Clone, Parser, Debug)]
@@ -29,14 +39,48 @@ pub struct CreateArgs {
     #[arg(short = 't', long)]
     pub template: Option<String>,
 
-    /// Directory where the new crate will be created.
+    /// Generate from the template in this git repository instead of one from the configured
+    /// template repository.
+    #[arg(long, value_name = "URL", conflicts_with_all = ["template", "from_path", "expect_tree"])]
+    pub from_git: Option<String>,
+
+    /// Folder of the --from-git repository that holds the template.
+    #[arg(long, value_name = "PATH", requires = "from_git")]
+    pub subfolder: Option<String>,
+
+    /// Generate from the template in this local directory instead of one from the configured
+    /// template repository.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["template", "expect_tree"])]
+    pub from_path: Option<PathBuf>,
+
+    /// Branch, tag or commit of the template repository to use for this run
+    /// (overrides `template-repository.reference` in the CLI config).
+    #[arg(long, value_name = "REF")]
+    pub template_repo_ref: Option<String>,
+
+    /// Fail unless the checked out template repository has this tree hash (see `tari templates info`).
+    /// The full hash, or a prefix of at least 7 hex digits.
+    #[arg(long, value_name = "HASH", value_parser = tree_hash_parser)]
+    pub expect_tree: Option<String>,
+
+    /// Value for a variable declared in the template's `[variables]` section (KEY=VALUE).
+    /// Can be repeated. Declared variables that are not set here are prompted for.
+    #[arg(short = 'd', long = "define", value_name = "KEY=VALUE", value_parser = template_define_parser)]
+    pub defines: Vec<(String, String)>,
+
+    /// Directory where the new crate will be created. Created if it does not exist.
     #[arg(long, short = 'o', value_name = "PATH", default_value = crate::cli::command::default_output_dir().into_os_string())]
     pub output: PathBuf,
 
-    /// Skip git init.
-    #[arg(long, default_value_t = false)]
+    /// Skip git init. Same as `--init-git=false`.
+    #[arg(long, default_value_t = false, conflicts_with = "init_git")]
     pub skip_init: bool,
 
+    /// Whether to run `git init` in the new crate.
+    /// Defaults to true, unless the output directory is already inside a git repository.
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub init_git: Option<bool>,
+
     /// Skip automatic template metadata initialisation.
     /// By default, new templates are set up with build.rs and
     /// [package.metadata.tari-template] for metadata generation.
@@ -44,80 +88,1013 @@ pub struct CreateArgs {
     #[arg(long, default_value_t = false)]
     pub skip_metadata: bool,
 
-    /// Enables more verbose output.
-    #[arg(long, short = 'v')]
-    pub verbose: bool,
+    /// Create the crate even if the output directory is inside another Tari project or a Cargo
+    /// workspace.
+    #[arg(long, default_value_t = false)]
+    pub force: bool,
+
+    /// Regenerate over an existing crate of the same name. Files the template writes are
+    /// overwritten (each changed file is reported); other files are kept.
+    #[arg(long, alias = "overwrite", default_value_t = false)]
+    pub replace: bool,
+
+    /// Generate the template's files directly into the output directory instead of a new
+    /// `<NAME>` directory inside it, like `cargo generate --init`. The directory must be empty
+    /// (apart from `.git`); the crate name defaults to the directory's name.
+    #[arg(long, default_value_t = false, conflicts_with = "replace")]
+    pub init: bool,
+
+    /// Verbosity level, set from the global `--verbose` flag.
+    #[arg(skip)]
+    pub verbose: u8,
+
+    /// Output format. `json` prints a single JSON report of what was created.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub output_format: OutputFormat,
+}
+
+/// What `create` produced, reported with `--output-format json`.
+#[derive(Debug, Serialize)]
+struct CreateReport {
+    crate_path: PathBuf,
+    template: String,
+    metadata_initialized: bool,
+    git_initialized: bool,
+    /// With `--replace`, files of the existing crate whose contents changed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    overwritten_files: Vec<PathBuf>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+}
+
+impl CreateArgs {
+    /// Whether the template comes from `--from-git` or `--from-path` rather than the template
+    /// repository.
+    pub fn uses_ad_hoc_template(&self) -> bool {
+        self.from_git.is_some() || self.from_path.is_some()
+    }
+}
+
+pub fn template_define_parser(define: &str) -> Result<(String, String), String> {
+    let Some((key, value)) = define.split_once('=') else {
+        return Err(String::from("Invalid template variable! Expected KEY=VALUE."));
+    };
+    if key.trim().is_empty() {
+        return Err(String::from("Template variable name cannot be empty!"));
+    }
+    Ok((key.trim().to_string(), value.to_string()))
 }
 
 #[derive(Error, Debug)]
 pub enum CreateHandlerError {
-    #[error("Template not found by name: {0}. Possible values: {1:?}")]
-    TemplateNotFound(String, Vec<String>),
+    #[error("Template not found by name: {0}.{hint} Possible values: {1:?}", hint = did_you_mean(.2))]
+    TemplateNotFound(String, Vec<String>, Vec<String>),
+    #[error(
+        "Template folder `{folder}` not found in the template repository {url} (at {reference}); looked in {}. \
+         The `template-repository.folder` setting may be wrong for this repository or reference.{}",
+        .path.display(),
+        available_folders(.available)
+    )]
+    TemplateFolderNotFound {
+        folder: String,
+        url: String,
+        reference: String,
+        path: PathBuf,
+        available: Vec<String>,
+    },
+}
+
+fn available_folders(folders: &[String]) -> String {
+    if folders.is_empty() {
+        String::new()
+    } else {
+        format!(
+            " Folders in the repository: {}. Set it with `-e template_repository.folder=<FOLDER>`.",
+            folders.join(", ")
+        )
+    }
 }
 
-pub async fn handle(config: Config, template_repo_dir: PathBuf, mut args: CreateArgs) -> anyhow::Result<()> {
+/// The folder holding the starter templates in the local clone at `template_repo_dir`. Fails when
+/// it is missing, which would otherwise look like a repository without templates.
+pub fn templates_dir(config: &Config, template_repo_dir: &Path) -> Result<PathBuf, CreateHandlerError> {
+    let repository = &config.template_repository;
+    let path = template_repo_dir.join(&repository.folder);
+    if path.is_dir() {
+        return Ok(path);
+    }
+    let mut available: Vec<String> = std::fs::read_dir(template_repo_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+        .filter(|name| !name.starts_with('.'))
+        .collect();
+    available.sort();
+    Err(CreateHandlerError::TemplateFolderNotFound {
+        folder: repository.folder.clone(),
+        url: repository.url.clone(),
+        reference: repository.reference.clone(),
+        path,
+        available,
+    })
+}
+
+fn did_you_mean(suggestions: &[String]) -> String {
+    if suggestions.is_empty() {
+        String::new()
+    } else {
+        format!(" Did you mean: {}?", suggestions.join(", "))
+    }
+}
+
+/// Creates the template crate and returns its directory.
+pub async fn handle(config: Config, template_repo_dir: PathBuf, mut args: CreateArgs) -> anyhow::Result<PathBuf> {
+    let mut warnings = Warnings::new(args.output_format).on_stderr();
+    if let Some(enclosing) = find_enclosing_project(&args.output) {
+        if !args.force {
+            return Err(anyhow!(
+                "{} is inside {enclosing}. Creating a crate there nests it in another project, which cargo rejects \
+                 unless the crate is added to that workspace. Choose another --output, or pass --force to create it anyway.",
+                args.output.display()
+            ));
+        }
+        warnings.warn(format!("Creating a crate inside {enclosing}"));
+    }
+
     let name = match args.name.take() {
         Some(name) => name,
+        None if args.init => init_crate_name(&args.output)?,
         None => {
             let raw: String = Input::new().with_prompt("Template crate name").interact_text()?;
             project_name_parser(&raw).map_err(|e| anyhow!(e))?
         },
     };
 
-    let templates = loading!(
-        "Collecting available templates",
-        Collector::new(template_repo_dir.join(&config.template_repository.folder))
-            .collect()
-            .await
-    )?;
+    let progress = args.output_format.progress_stream();
+    let (template_path, defines, source) = match ad_hoc_template(&args)? {
+        Some((template_path, source)) => {
+            // cargo-generate prompts for placeholders that are not defined
+            let defines = args
+                .defines
+                .iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect();
+            (template_path, defines, source)
+        },
+        None => {
+            let templates_folder = templates_dir(&config, &template_repo_dir)?;
+            let templates = loading!(
+                @stream progress,
+                "Collecting available templates",
+                Collector::new(templates_folder).collect().await
+            )?;
 
-    let template = match &args.template {
-        Some(template_id) => templates
-            .iter()
-            .rfind(|t| t.id().eq_ignore_ascii_case(template_id) || t.name().eq_ignore_ascii_case(template_id))
-            .ok_or_else(|| {
-                CreateHandlerError::TemplateNotFound(
-                    template_id.to_string(),
-                    templates.iter().map(|t| t.id().to_string()).collect(),
-                )
-            })?,
-        None => util::cli_select("🔎 Select a template", templates.as_slice())?,
+            let template = match &args.template {
+                Some(template_id) => Template::find_by_id(&templates, template_id).ok_or_else(|| {
+                    CreateHandlerError::TemplateNotFound(
+                        template_id.to_string(),
+                        templates.iter().map(|t| t.id().to_string()).collect(),
+                        util::suggest_similar(template_id, templates.iter().map(|t| t.id())),
+                    )
+                })?,
+                None => util::cli_select("🔎 Select a template", templates.as_slice())?,
+            };
+            (
+                cargo_template_path(template)?,
+                resolve_template_variables(template, &args.defines)?,
+                template_source(&config, &template_repo_dir, template),
+            )
+        },
+    };
+
+    // keep stdout clean for the JSON report
+    let notice = |message: String| {
+        if args.output_format.is_json() {
+            eprintln!("{message}");
+        } else {
+            println!("{message}");
+        }
     };
 
-    let template_path = template
-        .path()
-        .to_str()
-        .ok_or(anyhow!("Invalid template path!"))?
-        .to_string();
+    if prepare_output_dir(&args.output).await? {
+        notice(format!("📁 Created output directory {}", args.output.display()));
+    }
+
+    let crate_dir = if args.init {
+        ensure_init_dir_is_empty(&args.output)?;
+        args.output.clone()
+    } else {
+        args.output.join(&name)
+    };
+    let previous_files = if crate_dir.exists() && !args.init {
+        if !args.replace {
+            return Err(anyhow!(
+                "{} already exists. Pass --replace to regenerate the crate in place, keeping files the template \
+                 does not write.",
+                crate_dir.display()
+            ));
+        }
+        Some(snapshot_files(&crate_dir)?)
+    } else {
+        None
+    };
 
+    // cargo-generate refuses to create a crate directory that exists, so --replace generates into
+    // it the way --init does.
+    let in_place = args.init || previous_files.is_some();
     let generate_args = CargoGenerateArgs {
         name: Some(name.clone()),
-        destination: Some(args.output.clone()),
-        template_path: TemplatePath {
-            path: Some(template_path),
-            ..TemplatePath::default()
-        },
-        verbose: args.verbose,
+        destination: Some(if in_place {
+            crate_dir.clone()
+        } else {
+            args.output.clone()
+        }),
+        template_path,
+        verbose: args.verbose > 0,
+        define: defines,
+        overwrite: args.replace,
+        init: in_place,
+        // git is initialised below, as `should_init_git` decides
+        vcs: Some(cargo_generate::Vcs::None),
         ..CargoGenerateArgs::default()
     };
-    loading!("Generating template crate", cargo_generate::generate(generate_args))?;
+    loading!(
+        @stream progress,
+        "Generating template crate",
+        cargo_generate::generate(generate_args)
+    )?;
 
-    let crate_dir = args.output.join(&name);
+    validate_generated_crate(&crate_dir, &source.id)?;
+    record_template_source(&crate_dir, &source).await?;
 
     // initialise template metadata (build.rs + Cargo.toml metadata section)
     if !args.skip_metadata {
         loading!(
+            @stream progress,
             format!("Initialising template metadata for **{}**", name),
             init_metadata::auto_init(&crate_dir).await
         )?;
     }
 
-    if !args.skip_init
-        && let Err(error) = GitRepository::new(crate_dir).init()
-        && args.verbose
-    {
-        println!("ℹ️ Git repository already initialized: {error}");
+    let inside_repo = GitRepository::new(args.output.clone()).discover().is_ok();
+    let mut git_initialized = false;
+    if should_init_git(args.init_git, args.skip_init, inside_repo) {
+        match GitRepository::new(crate_dir.clone()).init() {
+            Ok(()) => git_initialized = true,
+            Err(error) if args.verbose > 0 => warnings.warn(format!("Git repository not initialized: {error}")),
+            Err(_) => {},
+        }
     }
 
+    let overwritten_files = match &previous_files {
+        Some(previous) => changed_files(previous, &crate_dir)?,
+        None => vec![],
+    };
+    for path in &overwritten_files {
+        warnings.warn(format!("Overwrote {}", path.display()));
+    }
+
+    if args.output_format.is_json() {
+        let report = CreateReport {
+            crate_path: crate_dir.clone(),
+            template: source.id.clone(),
+            metadata_initialized: !args.skip_metadata,
+            git_initialized,
+            overwritten_files,
+            warnings: warnings.into_vec(),
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    }
+
+    Ok(crate_dir)
+}
+
+/// Where cargo-generate fetches `template` from: its remote scaffold, or its directory in the
+/// template repository.
+pub fn cargo_template_path(template: &Template) -> anyhow::Result<TemplatePath> {
+    Ok(match template.source() {
+        Some(source) => TemplatePath {
+            git: Some(source.git.clone()),
+            subfolder: source.subfolder.clone(),
+            branch: source.branch.clone(),
+            ..TemplatePath::default()
+        },
+        None => TemplatePath {
+            path: Some(
+                template
+                    .path()
+                    .to_str()
+                    .ok_or(anyhow!("Invalid template path!"))?
+                    .to_string(),
+            ),
+            ..TemplatePath::default()
+        },
+    })
+}
+
+/// Makes sure `output` is a directory cargo-generate can write into, creating it if missing.
+/// Returns whether it was created (as opposed to reused).
+async fn prepare_output_dir(output: &Path) -> anyhow::Result<bool> {
+    if util::dir_exists(output).await? {
+        return Ok(false);
+    }
+    if tokio::fs::try_exists(output).await? {
+        return Err(anyhow!(
+            "--output {} is not a directory. Choose a directory for the new crate.",
+            output.display()
+        ));
+    }
+    util::create_dir(output)
+        .await
+        .map_err(|e| anyhow!("Failed to create output directory {}: {e}", output.display()))?;
+    Ok(true)
+}
+
+/// The crate name for `--init` without a name: the output directory's name.
+fn init_crate_name(output: &Path) -> anyhow::Result<String> {
+    let dir = std::path::absolute(output)?;
+    match dir.file_name().and_then(|name| name.to_str()) {
+        Some(name) => project_name_parser(name).map_err(|e| anyhow!(e)),
+        None => Err(anyhow!(
+            "Cannot take the crate name from {}. Pass a name: `tari create --init <NAME>`.",
+            dir.display()
+        )),
+    }
+}
+
+/// `--init` generates into the output directory itself, so it must not hold anything the template
+/// could clash with. A `.git` directory is allowed.
+fn ensure_init_dir_is_empty(dir: &Path) -> anyhow::Result<()> {
+    let mut entries: Vec<String> = std::fs::read_dir(dir)
+        .with_context(|| format!("reading {}", dir.display()))?
+        .filter_map(Result::ok)
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| name != ".git")
+        .collect();
+    if entries.is_empty() {
+        return Ok(());
+    }
+    entries.sort();
+    Err(anyhow!(
+        "--init needs an empty directory, but {} contains: {}. Create the crate in a new directory instead.",
+        dir.display(),
+        entries.join(", ")
+    ))
+}
+
+/// Contents of the files under `dir`, by path relative to it. `.git` and `target` are skipped.
+pub fn snapshot_files(dir: &Path) -> anyhow::Result<BTreeMap<PathBuf, Vec<u8>>> {
+    let mut files = BTreeMap::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let entry = entry?;
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                if !matches!(entry.file_name().to_str(), Some(".git" | "target")) {
+                    pending.push(path);
+                }
+            } else if file_type.is_file() {
+                files.insert(path.strip_prefix(dir)?.to_path_buf(), std::fs::read(&path)?);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Files in `before` whose contents in `dir` are now different, sorted.
+fn changed_files(before: &BTreeMap<PathBuf, Vec<u8>>, dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let after = snapshot_files(dir)?;
+    Ok(before
+        .iter()
+        .filter(|(path, contents)| after.get(*path).is_some_and(|now| now != *contents))
+        .map(|(path, _)| path.clone())
+        .collect())
+}
+
+/// Checks that the generated crate can be built and published as a template: it must be a package
+/// (not a workspace) whose `[lib]` builds a `cdylib`, or `tari build` finds no WASM binary.
+/// Catches broken starter templates at creation instead of at publish time.
+fn validate_generated_crate(crate_dir: &Path, template_id: &str) -> anyhow::Result<()> {
+    let manifest_path = crate_dir.join("Cargo.toml");
+    let content = std::fs::read(&manifest_path).map_err(|e| {
+        anyhow!(
+            "Template '{template_id}' did not generate a Cargo.toml at {}: {e}",
+            manifest_path.display()
+        )
+    })?;
+    let manifest = Manifest::from_slice(&content)
+        .map_err(|e| anyhow!("Template '{template_id}' generated an invalid Cargo.toml: {e}"))?;
+    if manifest.package.is_none() {
+        return Err(anyhow!(
+            "Template '{template_id}' is not a valid template crate: the generated Cargo.toml has no [package]"
+        ));
+    }
+    if !builds_cdylib(&manifest) {
+        return Err(anyhow!(
+            "Template '{template_id}' is not a valid template crate: the generated Cargo.toml must set \
+             `crate-type = [\"cdylib\", ...]` under [lib] to build a WASM binary"
+        ));
+    }
     Ok(())
 }
+
+/// The template given with `--from-git` or `--from-path`, if any, checked before generation.
+/// Such templates bypass the template repository; their id is the last component of the path.
+fn ad_hoc_template(args: &CreateArgs) -> anyhow::Result<Option<(TemplatePath, TemplateSource)>> {
+    if let Some(url) = &args.from_git {
+        if !is_git_url(url) {
+            return Err(anyhow!(
+                "--from-git {url} is not a git URL. Use an https://, ssh://, git:// or file:// URL, or user@host:path."
+            ));
+        }
+        let id = last_path_component(args.subfolder.as_deref().unwrap_or(url));
+        let template_path = TemplatePath {
+            git: Some(url.clone()),
+            subfolder: args.subfolder.clone(),
+            ..TemplatePath::default()
+        };
+        let source = TemplateSource {
+            id,
+            repository: url.clone(),
+            commit: None,
+        };
+        return Ok(Some((template_path, source)));
+    }
+    if let Some(path) = &args.from_path {
+        if !path.is_dir() {
+            return Err(anyhow!("--from-path {} is not a directory", path.display()));
+        }
+        let path = std::path::absolute(path)?;
+        let path_str = path
+            .to_str()
+            .ok_or_else(|| anyhow!("Invalid template path: {}", path.display()))?;
+        let template_path = TemplatePath {
+            path: Some(path_str.to_string()),
+            ..TemplatePath::default()
+        };
+        let source = TemplateSource {
+            id: last_path_component(path_str),
+            repository: path_str.to_string(),
+            commit: None,
+        };
+        return Ok(Some((template_path, source)));
+    }
+    Ok(None)
+}
+
+/// Whether `url` is a URL git can clone: one with a git transport scheme, or scp-like `user@host:path`.
+fn is_git_url(url: &str) -> bool {
+    match url::Url::parse(url) {
+        Ok(parsed) => matches!(parsed.scheme(), "https" | "http" | "ssh" | "git" | "file") && parsed.path().len() > 1,
+        Err(_) => url
+            .split_once(':')
+            .is_some_and(|(host, path)| host.contains('@') && !path.is_empty()),
+    }
+}
+
+fn last_path_component(path: &str) -> String {
+    let last = path
+        .trim_end_matches(['/', '\\'])
+        .rsplit(['/', '\\', ':'])
+        .next()
+        .unwrap_or(path);
+    last.strip_suffix(".git").unwrap_or(last).to_string()
+}
+
+/// Where `template` comes from: its remote scaffold, or the configured template repository at
+/// its checked-out commit.
+pub fn template_source(config: &Config, template_repo_dir: &Path, template: &Template) -> TemplateSource {
+    match template.source() {
+        Some(source) => TemplateSource {
+            id: template.id().to_string(),
+            repository: source.git.clone(),
+            commit: None,
+        },
+        None => {
+            let mut repo = GitRepository::new(template_repo_dir.to_path_buf());
+            TemplateSource {
+                id: template.id().to_string(),
+                repository: config.template_repository.url.clone(),
+                commit: repo.load().and_then(|()| repo.head_commit_hash()).ok(),
+            }
+        },
+    }
+}
+
+/// Records `source` under `[template]` in the crate's project config, creating the config (as
+/// `tari init` would) if the template did not generate one.
+async fn record_template_source(crate_dir: &Path, source: &TemplateSource) -> anyhow::Result<()> {
+    let config_path = crate_dir.join(CONFIG_FILE_NAME);
+    let content = if config_path.is_file() {
+        tokio::fs::read_to_string(&config_path).await?
+    } else {
+        toml::to_string_pretty(&ProjectConfig::default())?
+    };
+    let mut doc = content
+        .parse::<toml_edit::DocumentMut>()
+        .with_context(|| format!("parsing {}", config_path.display()))?;
+    set_dotted_key(&mut doc, "template.id", &source.id)?;
+    set_dotted_key(&mut doc, "template.repository", &source.repository)?;
+    if let Some(commit) = &source.commit {
+        set_dotted_key(&mut doc, "template.commit", commit)?;
+    }
+    tokio::fs::write(&config_path, doc.to_string())
+        .await
+        .with_context(|| format!("writing {}", config_path.display()))
+}
+
+/// Prints the commands to run next for a freshly created crate.
+pub fn print_next_steps(crate_dir: &Path) {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let shown = crate_dir.strip_prefix(&cwd).unwrap_or(crate_dir);
+    println!();
+    println!("🎉 Next steps:");
+    println!("   cd {}", shown.display());
+    println!("   tari build      # build the WASM binary");
+    println!("   tari publish    # publish it to the network");
+}
+
+/// Describes the closest Tari project (a crate with a `tari.config.toml`) or Cargo workspace that
+/// `dir` is in. A `tari.config.toml` without a `Cargo.toml` beside it, such as one `tari config
+/// init` wrote at a repository root, is not a project.
+pub(crate) fn find_enclosing_project(dir: &Path) -> Option<String> {
+    let dir = std::path::absolute(dir).ok()?;
+    dir.ancestors().find_map(|ancestor| {
+        if ancestor.join(CONFIG_FILE_NAME).is_file() && ancestor.join("Cargo.toml").is_file() {
+            return Some(format!("the Tari project at {}", ancestor.display()));
+        }
+        let manifest = std::fs::read_to_string(ancestor.join("Cargo.toml")).ok()?;
+        let manifest = manifest.parse::<toml::Table>().ok()?;
+        manifest
+            .contains_key("workspace")
+            .then(|| format!("the Cargo workspace at {}", ancestor.display()))
+    })
+}
+
+/// Whether to `git init` the new crate. Explicit flags win; by default a crate created inside an
+/// existing repository is not turned into a nested one.
+fn should_init_git(init_git: Option<bool>, skip_init: bool, inside_repo: bool) -> bool {
+    if skip_init {
+        return false;
+    }
+    init_git.unwrap_or(!inside_repo)
+}
+
+/// Resolves the template's declared variables into cargo-generate defines (`name=value`).
+/// Values passed with `--define` are validated against the declared type; any other variable
+/// is prompted for.
+pub fn resolve_template_variables(template: &Template, defines: &[(String, String)]) -> anyhow::Result<Vec<String>> {
+    let variables = template.variables();
+    if let Some((unknown, _)) = defines.iter().find(|(key, _)| !variables.contains_key(key)) {
+        return Err(anyhow!(
+            "Unknown template variable: {unknown}. Declared variables: {:?}",
+            variables.keys().collect::<Vec<_>>()
+        ));
+    }
+
+    let mut resolved = Vec::with_capacity(variables.len());
+    for (name, variable) in variables {
+        let value = match defines.iter().rfind(|(key, _)| key == name) {
+            Some((_, raw)) => Some(
+                variable
+                    .parse_value(raw)
+                    .map_err(|e| anyhow!("Invalid value for template variable {name}: {e}"))?,
+            ),
+            None => prompt_template_variable(name, variable)?,
+        };
+        match value {
+            Some(value) => resolved.push(format!("{name}={value}")),
+            None if variable.required => return Err(anyhow!("Template variable {name} is required")),
+            None => {},
+        }
+    }
+
+    Ok(resolved)
+}
+
+fn prompt_template_variable(name: &str, variable: &TemplateVariable) -> anyhow::Result<Option<String>> {
+    let default = variable
+        .default_value()
+        .map_err(|e| anyhow!("Invalid default for template variable {name}: {e}"))?;
+    let prompt = match &variable.description {
+        Some(description) => format!("{description} ({name}, {})", variable.kind),
+        None => format!("{name} ({})", variable.kind),
+    };
+
+    if variable.kind == TemplateVariableType::Bool {
+        let value = Confirm::new()
+            .with_prompt(prompt)
+            .default(default.as_deref() == Some("true"))
+            .interact()?;
+        return Ok(Some(value.to_string()));
+    }
+
+    let mut input = Input::<String>::new()
+        .with_prompt(prompt)
+        .allow_empty(!variable.required)
+        .validate_with(|raw: &String| -> Result<(), String> {
+            if raw.trim().is_empty() {
+                return Ok(());
+            }
+            variable.parse_value(raw).map(|_| ())
+        });
+    if let Some(default) = default {
+        input = input.default(default);
+    }
+    let raw = input.interact_text()?;
+    if raw.trim().is_empty() {
+        return Ok(None);
+    }
+    variable.parse_value(&raw).map(Some).map_err(|e| anyhow!(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeMap, HashMap};
+    use tempfile::TempDir;
+
+    /// Writes a minimal cargo-generate template named `id` into a fixture template repository
+    /// laid out like the configured one (`<repo>/<folder>/<id>/template.toml`).
+    fn write_fixture_template(repo_dir: &Path, id: &str) {
+        let template_dir = repo_dir.join(Config::default().template_repository.folder).join(id);
+        std::fs::create_dir_all(template_dir.join("src")).unwrap();
+        std::fs::write(
+            template_dir.join("template.toml"),
+            format!("name = \"{id}\"\ndescription = \"Fixture template\"\n"),
+        )
+        .unwrap();
+        std::fs::write(
+            template_dir.join("Cargo.toml"),
+            "[package]\nname = \"{{project-name}}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[lib]\ncrate-type = [\"cdylib\", \"lib\"]\n",
+        )
+        .unwrap();
+        std::fs::write(template_dir.join("src").join("lib.rs"), "// {{project-name}}\n").unwrap();
+    }
+
+    fn create_args(name: &str, template: &str, output: &Path) -> CreateArgs {
+        CreateArgs {
+            name: Some(name.to_string()),
+            template: Some(template.to_string()),
+            from_git: None,
+            subfolder: None,
+            from_path: None,
+            template_repo_ref: None,
+            expect_tree: None,
+            defines: vec![],
+            output: output.to_path_buf(),
+            skip_init: false,
+            init_git: None,
+            skip_metadata: false,
+            force: false,
+            replace: false,
+            init: false,
+            verbose: 0,
+            output_format: OutputFormat::Text,
+        }
+    }
+
+    #[tokio::test]
+    async fn creates_crate_from_fixture_template_repo() {
+        let repo_dir = TempDir::new().unwrap();
+        let output_dir = TempDir::new().unwrap();
+        write_fixture_template(repo_dir.path(), "counter");
+
+        handle(
+            Config::default(),
+            repo_dir.path().to_path_buf(),
+            create_args("my_counter", "counter", output_dir.path()),
+        )
+        .await
+        .expect("create should succeed");
+
+        let crate_dir = output_dir.path().join("my_counter");
+        let cargo_toml = std::fs::read_to_string(crate_dir.join("Cargo.toml")).expect("crate should be generated");
+        assert!(cargo_toml.contains("my_counter"), "got: {cargo_toml}");
+        // metadata auto-initialisation
+        assert!(cargo_toml.contains("tari_ootle_template_build"), "got: {cargo_toml}");
+        assert!(
+            cargo_toml.contains("[package.metadata.tari-template]"),
+            "got: {cargo_toml}"
+        );
+        assert!(crate_dir.join("build.rs").exists());
+        // git init
+        assert!(crate_dir.join(".git").exists());
+    }
+
+    #[tokio::test]
+    async fn unknown_template_id_suggests_close_match() {
+        let repo_dir = TempDir::new().unwrap();
+        let output_dir = TempDir::new().unwrap();
+        write_fixture_template(repo_dir.path(), "counter");
+
+        let err = handle(
+            Config::default(),
+            repo_dir.path().to_path_buf(),
+            create_args("my_counter", "countr", output_dir.path()),
+        )
+        .await
+        .unwrap_err();
+
+        let msg = err.to_string();
+        assert!(msg.contains("Did you mean: counter?"), "got: {msg}");
+        assert!(!output_dir.path().join("my_counter").exists());
+    }
+
+    #[tokio::test]
+    async fn replace_regenerates_an_existing_crate_in_place() {
+        let repo_dir = TempDir::new().unwrap();
+        let output_dir = TempDir::new().unwrap();
+        write_fixture_template(repo_dir.path(), "counter");
+        let create = |replace: bool| {
+            let mut args = create_args("my_counter", "counter", output_dir.path());
+            args.replace = replace;
+            handle(Config::default(), repo_dir.path().to_path_buf(), args)
+        };
+        create(false).await.expect("first create should succeed");
+
+        let crate_dir = output_dir.path().join("my_counter");
+        std::fs::write(crate_dir.join("src").join("lib.rs"), "// my changes\n").unwrap();
+        std::fs::write(crate_dir.join("NOTES.md"), "keep me\n").unwrap();
+
+        let err = create(false).await.unwrap_err().to_string();
+        assert!(err.contains("--replace"), "got: {err}");
+
+        let before = snapshot_files(&crate_dir).unwrap();
+        create(true).await.expect("create --replace should succeed");
+        let lib_rs = std::fs::read_to_string(crate_dir.join("src").join("lib.rs")).unwrap();
+        assert_eq!(lib_rs, "// my_counter\n");
+        assert_eq!(
+            std::fs::read_to_string(crate_dir.join("NOTES.md")).unwrap(),
+            "keep me\n"
+        );
+        assert_eq!(
+            changed_files(&before, &crate_dir).unwrap(),
+            vec![PathBuf::from("src").join("lib.rs")]
+        );
+    }
+
+    #[tokio::test]
+    async fn creates_missing_output_directory() {
+        let repo_dir = TempDir::new().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        write_fixture_template(repo_dir.path(), "counter");
+        let output = temp_dir.path().join("not").join("yet");
+
+        handle(
+            Config::default(),
+            repo_dir.path().to_path_buf(),
+            create_args("my_counter", "counter", &output),
+        )
+        .await
+        .expect("create should succeed");
+
+        assert!(output.join("my_counter").join("Cargo.toml").exists());
+    }
+
+    #[tokio::test]
+    async fn skip_init_creates_no_git_repository() {
+        let repo_dir = TempDir::new().unwrap();
+        let output_dir = TempDir::new().unwrap();
+        write_fixture_template(repo_dir.path(), "counter");
+
+        let mut args = create_args("my_counter", "counter", output_dir.path());
+        args.skip_init = true;
+        let crate_dir = handle(Config::default(), repo_dir.path().to_path_buf(), args)
+            .await
+            .expect("create should succeed");
+        assert!(crate_dir.join("Cargo.toml").exists());
+        assert!(!crate_dir.join(".git").exists());
+    }
+
+    #[tokio::test]
+    async fn init_generates_into_the_output_directory() {
+        let repo_dir = TempDir::new().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        write_fixture_template(repo_dir.path(), "counter");
+        let output = temp_dir.path().join("my_counter");
+        std::fs::create_dir_all(output.join(".git")).unwrap();
+
+        let mut args = create_args("unused", "counter", &output);
+        args.name = None;
+        args.init = true;
+        let crate_dir = handle(Config::default(), repo_dir.path().to_path_buf(), args)
+            .await
+            .expect("create --init should succeed");
+        assert_eq!(crate_dir, output);
+        let cargo_toml = std::fs::read_to_string(output.join("Cargo.toml")).unwrap();
+        assert!(cargo_toml.contains("name = \"my_counter\""), "got: {cargo_toml}");
+
+        let err = ensure_init_dir_is_empty(&output).unwrap_err().to_string();
+        assert!(err.contains("Cargo.toml"), "got: {err}");
+    }
+
+    #[tokio::test]
+    async fn creates_crate_from_an_ad_hoc_template_path() {
+        let repo_dir = TempDir::new().unwrap();
+        let output_dir = TempDir::new().unwrap();
+        write_fixture_template(repo_dir.path(), "counter");
+        let template_dir = repo_dir
+            .path()
+            .join(Config::default().template_repository.folder)
+            .join("counter");
+
+        let mut args = create_args("my_counter", "unused", output_dir.path());
+        args.template = None;
+        args.from_path = Some(template_dir.clone());
+        // no template repository is needed
+        handle(Config::default(), PathBuf::from("missing"), args)
+            .await
+            .expect("create --from-path should succeed");
+
+        let project_config =
+            std::fs::read_to_string(output_dir.path().join("my_counter").join(CONFIG_FILE_NAME)).unwrap();
+        let config: ProjectConfig = toml::from_str(&project_config).unwrap();
+        let source = config.template_source().expect("template source");
+        assert_eq!(source.id, "counter");
+        assert_eq!(source.repository, template_dir.to_str().unwrap());
+    }
+
+    #[test]
+    fn ad_hoc_git_urls_are_validated() {
+        for url in [
+            "https://github.com/tari-project/wasm-template",
+            "git@github.com:tari-project/wasm-template.git",
+            "ssh://git@example.com/templates.git",
+        ] {
+            assert!(is_git_url(url), "{url}");
+        }
+        for url in ["github.com/tari-project/wasm-template", "https://", "not a url"] {
+            assert!(!is_git_url(url), "{url}");
+        }
+        assert_eq!(last_path_component("git@github.com:org/counter.git"), "counter");
+        assert_eq!(last_path_component("templates/basic/"), "basic");
+    }
+
+    #[tokio::test]
+    async fn output_that_is_a_file_is_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let output = temp_dir.path().join("file");
+        std::fs::write(&output, "").unwrap();
+
+        let err = prepare_output_dir(&output).await.unwrap_err().to_string();
+        assert!(err.contains("is not a directory"), "got: {err}");
+    }
+
+    #[tokio::test]
+    async fn existing_output_directory_is_reused() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(!prepare_output_dir(temp_dir.path()).await.unwrap());
+        let missing = temp_dir.path().join("new");
+        assert!(prepare_output_dir(&missing).await.unwrap());
+        assert!(missing.is_dir());
+    }
+
+    fn template_with_variables(variables: BTreeMap<String, TemplateVariable>) -> Template {
+        Template::new(
+            PathBuf::from("typed"),
+            "typed".to_string(),
+            "typed".to_string(),
+            String::new(),
+            HashMap::new(),
+            variables,
+        )
+    }
+
+    #[test]
+    fn missing_templates_folder_lists_repository_folders() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("templates")).unwrap();
+        std::fs::create_dir_all(temp_dir.path().join(".git")).unwrap();
+
+        let err = templates_dir(&Config::default(), temp_dir.path())
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.contains("`wasm_templates` not found"), "got: {err}");
+        assert!(err.contains("github.com/tari-project/wasm-template"), "got: {err}");
+        assert!(err.contains("Folders in the repository: templates."), "got: {err}");
+    }
+
+    #[test]
+    fn config_without_a_crate_is_not_an_enclosing_project() {
+        let temp_dir = TempDir::new().unwrap();
+        let nested = temp_dir.path().join("a");
+        std::fs::write(temp_dir.path().join(CONFIG_FILE_NAME), "").unwrap();
+        assert!(find_enclosing_project(&nested).is_none());
+    }
+
+    #[test]
+    fn detects_enclosing_workspace_and_tari_project() {
+        let temp_dir = TempDir::new().unwrap();
+        let nested = temp_dir.path().join("a").join("b");
+        assert!(find_enclosing_project(&nested).is_none());
+
+        std::fs::create_dir_all(temp_dir.path().join("a")).unwrap();
+        std::fs::write(
+            temp_dir.path().join("a").join("Cargo.toml"),
+            "[package]\nname = \"a\"\n",
+        )
+        .unwrap();
+        assert!(find_enclosing_project(&nested).is_none());
+
+        std::fs::write(temp_dir.path().join("Cargo.toml"), "[workspace]\nmembers = []\n").unwrap();
+        let found = find_enclosing_project(&nested).expect("workspace should be found");
+        assert!(found.contains("Cargo workspace"), "got: {found}");
+
+        std::fs::write(temp_dir.path().join("a").join(CONFIG_FILE_NAME), "").unwrap();
+        let found = find_enclosing_project(&nested).expect("project should be found");
+        assert!(found.contains("Tari project"), "got: {found}");
+    }
+
+    #[test]
+    fn generated_crate_must_be_a_cdylib_package() {
+        let temp_dir = TempDir::new().unwrap();
+        let manifest = temp_dir.path().join("Cargo.toml");
+
+        std::fs::write(&manifest, "[workspace]\nmembers = []\n").unwrap();
+        let err = validate_generated_crate(temp_dir.path(), "broken").unwrap_err();
+        assert!(err.to_string().contains("no [package]"), "got: {err}");
+
+        std::fs::write(&manifest, "[package]\nname = \"a\"\nversion = \"0.1.0\"\n").unwrap();
+        let err = validate_generated_crate(temp_dir.path(), "broken").unwrap_err();
+        assert!(err.to_string().contains("cdylib"), "got: {err}");
+
+        std::fs::write(
+            &manifest,
+            "[package]\nname = \"a\"\nversion = \"0.1.0\"\n\n[lib]\ncrate-type = [\"cdylib\", \"lib\"]\n",
+        )
+        .unwrap();
+        assert!(validate_generated_crate(temp_dir.path(), "ok").is_ok());
+    }
+
+    #[test]
+    fn git_init_skipped_inside_existing_repo_unless_forced() {
+        assert!(should_init_git(None, false, false));
+        assert!(!should_init_git(None, false, true));
+        assert!(should_init_git(Some(true), false, true));
+        assert!(!should_init_git(Some(false), false, false));
+        assert!(!should_init_git(None, true, false));
+    }
+
+    #[test]
+    fn define_parser_splits_on_first_equals() {
+        assert_eq!(
+            template_define_parser("symbol=A=B").unwrap(),
+            ("symbol".to_string(), "A=B".to_string())
+        );
+        assert!(template_define_parser("symbol").is_err());
+        assert!(template_define_parser("=value").is_err());
+    }
+
+    #[test]
+    fn provided_defines_are_validated_and_normalized() {
+        let template = template_with_variables(BTreeMap::from([(
+            "supply".to_string(),
+            TemplateVariable {
+                kind: TemplateVariableType::Int,
+                ..TemplateVariable::default()
+            },
+        )]));
+        let resolved = resolve_template_variables(&template, &[("supply".to_string(), " 1000 ".to_string())]).unwrap();
+        assert_eq!(resolved, vec!["supply=1000".to_string()]);
+
+        let err = resolve_template_variables(&template, &[("supply".to_string(), "lots".to_string())]).unwrap_err();
+        assert!(err.to_string().contains("supply"), "got: {err}");
+    }
+
+    #[test]
+    fn unknown_defines_are_rejected() {
+        let template = template_with_variables(BTreeMap::new());
+        let err = resolve_template_variables(&template, &[("bogus".to_string(), "1".to_string())]).unwrap_err();
+        assert!(err.to_string().contains("bogus"), "got: {err}");
+    }
+
+    #[tokio::test]
+    async fn template_source_is_recorded_in_project_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = TemplateSource {
+            id: "fungible".to_string(),
+            repository: "https://github.com/tari-project/wasm-template".to_string(),
+            commit: Some("0123abcd".to_string()),
+        };
+        record_template_source(temp_dir.path(), &source).await.unwrap();
+        let content = std::fs::read_to_string(temp_dir.path().join(CONFIG_FILE_NAME)).unwrap();
+        let config: ProjectConfig = toml::from_str(&content).unwrap();
+        assert_eq!(config.template_source(), Some(&source));
+        // a fresh config gets the same defaults as `tari init`
+        assert_eq!(config.default_network(), ProjectConfig::default().default_network());
+
+        // an existing config keeps its settings
+        std::fs::write(temp_dir.path().join(CONFIG_FILE_NAME), "default-account = \"alice\"\n").unwrap();
+        record_template_source(temp_dir.path(), &source).await.unwrap();
+        let content = std::fs::read_to_string(temp_dir.path().join(CONFIG_FILE_NAME)).unwrap();
+        assert!(content.starts_with("default-account = \"alice\""), "got: {content}");
+        let config: ProjectConfig = toml::from_str(&content).unwrap();
+        assert_eq!(config.template_source(), Some(&source));
+    }
+}
@@ -0,0 +1,215 @@
+// Copyright 2024 The Tari Project
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, anyhow};
+use clap::Parser;
+use ootle_network::Network;
+use serde::Serialize;
+use tari_ootle_publish_lib::publisher::{CheckBalanceResult, Template, TemplatePublisher};
+use tari_ootle_publish_lib::{CancellationToken, NetworkConfig, PublisherError};
+use tari_utilities::Hidden;
+
+use crate::cli::account_cache::AccountCache;
+use crate::cli::build_cache::BuildCache;
+use crate::cli::command::AccountArgs;
+use crate::cli::commands::publish::{
+    CargoBuildArgs, build_template_with_output, load_project_config, print_build_log, resolve_active_network,
+    resolve_custom_network, resolve_wallet_daemon_url, use_cargo_opts,
+};
+use crate::cli::commands::template::publish::{MAX_WASM_SIZE, print_fee_breakdown, resolve_account};
+use crate::cli::config::Config;
+use crate::cli::util::{self, OutputFormat, Warnings};
+use crate::loading;
+
+#[derive(Clone, Parser, Debug)]
+pub struct FeeArgs {
+    /// Path to the template crate directory.
+    /// Defaults to the current directory.
+    #[arg(default_value = ".")]
+    pub path: PathBuf,
+
+    #[clap(flatten)]
+    pub account: AccountArgs,
+
+    /// (Optional) Custom network name.
+    /// Selects a network configured under `[networks.<name>]` in the project or global config.
+    #[arg(short = 'c', long)]
+    pub custom_network: Option<String>,
+
+    /// (Optional) Path to a pre-compiled WASM binary.
+    #[arg(long, alias = "bin")]
+    pub binary: Option<PathBuf>,
+
+    /// Wallet daemon JSON-RPC URL.
+    /// Overrides the value in tari.config.toml and global CLI config for this run only.
+    #[arg(long, visible_alias = "network-url")]
+    pub wallet_daemon_url: Option<url::Url>,
+
+    /// Skip the size-optimizing release profile overrides passed to `cargo build`.
+    #[arg(long, default_value_t = false)]
+    pub no_cargo_opts: bool,
+
+    /// Print the estimate as JSON.
+    #[arg(long, default_value_t = false)]
+    pub json: bool,
+
+    /// Verbosity level, set from the global `--verbose` flag.
+    #[arg(skip)]
+    pub verbose: u8,
+
+    #[clap(flatten)]
+    pub cargo: CargoBuildArgs,
+}
+
+/// The fee estimate, reported with `--json`.
+#[derive(Debug, Serialize)]
+struct FeeReport {
+    network: String,
+    account: String,
+    template_hash: Option<String>,
+    binary_size: Option<usize>,
+    estimated_fee: u64,
+    wallet_balance: String,
+    can_afford: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+}
+
+/// Estimates the fee for publishing the template and checks it against the account balance,
+/// without publishing anything.
+pub async fn handle(
+    config: Config,
+    base_dir: &Path,
+    network_override: Option<Network>,
+    api_key: Option<Hidden<String>>,
+    mut args: FeeArgs,
+    cancel: CancellationToken,
+) -> anyhow::Result<()> {
+    args.account.read_account_source().await?;
+    let crate_dir = &args.path;
+    let output = if args.json {
+        OutputFormat::Json
+    } else {
+        OutputFormat::Text
+    };
+    let mut warnings = Warnings::new(output);
+
+    let project_config = load_project_config(crate_dir).await?;
+    args.cargo.apply_project_defaults(&project_config);
+    let network_override = resolve_custom_network(
+        network_override,
+        args.custom_network.as_deref(),
+        &project_config,
+        &config,
+    )?;
+    let network = resolve_active_network(network_override, &project_config, &config);
+    let wallet_daemon_url =
+        resolve_wallet_daemon_url(args.wallet_daemon_url.as_ref(), &project_config, &config, network);
+
+    let template_bin = match &args.binary {
+        Some(bin_path) => bin_path.clone(),
+        None => build_template_with_output(
+            crate_dir,
+            use_cargo_opts(args.no_cargo_opts, &project_config),
+            &args.cargo,
+            Some(&BuildCache::new(base_dir)),
+            output,
+        )
+        .await
+        .inspect_err(|e| {
+            if args.verbose > 0 {
+                print_build_log(e)
+            }
+        })?,
+    };
+
+    let publisher = TemplatePublisher::new(NetworkConfig::new(wallet_daemon_url.clone()).with_api_key(api_key))
+        .with_cancellation_token(cancel);
+    let info = publisher
+        .get_wallet_info()
+        .await
+        .with_context(|| anyhow!("Failed to connect to the wallet at {}", wallet_daemon_url))?;
+    if info.network_byte != network.as_byte() {
+        return Err(anyhow!(
+            "Wallet daemon is on network '{}' but the CLI is configured for '{network}'. \
+             Use --network <name> to switch, or point --wallet-daemon-url at a daemon for the right network.",
+            info.network
+        ));
+    }
+
+    let account_cache = AccountCache::load(base_dir).await.unwrap_or_default();
+    let (account, account_source) = resolve_account(
+        args.account.selected(),
+        &config,
+        &publisher,
+        &project_config,
+        &account_cache,
+        network,
+        None,
+    )
+    .await?;
+
+    let built_size = tokio::fs::metadata(&template_bin).await?.len() as usize;
+    let template = Template::Path { path: template_bin };
+    // The publish balance check runs the same dry run as `publish`; an insufficient balance is
+    // an answer here, not an error.
+    let mut report = match loading!(
+        @stream output.progress_stream(),
+        "Estimating publish fee",
+        publisher.check_balance_for_publish(&account, &template, None).await
+    ) {
+        Ok(CheckBalanceResult {
+            max_fee,
+            binary_size,
+            wallet_balance,
+            template_hash,
+        }) => {
+            if !args.json {
+                print_fee_breakdown(built_size, binary_size, max_fee, &wallet_balance);
+            }
+            if binary_size > MAX_WASM_SIZE {
+                warnings.warn(format!("WASM binary size exceeded: {}", util::human_bytes(binary_size)));
+            }
+            FeeReport {
+                network: network.to_string(),
+                account: account.to_string(),
+                template_hash: Some(template_hash.to_string()),
+                binary_size: Some(binary_size),
+                estimated_fee: max_fee,
+                wallet_balance: wallet_balance.to_string(),
+                can_afford: true,
+                warnings: vec![],
+            }
+        },
+        Err(PublisherError::InsufficientBalance { current, fee }) => FeeReport {
+            network: network.to_string(),
+            account: account.to_string(),
+            template_hash: None,
+            binary_size: None,
+            estimated_fee: fee,
+            wallet_balance: current.to_string(),
+            can_afford: false,
+            warnings: vec![],
+        },
+        Err(e) => return Err(e.into()),
+    };
+
+    if args.json {
+        report.warnings = warnings.into_vec();
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("🔍 Account: {account} (from {account_source})");
+    if report.can_afford {
+        println!("✅ The account can afford to publish this template on {network}.");
+    } else {
+        println!(
+            "❌ Insufficient balance: the account holds {} but the estimated fee is {}.",
+            report.wallet_balance, report.estimated_fee
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,297 @@
+// Copyright 2024 The Tari Project
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::path::PathBuf;
+
+use anyhow::{Context, anyhow};
+use clap::Parser;
+use serde::Serialize;
+use tari_ootle_publish_lib::publisher::{Template, TemplateFunction, inspect_template};
+use wasmparser::{ExternalKind, Payload, TypeRef};
+
+use crate::cli::commands::template::publish::MAX_WASM_SIZE;
+use crate::cli::util::{self, OutputFormat, Warnings};
+use crate::loading;
+
+#[derive(Clone, Parser, Debug)]
+pub struct InspectArgs {
+    /// Path to a built template WASM binary.
+    pub wasm_path: PathBuf,
+
+    /// Print the report as JSON.
+    #[arg(long, default_value_t = false)]
+    pub json: bool,
+}
+
+/// What `inspect` reports, printed as-is with `--json`.
+#[derive(Debug, Serialize)]
+struct InspectReport {
+    path: PathBuf,
+    file_size: usize,
+    /// Size as it would be published (after optimization, if enabled).
+    binary_size: usize,
+    template_hash: String,
+    template_name: String,
+    functions: Vec<FunctionReport>,
+    imports: Vec<WasmImport>,
+    exports: Vec<WasmExport>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct FunctionReport {
+    name: String,
+    arguments: Vec<ArgumentReport>,
+    output: String,
+    is_mut: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ArgumentReport {
+    name: String,
+    r#type: String,
+}
+
+impl From<TemplateFunction> for FunctionReport {
+    fn from(function: TemplateFunction) -> Self {
+        Self {
+            name: function.name,
+            arguments: function
+                .arguments
+                .into_iter()
+                .map(|(name, r#type)| ArgumentReport { name, r#type })
+                .collect(),
+            output: function.output,
+            is_mut: function.is_mut,
+        }
+    }
+}
+
+/// Describes a built template binary offline: its size and hash as it would be published, the
+/// template ABI, and the module's imports and exports.
+pub async fn handle(args: InspectArgs) -> anyhow::Result<()> {
+    let output = if args.json {
+        OutputFormat::Json
+    } else {
+        OutputFormat::Text
+    };
+    let mut warnings = Warnings::new(output);
+    let code = tokio::fs::read(&args.wasm_path)
+        .await
+        .with_context(|| format!("reading {}", args.wasm_path.display()))?;
+    let interface = parse_wasm_interface(&code).with_context(|| format!("parsing {}", args.wasm_path.display()))?;
+
+    let template = Template::Path {
+        path: args.wasm_path.clone(),
+    };
+    let inspection = loading!(
+        @stream output.progress_stream(),
+        "Loading template",
+        inspect_template(&template).await
+    )?;
+
+    if inspection.binary_size > MAX_WASM_SIZE {
+        warnings.warn(format!(
+            "WASM binary size exceeded: {}",
+            util::human_bytes(inspection.binary_size)
+        ));
+    }
+
+    let report = InspectReport {
+        path: args.wasm_path,
+        file_size: code.len(),
+        binary_size: inspection.binary_size,
+        template_hash: inspection.template_hash.to_string(),
+        template_name: inspection.template_name,
+        functions: inspection.functions.into_iter().map(Into::into).collect(),
+        imports: interface.imports,
+        exports: interface.exports,
+        warnings: warnings.into_vec(),
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+    Ok(())
+}
+
+fn print_report(report: &InspectReport) {
+    println!("📦 WASM binary: {}", report.path.display());
+    println!("   File size:      {}", util::human_bytes(report.file_size));
+    println!("   Published size: {}", util::human_bytes(report.binary_size));
+    println!("🔑 Template hash: {}", report.template_hash);
+    println!("📝 Template: {}", report.template_name);
+
+    println!("   Functions ({}):", report.functions.len());
+    for function in &report.functions {
+        let arguments = function
+            .arguments
+            .iter()
+            .map(|arg| format!("{}: {}", arg.name, arg.r#type))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mutability = if function.is_mut { " (mut)" } else { "" };
+        println!("     {}({arguments}) -> {}{mutability}", function.name, function.output);
+    }
+
+    println!("📥 Imports ({}):", report.imports.len());
+    for import in &report.imports {
+        println!("     {}.{} ({})", import.module, import.name, import.kind);
+    }
+
+    println!("📤 Exports ({}):", report.exports.len());
+    for export in &report.exports {
+        println!("     {} ({})", export.name, export.kind);
+    }
+}
+
+const WASM_MAGIC: &[u8] = b"\0asm";
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct WasmInterface {
+    imports: Vec<WasmImport>,
+    exports: Vec<WasmExport>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+struct WasmImport {
+    module: String,
+    name: String,
+    kind: &'static str,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+struct WasmExport {
+    name: String,
+    kind: &'static str,
+}
+
+/// Reads the import and export sections of a WASM module. Other sections are skipped.
+fn parse_wasm_interface(code: &[u8]) -> anyhow::Result<WasmInterface> {
+    if !code.starts_with(WASM_MAGIC) {
+        return Err(anyhow!("not a WASM module"));
+    }
+
+    let mut interface = WasmInterface::default();
+    for payload in wasmparser::Parser::new(0).parse_all(code) {
+        match payload? {
+            Payload::ImportSection(imports) => {
+                for import in imports.into_imports() {
+                    let import = import?;
+                    interface.imports.push(WasmImport {
+                        module: import.module.to_string(),
+                        name: import.name.to_string(),
+                        kind: import_kind(import.ty),
+                    });
+                }
+            },
+            Payload::ExportSection(exports) => {
+                for export in exports {
+                    let export = export?;
+                    interface.exports.push(WasmExport {
+                        name: export.name.to_string(),
+                        kind: export_kind(export.kind),
+                    });
+                }
+            },
+            _ => {},
+        }
+    }
+    Ok(interface)
+}
+
+fn import_kind(ty: TypeRef) -> &'static str {
+    match ty {
+        TypeRef::Func(_) | TypeRef::FuncExact(_) => "function",
+        TypeRef::Table(_) => "table",
+        TypeRef::Memory(_) => "memory",
+        TypeRef::Global(_) => "global",
+        TypeRef::Tag(_) => "tag",
+    }
+}
+
+fn export_kind(kind: ExternalKind) -> &'static str {
+    match kind {
+        ExternalKind::Func | ExternalKind::FuncExact => "function",
+        ExternalKind::Table => "table",
+        ExternalKind::Memory => "memory",
+        ExternalKind::Global => "global",
+        ExternalKind::Tag => "tag",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A module importing `env.tari_engine` (function) and `env.memory`, and exporting
+    /// `Counter_abi` and `Counter_main`.
+    fn sample_module() -> Vec<u8> {
+        let mut code = b"\0asm\x01\0\0\0".to_vec();
+        // type section: one `() -> ()` type
+        code.extend([1, 4, 1, 0x60, 0, 0]);
+        let mut imports = vec![2];
+        imports.extend([3, b'e', b'n', b'v', 11]);
+        imports.extend(b"tari_engine");
+        imports.extend([0, 0]);
+        imports.extend([3, b'e', b'n', b'v', 6]);
+        imports.extend(b"memory");
+        imports.extend([2, 0, 1]);
+        code.extend([2, imports.len() as u8]); // import section
+        code.extend(imports);
+        let mut exports = vec![2, 11];
+        exports.extend(b"Counter_abi");
+        exports.extend([0, 1, 12]);
+        exports.extend(b"Counter_main");
+        exports.extend([0, 2]);
+        code.extend([7, exports.len() as u8]); // export section
+        code.extend(exports);
+        code
+    }
+
+    #[test]
+    fn reads_imports_and_exports() {
+        let interface = parse_wasm_interface(&sample_module()).unwrap();
+        assert_eq!(
+            interface.imports,
+            vec![
+                WasmImport {
+                    module: "env".to_string(),
+                    name: "tari_engine".to_string(),
+                    kind: "function",
+                },
+                WasmImport {
+                    module: "env".to_string(),
+                    name: "memory".to_string(),
+                    kind: "memory",
+                },
+            ]
+        );
+        let exports = interface.exports.iter().map(|e| e.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(exports, ["Counter_abi", "Counter_main"]);
+    }
+
+    #[test]
+    fn reads_shared_memory_imports() {
+        let mut code = b"\0asm\x01\0\0\0".to_vec();
+        let mut imports = vec![1, 3, b'e', b'n', b'v', 6];
+        imports.extend(b"memory");
+        // shared memory with a maximum: flags 0x03, min 1, max 2
+        imports.extend([2, 3, 1, 2]);
+        code.extend([2, imports.len() as u8]); // import section
+        code.extend(imports);
+        let interface = parse_wasm_interface(&code).unwrap();
+        assert_eq!(interface.imports[0].kind, "memory");
+        assert!(interface.exports.is_empty());
+    }
+
+    #[test]
+    fn rejects_non_wasm_and_truncated_input() {
+        assert!(parse_wasm_interface(b"hello world").is_err());
+        let module = sample_module();
+        assert!(parse_wasm_interface(&module[..module.len() - 3]).is_err());
+    }
+}
@@ -5,7 +5,8 @@ use std::path::PathBuf;
 use std::time::Duration;
 
 use crate::cli::commands::publish::{
-    decode_metadata_cbor, find_metadata_cbor, load_project_config, resolve_active_network, resolve_wallet_daemon_url,
+    DEFAULT_WASM_TARGET, decode_metadata_cbor, find_metadata_cbor, load_project_config, resolve_active_network,
+    resolve_wallet_daemon_url,
 };
 use crate::cli::config::Config;
 use crate::cli::util::get_default_metadata_server_url;
@@ -68,7 +69,7 @@ pub async fn handle(
     api_key: Option<Hidden<String>>,
     args: PublishMetadataArgs,
 ) -> anyhow::Result<()> {
-    let cbor_path = find_metadata_cbor(&args.path).await?;
+    let cbor_path = find_metadata_cbor(&args.path, DEFAULT_WASM_TARGET).await?;
     let mut cbor_bytes = std::fs::read(&cbor_path).context("reading metadata CBOR file")?;
 
     let project_config = load_project_config(&args.path).await?;
@@ -115,8 +116,8 @@ pub async fn handle(
                     .default(true)
                     .interact()?;
                 if rebuild {
-                    crate::cli::commands::publish::build_template(&args.path, true).await?;
-                    let new_cbor_path = find_metadata_cbor(&args.path).await?;
+                    crate::cli::commands::publish::build_template(&args.path, true, &Default::default(), None).await?;
+                    let new_cbor_path = find_metadata_cbor(&args.path, DEFAULT_WASM_TARGET).await?;
                     cbor_bytes = std::fs::read(&new_cbor_path).context("reading rebuilt metadata CBOR")?;
                     metadata = decode_metadata_cbor(&cbor_bytes)?;
                     println!("✅ Metadata rebuilt");
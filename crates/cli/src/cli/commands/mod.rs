@@ -2,10 +2,18 @@
 // SPDX-License-Identifier: BSD-3-Clause
 
 pub mod build;
+pub mod bundle;
+pub mod clean;
 pub mod config;
 pub mod create;
+pub mod fee;
 pub mod init;
+pub mod inspect;
 pub mod metadata;
 pub mod publish;
+pub mod rename;
 pub mod template;
+pub mod templates;
+pub mod upgrade;
+pub mod whoami;
 pub mod wizard;
@@ -1,19 +1,24 @@
 // Copyright 2024 The Tari Project
 // SPDX-License-Identifier: BSD-3-Clause
 
-use crate::cli::commands::template::publish::TemplatePublishArgs;
+use crate::cli::build_cache::{self, BuildCache};
+use crate::cli::command::AccountArgs;
+use crate::cli::commands::bundle::extract_bundle;
+use crate::cli::commands::template::publish::{BundledMetadata, PublishResult, TemplatePublishArgs};
 use crate::cli::config::Config;
 use crate::cli::util;
+use crate::cli::util::OutputFormat;
 use crate::{loading, project};
 use anyhow::{Context, anyhow};
 use cargo_toml::Manifest;
-use clap::Parser;
+use clap::{Args, Parser};
 use ootle_network::Network;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use tari_ootle_publish_lib::walletd_client::ComponentAddressOrName;
+use tari_ootle_publish_lib::CancellationToken;
 use tari_ootle_template_metadata::TemplateMetadata;
 use tari_utilities::Hidden;
+use thiserror::Error;
 use tokio::fs;
 use tokio::process::Command;
 
@@ -24,16 +29,30 @@ pub struct PublishArgs {
     #[arg(default_value = ".")]
     pub path: PathBuf,
 
-    /// Account to be used for publishing fees.
-    #[arg(short = 'a', long)]
-    pub account: Option<ComponentAddressOrName>,
+    /// Path to the template crate's `Cargo.toml`, as with cargo. The crate directory is its
+    /// parent, which must also contain a `tari.config.toml`. Use instead of PATH.
+    #[arg(long, value_name = "PATH", conflicts_with = "path")]
+    pub manifest_path: Option<PathBuf>,
+
+    #[clap(flatten)]
+    pub account: AccountArgs,
 
     /// (Optional) Custom network name.
-    /// Custom network name set in project config.
-    /// It must be set when network is set to custom!
+    /// Selects a network configured under `[networks.<name>]` in the project or global config.
     #[arg(short = 'c', long)]
     pub custom_network: Option<String>,
 
+    /// Publish to each of these networks in turn (comma-separated), building the template once.
+    /// Each network uses its own wallet daemon and account from the project or global config.
+    #[arg(
+        long,
+        value_name = "NETWORKS",
+        value_delimiter = ',',
+        value_parser = crate::cli::command::parse_network,
+        conflicts_with_all = ["custom_network", "wallet_daemon_url", "bundle"]
+    )]
+    pub networks: Vec<Network>,
+
     /// Confirm template publishing.
     /// If false, it will be asked.
     #[arg(short = 'y', long, default_value_t = false)]
@@ -46,14 +65,44 @@ pub struct PublishArgs {
     #[arg(short = 'f', long)]
     pub max_fee: Option<u64>,
 
+    /// Multiplier applied to the estimated fee to pad the maximum fee.
+    /// Ignored when --max-fee is set.
+    #[arg(long, default_value_t = 1.0, value_parser = crate::cli::command::fee_multiplier_parser)]
+    pub fee_multiplier: f64,
+
+    /// If no account is configured and the wallet has no default account, create an account with
+    /// this name, funded with free test coins. Test networks only.
+    #[arg(long, value_name = "NAME")]
+    pub create_account: Option<String>,
+
+    /// Retry publishing up to N times when the transaction is rejected for a reason that may be
+    /// temporary (e.g. it lost a race for its inputs), re-estimating the fee each time.
+    /// Permanent rejections are never retried.
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub retry_on_reject: u32,
+
+    /// Print the publish request sent to the wallet daemon (with the binary's size and hash in
+    /// place of its bytes) before asking for confirmation. Suppressed by --quiet.
+    #[arg(long, default_value_t = false)]
+    pub show_request: bool,
+
+    /// Publish without the fee, republish and metadata prompts, as for a local development daemon.
+    /// Implied on the `localnet` network unless --no-local is given.
+    #[arg(long, default_value_t = false, conflicts_with = "no_local")]
+    pub local: bool,
+
+    /// Keep the confirmation prompts even on the `localnet` network.
+    #[arg(long, default_value_t = false)]
+    pub no_local: bool,
+
     /// (Optional) Path to the compiled WASM binary.
     /// If not set, the project will be built before publishing.
     #[arg(long, alias = "bin")]
     pub binary: Option<PathBuf>,
 
     /// Wallet daemon JSON-RPC URL.
-    /// Overrides the value in tari.config.toml and global CLI config.
-    #[arg(long)]
+    /// Overrides the value in tari.config.toml and global CLI config for this run only.
+    #[arg(long, visible_alias = "network-url")]
     pub wallet_daemon_url: Option<url::Url>,
 
     /// After publishing, automatically submit metadata to a metadata server.
@@ -69,6 +118,188 @@ pub struct PublishArgs {
     /// By default the template is compiled with size optimizations (see `build_template`).
     #[arg(long, default_value_t = false)]
     pub no_cargo_opts: bool,
+
+    /// Skip publishing if the binary matches the one last published from this project on the
+    /// active network, and report the existing template address instead.
+    #[arg(long, default_value_t = false)]
+    pub skip_if_exists: bool,
+
+    /// Rebuild the template and refuse to publish unless its hash matches `tari.publish.lock`.
+    #[arg(long, default_value_t = false, conflicts_with = "binary")]
+    pub from_lockfile: bool,
+
+    /// Publish every template in a bundle archive created with `tari bundle`, instead of
+    /// building the crate at PATH. PATH is still used to find tari.config.toml.
+    #[arg(long, value_name = "ARCHIVE", conflicts_with_all = ["binary", "from_lockfile", "skip_if_exists"])]
+    pub bundle: Option<PathBuf>,
+
+    /// With --bundle, keep publishing the remaining templates after one fails and report all
+    /// failures at the end. Templates are still published one at a time.
+    #[arg(long, default_value_t = false, requires = "bundle")]
+    pub keep_going: bool,
+
+    /// With --yes, don't print the publish summary.
+    #[arg(short = 'q', long, default_value_t = false)]
+    pub quiet: bool,
+
+    /// Verbosity level, set from the global `--verbose` flag.
+    #[arg(skip)]
+    pub verbose: u8,
+
+    #[clap(flatten)]
+    pub cargo: CargoBuildArgs,
+}
+
+/// Target triple templates are built for unless `--target` says otherwise.
+pub const DEFAULT_WASM_TARGET: &str = "wasm32-unknown-unknown";
+
+/// Options for building a template, mostly passed through to `cargo build`.
+#[derive(Clone, Args, Debug, Default)]
+pub struct CargoBuildArgs {
+    /// Require `Cargo.lock` to be up to date (passed to `cargo build`).
+    #[arg(long, default_value_t = false)]
+    pub locked: bool,
+
+    /// Build without accessing the network (passed to `cargo build`).
+    #[arg(long, default_value_t = false)]
+    pub offline: bool,
+
+    /// Same as --locked and --offline together (passed to `cargo build`).
+    #[arg(long, default_value_t = false)]
+    pub frozen: bool,
+
+    /// Comma-separated cargo features of the template crate to enable.
+    #[arg(short = 'F', long, value_delimiter = ',')]
+    pub features: Vec<String>,
+
+    /// Do not enable the template crate's `default` feature.
+    #[arg(long, default_value_t = false)]
+    pub no_default_features: bool,
+
+    /// Enable the template crate's `default` feature even if `tari.config.toml` turns it off.
+    #[arg(long, default_value_t = false, conflicts_with = "no_default_features")]
+    pub default_features: bool,
+
+    /// Rebuild even if a cached build of the same sources and flags exists.
+    #[arg(long, default_value_t = false)]
+    pub force_build: bool,
+
+    /// Target triple to build the template for (passed to `cargo build`) [default:
+    /// wasm32-unknown-unknown, or the `[build]` target in `tari.config.toml`].
+    #[arg(long, value_name = "TRIPLE")]
+    pub target: Option<String>,
+
+    /// Write cargo's output to this file, whether or not the build succeeds. Implies
+    /// `--force-build`, so there always is output to write.
+    #[arg(long, value_name = "PATH")]
+    pub keep_build_output: Option<PathBuf>,
+}
+
+impl CargoBuildArgs {
+    /// Fills in the project's `[build]` defaults for what the flags leave unset: features (unless
+    /// `--features` is given), `no-default-features` (unless `--default-features` or
+    /// `--no-default-features` is given) and the target (unless `--target` is given).
+    pub fn apply_project_defaults(&mut self, project: &project::ProjectConfig) {
+        let Some(build) = project.build_settings() else {
+            return;
+        };
+        if self.features.is_empty() {
+            self.features = build.features.clone();
+        }
+        if !self.default_features {
+            self.no_default_features |= build.no_default_features;
+        }
+        if self.target.is_none() {
+            self.target = build.target.clone();
+        }
+    }
+
+    /// The target triple to build for: `--target`, else the project default, else
+    /// [`DEFAULT_WASM_TARGET`].
+    pub fn target(&self) -> &str {
+        self.target.as_deref().unwrap_or(DEFAULT_WASM_TARGET)
+    }
+
+    /// `--keep-build-output` names a single file, so it can't take the output of several builds.
+    pub fn ensure_single_build_output(&self, crates: usize) -> anyhow::Result<()> {
+        if self.keep_build_output.is_some() && crates > 1 {
+            return Err(anyhow!(
+                "--keep-build-output takes the output of a single build, but {crates} template crates were given. \
+                 Build them one at a time to keep each one's output."
+            ));
+        }
+        Ok(())
+    }
+
+    fn apply(&self, cmd: &mut Command) {
+        for (enabled, flag) in [
+            (self.locked, "--locked"),
+            (self.offline, "--offline"),
+            (self.frozen, "--frozen"),
+            (self.no_default_features, "--no-default-features"),
+        ] {
+            if enabled {
+                cmd.arg(flag);
+            }
+        }
+        if !self.features.is_empty() {
+            cmd.arg("--features").arg(self.features.join(","));
+        }
+    }
+
+    /// The flags that change the built binary, as part of the build cache key.
+    fn cache_flags(&self, optimize: bool) -> String {
+        format!(
+            "target={};optimize={optimize};no-default-features={};features={}",
+            self.target(),
+            self.no_default_features,
+            self.features.join(",")
+        )
+    }
+
+    /// Fails if a requested feature is not declared by the crate in `crate_dir`, listing the
+    /// available ones. Optional dependencies count as features; `dep/feature` entries are left
+    /// for cargo to check.
+    fn validate_features(&self, crate_dir: &Path) -> anyhow::Result<()> {
+        if self.features.is_empty() {
+            return Ok(());
+        }
+        let manifest = Manifest::from_path(crate_dir.join("Cargo.toml"))?;
+        let available: Vec<&str> = manifest
+            .features
+            .keys()
+            .map(String::as_str)
+            .chain(
+                manifest
+                    .dependencies
+                    .iter()
+                    .filter(|(_, dep)| dep.optional())
+                    .map(|(name, _)| name.as_str()),
+            )
+            .collect();
+        let unknown = unknown_features(&self.features, &available);
+        if !unknown.is_empty() {
+            return Err(anyhow!(
+                "Unknown feature(s) {} for template crate in {}. Available features: {}",
+                unknown.join(", "),
+                crate_dir.display(),
+                if available.is_empty() {
+                    "none".to_string()
+                } else {
+                    available.join(", ")
+                }
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn unknown_features<'a>(requested: &'a [String], available: &[&str]) -> Vec<&'a str> {
+    requested
+        .iter()
+        .map(|feature| feature.trim())
+        .filter(|feature| !feature.is_empty() && !feature.contains('/') && !available.contains(feature))
+        .collect()
 }
 
 /// Size-optimizing `[profile.release]` overrides applied to the WASM build via `cargo build
@@ -83,51 +314,568 @@ const CARGO_OPT_CONFIGS: &[&str] = &[
     "profile.release.strip=true",      // Strip symbols.
 ];
 
-pub async fn build_template(crate_dir: &Path, optimize: bool) -> anyhow::Result<PathBuf> {
+/// Whether to apply [`CARGO_OPT_CONFIGS`]: yes, unless `--no-cargo-opts` is given or the project's
+/// `[build]` section sets `cargo-opts = false`.
+pub fn use_cargo_opts(no_cargo_opts: bool, project: &project::ProjectConfig) -> bool {
+    !no_cargo_opts && project.build_settings().and_then(|b| b.cargo_opts).unwrap_or(true)
+}
+
+/// The crate directory for `--manifest-path`: the manifest's parent, after checking that it holds
+/// both the manifest and a project config.
+fn crate_dir_from_manifest(manifest_path: &Path) -> anyhow::Result<PathBuf> {
+    if manifest_path.file_name().is_none_or(|name| name != "Cargo.toml") {
+        return Err(anyhow!(
+            "--manifest-path must point to a Cargo.toml file, got {}",
+            manifest_path.display()
+        ));
+    }
+    if !manifest_path.is_file() {
+        return Err(anyhow!("No Cargo.toml found at {}", manifest_path.display()));
+    }
+    let crate_dir = match manifest_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if !crate_dir.join(project::CONFIG_FILE_NAME).is_file() {
+        return Err(anyhow!(
+            "No {} found next to {}. Run `tari init {}` first.",
+            project::CONFIG_FILE_NAME,
+            manifest_path.display(),
+            crate_dir.display()
+        ));
+    }
+    Ok(crate_dir.to_path_buf())
+}
+
+/// The library crate templates are written against.
+const TEMPLATE_LIB_CRATE: &str = "tari_template_lib";
+
+/// Whether the manifest's `[lib]` builds a `cdylib`, which is what produces the WASM binary.
+pub fn builds_cdylib(manifest: &Manifest) -> bool {
+    manifest
+        .lib
+        .as_ref()
+        .is_some_and(|lib| lib.crate_type.iter().any(|t| t == "cdylib"))
+}
+
+/// Fails early, with an explanation, when the crate in `crate_dir` doesn't look like a Tari
+/// template: it must build a `cdylib` and depend on `tari_template_lib` (possibly renamed).
+/// Otherwise building a regular library ends in a confusing cargo error or a missing binary.
+fn ensure_template_crate(crate_dir: &Path) -> anyhow::Result<()> {
+    let manifest = Manifest::from_path(crate_dir.join("Cargo.toml"))?;
+    let depends_on_template_lib = manifest
+        .dependencies
+        .iter()
+        .any(|(name, dep)| dep.package().unwrap_or(name) == TEMPLATE_LIB_CRATE);
+    let problem = if !builds_cdylib(&manifest) {
+        "does not set `crate-type = [\"cdylib\", ...]` under [lib]"
+    } else if !depends_on_template_lib {
+        "does not depend on tari_template_lib"
+    } else {
+        return Ok(());
+    };
+    Err(anyhow!(
+        "The crate in {} doesn't look like a Tari template: its Cargo.toml {problem}. Check that you \
+         are pointing at the template crate, not a helper library.",
+        crate_dir.display()
+    ))
+}
+
+/// Reads the package name from the template crate's `Cargo.toml`.
+pub fn template_crate_name(crate_dir: &Path) -> anyhow::Result<String> {
     let cargo_path = crate_dir.join("Cargo.toml");
     if !cargo_path.exists() {
         return Err(anyhow!("No Cargo.toml found at {}", cargo_path.display()));
     }
 
     let manifest = Manifest::from_path(&cargo_path)?;
-    let crate_name = manifest
+    Ok(manifest
         .package
         .ok_or_else(|| anyhow!("No [package] section in {}", cargo_path.display()))?
-        .name;
+        .name)
+}
+
+/// Builds the template crate, reusing the build cached for the same sources and flags when
+/// `cache` is given (unless `--force-build` is set).
+pub async fn build_template(
+    crate_dir: &Path,
+    optimize: bool,
+    cargo: &CargoBuildArgs,
+    cache: Option<&BuildCache>,
+) -> anyhow::Result<PathBuf> {
+    build_template_with_output(crate_dir, optimize, cargo, cache, OutputFormat::Text).await
+}
+
+/// Same as [`build_template`], but reports progress on stderr when `output` is JSON.
+pub async fn build_template_with_output(
+    crate_dir: &Path,
+    optimize: bool,
+    cargo: &CargoBuildArgs,
+    cache: Option<&BuildCache>,
+    output: OutputFormat,
+) -> anyhow::Result<PathBuf> {
+    let crate_name = template_crate_name(crate_dir)?;
 
-    let template_bin = loading!(
+    let cache_key = match cache {
+        Some(_) => Some(build_cache::source_hash(crate_dir, &cargo.cache_flags(optimize)).await?),
+        None => None,
+    };
+    // A cached build has no cargo output, so --keep-build-output always rebuilds.
+    if let (Some(cache), Some(key)) = (cache, &cache_key)
+        && !cargo.force_build
+        && cargo.keep_build_output.is_none()
+    {
+        let wasm_path = wasm_binary_path(&find_target_dir(crate_dir).await?, cargo.target(), &crate_name);
+        if cache.restore(key, &wasm_path).await? {
+            report(
+                output,
+                format!("♻️  Reusing cached build of {crate_name} (source hash {key})"),
+            );
+            return Ok(wasm_path);
+        }
+    }
+
+    let built = loading!(
+        @stream output.progress_stream(),
         format!("Building WASM template project **{}**", crate_name),
-        build_project(crate_dir, &crate_name, optimize).await
-    )?;
+        build_project(crate_dir, &crate_name, optimize, cargo).await
+    );
+    // Build errors come after the output is written; other errors stop before cargo runs.
+    if let Some(path) = &cargo.keep_build_output
+        && built
+            .as_ref()
+            .map_or_else(|e| e.downcast_ref::<BuildError>().is_some(), |_| true)
+    {
+        report(output, format!("📝 Build output saved to {}", path.display()));
+    }
+    let template_bin = built?;
+
+    if let (Some(cache), Some(key)) = (cache, &cache_key) {
+        let metadata_path = find_metadata_cbor(crate_dir, cargo.target()).await.ok();
+        cache.store(key, &template_bin, metadata_path.as_deref()).await?;
+        report(
+            output,
+            format!("🔨 Fresh build of {crate_name} cached (source hash {key})"),
+        );
+    }
 
     Ok(template_bin)
 }
 
+/// Prints a progress line, on stderr when stdout is reserved for JSON.
+fn report(output: OutputFormat, line: String) {
+    if output.is_json() {
+        eprintln!("{line}");
+    } else {
+        println!("{line}");
+    }
+}
+
 /// `tari publish` delegates to `tari template publish` — they behave identically.
 pub async fn handle(
     config: Config,
+    base_dir: &Path,
     network_override: Option<Network>,
     api_key: Option<Hidden<String>>,
-    args: PublishArgs,
+    mut args: PublishArgs,
+    cancel: CancellationToken,
 ) -> anyhow::Result<()> {
+    // Read once here: every network or bundled template reuses the account.
+    args.account.read_account_source().await?;
+    if let Some(manifest_path) = args.manifest_path.take() {
+        args.path = crate_dir_from_manifest(&manifest_path)?;
+    }
+    if let Some(bundle) = args.bundle.take() {
+        return publish_bundle(config, base_dir, network_override, api_key, args, &bundle, cancel).await;
+    }
+    if !args.networks.is_empty() {
+        if let Some(network) = network_override {
+            return Err(anyhow!(
+                "--networks conflicts with --network '{network}'. Pass only one of them."
+            ));
+        }
+        return publish_to_networks(config, base_dir, api_key, args, cancel).await;
+    }
     let template_args = TemplatePublishArgs {
         path: args.path,
         account: args.account,
         custom_network: args.custom_network,
         yes: args.yes,
         max_fee: args.max_fee,
+        fee_multiplier: args.fee_multiplier,
+        retry_on_reject: args.retry_on_reject,
+        show_request: args.show_request,
+        local: args.local,
+        no_local: args.no_local,
+        create_account: args.create_account,
         binary: args.binary,
         wallet_daemon_url: args.wallet_daemon_url,
         publish_metadata: args.publish_metadata,
         metadata_server_url: args.metadata_server_url,
         no_cargo_opts: args.no_cargo_opts,
+        skip_if_exists: args.skip_if_exists,
+        from_lockfile: args.from_lockfile,
+        bundled: None,
+        quiet: args.quiet,
+        verbose: args.verbose,
+        cargo: args.cargo,
+    };
+    crate::cli::commands::template::publish::handle(config, base_dir, network_override, api_key, template_args, cancel)
+        .await
+        .map(|_| ())
+}
+
+/// Publishes the template to each of `--networks` in turn, stopping at the first failure, then
+/// prints a table of the results. The first publish builds the template; the others restore that
+/// build from the build cache.
+async fn publish_to_networks(
+    config: Config,
+    base_dir: &Path,
+    api_key: Option<Hidden<String>>,
+    args: PublishArgs,
+    cancel: CancellationToken,
+) -> anyhow::Result<()> {
+    let total = args.networks.len();
+    let mut results = Vec::with_capacity(total);
+    for (i, &network) in args.networks.iter().enumerate() {
+        if cancel.is_cancelled() {
+            break;
+        }
+        println!("🌐 Publishing to {network} ({} of {total})", i + 1);
+        let mut cargo = args.cargo.clone();
+        // Only the first publish may force a rebuild, so every network gets the same binary.
+        cargo.force_build &= i == 0;
+        let template_args = TemplatePublishArgs {
+            path: args.path.clone(),
+            account: args.account.clone(),
+            custom_network: None,
+            yes: args.yes,
+            max_fee: args.max_fee,
+            fee_multiplier: args.fee_multiplier,
+            retry_on_reject: args.retry_on_reject,
+            show_request: args.show_request,
+            local: args.local,
+            no_local: args.no_local,
+            create_account: args.create_account.clone(),
+            binary: args.binary.clone(),
+            wallet_daemon_url: None,
+            publish_metadata: args.publish_metadata,
+            metadata_server_url: args.metadata_server_url.clone(),
+            no_cargo_opts: args.no_cargo_opts,
+            skip_if_exists: args.skip_if_exists,
+            from_lockfile: args.from_lockfile,
+            bundled: None,
+            quiet: args.quiet,
+            verbose: args.verbose,
+            cargo,
+        };
+        let result = crate::cli::commands::template::publish::handle(
+            config.clone(),
+            base_dir,
+            Some(network),
+            api_key.clone(),
+            template_args,
+            cancel.clone(),
+        )
+        .await
+        .with_context(|| format!("publishing to {network}"));
+        let failed = result.is_err();
+        results.push((network, result));
+        if failed {
+            break;
+        }
+    }
+
+    println!("📋 Publish report:");
+    println!("   {:<12} {:<73} Fee", "Network", "Template address");
+    let not_attempted = total - results.len();
+    for (network, result) in &results {
+        let network = network.to_string();
+        match result {
+            Ok(PublishResult::Published { address, fee_charged }) => {
+                println!("   {network:<12} {:<73} {fee_charged}", address.to_string())
+            },
+            Ok(PublishResult::Skipped { address }) => {
+                println!("   {network:<12} {:<73} (already published)", address.to_string())
+            },
+            Err(e) => println!("   {network:<12} ❌ {e:#}"),
+        }
+    }
+    for network in &args.networks[results.len()..] {
+        println!("   {:<12} ⏭️  not attempted", network.to_string());
+    }
+
+    // Publishing stops at the first failure, so only the last result can be an error.
+    if let Some((_, Err(error))) = results.pop() {
+        return Err(error);
+    }
+    if not_attempted > 0 {
+        return Err(anyhow!(
+            "Cancelled with {not_attempted} of {total} networks not published"
+        ));
+    }
+    Ok(())
+}
+
+/// Publishes each template in a bundle archive, one after the other.
+async fn publish_bundle(
+    config: Config,
+    base_dir: &Path,
+    network_override: Option<Network>,
+    api_key: Option<Hidden<String>>,
+    args: PublishArgs,
+    bundle: &Path,
+    cancel: CancellationToken,
+) -> anyhow::Result<()> {
+    let extract_dir = tempfile::tempdir().context("creating a directory to unpack the bundle")?;
+    let templates = {
+        let bundle = bundle.to_path_buf();
+        let dest = extract_dir.path().to_path_buf();
+        tokio::task::spawn_blocking(move || extract_bundle(&bundle, &dest)).await??
+    };
+    println!(
+        "📦 Publishing {} template(s) from {}",
+        templates.len(),
+        bundle.display()
+    );
+
+    let total = templates.len();
+    let mut results = Vec::with_capacity(total);
+    for (template, binary) in templates {
+        if cancel.is_cancelled() {
+            break;
+        }
+        let name = template.name;
+        println!("📦 Template {name}");
+        let template_args = TemplatePublishArgs {
+            path: args.path.clone(),
+            account: args.account.clone(),
+            custom_network: args.custom_network.clone(),
+            yes: args.yes,
+            max_fee: args.max_fee,
+            fee_multiplier: args.fee_multiplier,
+            retry_on_reject: args.retry_on_reject,
+            show_request: args.show_request,
+            local: args.local,
+            no_local: args.no_local,
+            create_account: args.create_account.clone(),
+            binary: Some(binary),
+            wallet_daemon_url: args.wallet_daemon_url.clone(),
+            publish_metadata: false,
+            metadata_server_url: None,
+            no_cargo_opts: args.no_cargo_opts,
+            skip_if_exists: args.skip_if_exists,
+            from_lockfile: false,
+            bundled: Some(BundledMetadata {
+                metadata_hash: template.metadata_hash,
+            }),
+            quiet: args.quiet,
+            verbose: args.verbose,
+            cargo: args.cargo.clone(),
+        };
+        let result = crate::cli::commands::template::publish::handle(
+            config.clone(),
+            base_dir,
+            network_override,
+            api_key.clone(),
+            template_args,
+            cancel.clone(),
+        )
+        .await
+        .map(|_| ())
+        .with_context(|| format!("publishing template '{name}' from bundle"));
+        match result {
+            Err(error) if !args.keep_going => return Err(error),
+            result => results.push((name, result)),
+        }
+    }
+
+    let skipped = total - results.len();
+    if !args.keep_going {
+        if skipped > 0 {
+            return Err(anyhow!("Cancelled with {skipped} of {total} templates not published"));
+        }
+        return Ok(());
+    }
+
+    println!("📋 Bundle publish report:");
+    let mut failed = 0;
+    for (name, result) in &results {
+        match result {
+            Ok(()) => println!("   ✅ {name}"),
+            Err(error) => {
+                failed += 1;
+                println!("   ❌ {name}: {error:#}");
+            },
+        }
+    }
+    if skipped > 0 {
+        println!("   ⏭️  {skipped} template(s) not attempted (cancelled)");
+    }
+    if failed > 0 || skipped > 0 {
+        return Err(anyhow!("{} of {total} templates were not published", failed + skipped));
+    }
+    Ok(())
+}
+
+/// A failed template build. The full build log is kept out of the message; `tari build` and
+/// `tari publish` print it with `--verbose` (see [`print_build_log`]).
+#[derive(Error, Debug)]
+pub enum BuildError {
+    #[error(
+        "Cargo.lock of template crate `{crate_name}` in {dir:?} is out of date, and --locked or --frozen forbids \
+         updating it. Run `cargo update` in the crate (or its workspace) and commit the updated Cargo.lock."
+    )]
+    LockfileOutdated {
+        crate_name: String,
+        dir: PathBuf,
+        log: String,
+    },
+    #[error(
+        "Failed to build template crate `{crate_name}` in {dir:?}{}{}",
+        expected_binary_hint(.expected_binary),
+        first_error_hint(.first_error)
+    )]
+    Failed {
+        crate_name: String,
+        dir: PathBuf,
+        expected_binary: Option<PathBuf>,
+        first_error: Option<CompilerError>,
+        log: String,
+    },
+    #[error("Template crate `{crate_name}` built, but its binary is not present at {path:?}")]
+    MissingBinary {
+        crate_name: String,
+        path: PathBuf,
+        log: String,
+    },
+}
+
+impl BuildError {
+    pub fn log(&self) -> &str {
+        match self {
+            BuildError::LockfileOutdated { log, .. }
+            | BuildError::Failed { log, .. }
+            | BuildError::MissingBinary { log, .. } => log,
+        }
+    }
+}
+
+/// The first error reported by rustc during a build.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompilerError {
+    /// Crate (target) the error was reported for; may be a dependency of the template.
+    pub crate_name: String,
+    /// The error as rustc renders it on the terminal.
+    pub rendered: String,
+}
+
+fn expected_binary_hint(expected_binary: &Option<PathBuf>) -> String {
+    expected_binary
+        .as_ref()
+        .map(|path| format!(" (expected binary at {path:?})"))
+        .unwrap_or_default()
+}
+
+fn first_error_hint(first_error: &Option<CompilerError>) -> String {
+    match first_error {
+        Some(error) => format!(
+            "\n\nFirst error, in crate `{}`:\n{}\nRun with --verbose for the full build log.",
+            error.crate_name,
+            error.rendered.trim_end()
+        ),
+        None => "\n\nRun with --verbose for the full build log.".to_string(),
+    }
+}
+
+/// Prints the full build log to stderr if `error` was caused by a failed build.
+pub fn print_build_log(error: &anyhow::Error) {
+    if let Some(build_error) = error.chain().find_map(|cause| cause.downcast_ref::<BuildError>()) {
+        eprintln!("📜 Build log:\n{}", build_error.log());
+    }
+}
+
+/// Parses cargo's `--message-format=json` output. Returns the first rustc error, together with a
+/// log of every rendered compiler message.
+fn parse_cargo_messages(stdout: &str) -> (Option<CompilerError>, String) {
+    let mut first_error = None;
+    let mut log = String::new();
+    for line in stdout.lines() {
+        let Ok(message) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        if message["reason"] != "compiler-message" {
+            continue;
+        }
+        let Some(rendered) = message["message"]["rendered"].as_str() else {
+            continue;
+        };
+        log.push_str(rendered);
+        if first_error.is_none() && message["message"]["level"] == "error" {
+            first_error = Some(CompilerError {
+                crate_name: message["target"]["name"].as_str().unwrap_or("unknown").to_string(),
+                rendered: rendered.to_string(),
+            });
+        }
+    }
+    (first_error, log)
+}
+
+/// Path of the binary cargo builds for crate `name` and the target `triple`.
+fn wasm_binary_path(target_dir: &Path, triple: &str, name: &str) -> PathBuf {
+    let wasm_name = name.replace('-', "_");
+    target_dir
+        .join(triple)
+        .join("release")
+        .join(format!("{wasm_name}.wasm"))
+}
+
+/// Cargo's error when `--locked`/`--frozen` is passed and `Cargo.lock` would need changes.
+fn is_lockfile_outdated(stderr: &str) -> bool {
+    stderr.contains("needs to be updated but --locked was passed")
+        || stderr.contains("needs to be updated but --frozen was passed")
+}
+
+/// Fails if rustup is available and reports that `triple` is not installed for the toolchain
+/// `dir` builds with (its `rust-toolchain.toml`, if any). Without rustup the check is skipped and
+/// cargo reports a missing target itself.
+async fn ensure_target_installed(dir: &Path, triple: &str) -> anyhow::Result<()> {
+    let Ok(output) = Command::new("rustup")
+        .args(["target", "list", "--installed"])
+        .current_dir(dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .await
+    else {
+        return Ok(());
     };
-    crate::cli::commands::template::publish::handle(config, network_override, api_key, template_args).await
+    if output.status.success() && !is_target_listed(&String::from_utf8_lossy(&output.stdout), triple) {
+        return Err(anyhow!(
+            "Rust target {triple} is not installed. Install it with `rustup target add {triple}`."
+        ));
+    }
+    Ok(())
 }
 
-async fn build_project(dir: &Path, name: &str, optimize: bool) -> anyhow::Result<PathBuf> {
+/// Whether `triple` is one of the lines of `rustup target list --installed`.
+fn is_target_listed(installed: &str, triple: &str) -> bool {
+    installed.lines().any(|line| line.trim() == triple)
+}
+
+/// Runs `cargo build` for the WASM target in `dir` and returns the path of the produced binary.
+/// Cargo's output is captured, so concurrent builds don't interleave on the terminal.
+pub async fn build_project(dir: &Path, name: &str, optimize: bool, cargo: &CargoBuildArgs) -> anyhow::Result<PathBuf> {
+    ensure_template_crate(dir)?;
+    cargo.validate_features(dir)?;
+    ensure_target_installed(dir, cargo.target()).await?;
+
     let mut cmd = Command::new("cargo");
-    cmd.arg("build").arg("--target=wasm32-unknown-unknown").arg("--release");
+    cmd.arg("build")
+        .arg(format!("--target={}", cargo.target()))
+        .arg("--release")
+        .arg("--message-format=json");
+    cargo.apply(&mut cmd);
 
     if optimize {
         for config in CARGO_OPT_CONFIGS {
@@ -140,28 +888,50 @@ async fn build_project(dir: &Path, name: &str, optimize: bool) -> anyhow::Result
     let process = cmd.spawn()?;
 
     let output = process.wait_with_output().await?;
+    let (first_error, compiler_log) = parse_cargo_messages(&String::from_utf8_lossy(&output.stdout));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let log = format!("{compiler_log}{stderr}");
+    if let Some(path) = &cargo.keep_build_output {
+        fs::write(path, &log)
+            .await
+            .with_context(|| format!("writing build output to {}", path.display()))?;
+    }
+
+    if !output.status.success() && is_lockfile_outdated(&stderr) {
+        return Err(BuildError::LockfileOutdated {
+            crate_name: name.to_string(),
+            dir: dir.to_path_buf(),
+            log,
+        }
+        .into());
+    }
 
     if !output.status.success() {
-        return Err(anyhow!(
-            "Failed to build project: {dir:?}\nBuild Output:\n\n{}",
-            String::from_utf8_lossy(&output.stderr)
-        ));
+        let expected_binary = find_target_dir(dir)
+            .await
+            .ok()
+            .map(|target_dir| wasm_binary_path(&target_dir, cargo.target(), name));
+        return Err(BuildError::Failed {
+            crate_name: name.to_string(),
+            dir: dir.to_path_buf(),
+            expected_binary,
+            first_error,
+            log,
+        }
+        .into());
     }
 
     // Find the target directory (may be in a parent workspace)
     let target_dir = find_target_dir(dir).await?;
-    let wasm_name = name.replace('-', "_");
-    let output_bin = target_dir
-        .join("wasm32-unknown-unknown")
-        .join("release")
-        .join(format!("{wasm_name}.wasm"));
+    let output_bin = wasm_binary_path(&target_dir, cargo.target(), name);
 
     if !util::file_exists(&output_bin).await? {
-        return Err(anyhow!(
-            "Binary is not present after build at {:?}\n\nBuild Output:\n{}",
-            output_bin,
-            String::from_utf8_lossy(&output.stderr)
-        ));
+        return Err(BuildError::MissingBinary {
+            crate_name: name.to_string(),
+            path: output_bin,
+            log,
+        }
+        .into());
     }
 
     Ok(output_bin)
@@ -193,10 +963,10 @@ pub async fn find_target_dir(dir: &Path) -> anyhow::Result<PathBuf> {
 
 const METADATA_CBOR_FILENAME: &str = "template_metadata.cbor";
 
-/// Find the most recently generated metadata CBOR file in the build output.
-pub async fn find_metadata_cbor(project_dir: &Path) -> anyhow::Result<PathBuf> {
+/// Find the most recently generated metadata CBOR file in the build output for the target `triple`.
+pub async fn find_metadata_cbor(project_dir: &Path, triple: &str) -> anyhow::Result<PathBuf> {
     let target_dir = find_target_dir(project_dir).await?;
-    let build_dir = target_dir.join("wasm32-unknown-unknown").join("release").join("build");
+    let build_dir = target_dir.join(triple).join("release").join("build");
 
     if !build_dir.exists() {
         return Err(anyhow!(
@@ -255,7 +1025,7 @@ pub async fn load_project_config(project_folder: &Path) -> anyhow::Result<projec
                     error
                 )
             })?;
-            return toml::from_str::<project::ProjectConfig>(content.as_str()).context("parsing config toml");
+            return util::parse_toml_with_env::<project::ProjectConfig>(&content).context("parsing config toml");
         }
         if !search_dir.pop() {
             break;
@@ -277,6 +1047,36 @@ pub fn resolve_active_network(
         .unwrap_or_default()
 }
 
+/// Applies `--custom-network` on top of the `--network` override. The named network must be
+/// configured in the project or global config, and must not contradict `--network`.
+pub fn resolve_custom_network(
+    network_override: Option<Network>,
+    custom_network: Option<&str>,
+    project: &project::ProjectConfig,
+    global: &Config,
+) -> anyhow::Result<Option<Network>> {
+    let Some(name) = custom_network else {
+        return Ok(network_override);
+    };
+    let network: Network = name
+        .parse()
+        .map_err(|e| anyhow!("Invalid --custom-network '{name}': {e}"))?;
+    if let Some(cli_network) = network_override
+        && cli_network != network
+    {
+        return Err(anyhow!(
+            "--custom-network '{name}' conflicts with --network '{cli_network}'. Pass only one of them."
+        ));
+    }
+    if !project.has_network(network) && !global.has_network(network) {
+        return Err(anyhow!(
+            "--custom-network '{name}' has no matching [networks.{}] section in tari.config.toml or the global config.",
+            network.as_key_str()
+        ));
+    }
+    Ok(Some(network))
+}
+
 /// Resolve wallet-daemon URL for the active network. Precedence: CLI flag > project > global > default.
 pub fn resolve_wallet_daemon_url(
     cli_override: Option<&url::Url>,
@@ -292,3 +1092,160 @@ pub fn resolve_wallet_daemon_url(
             url::Url::parse(project::DEFAULT_WALLET_DAEMON_URL).expect("default wallet daemon URL is valid")
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_template_crates_are_rejected_before_building() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let check = |manifest: &str| {
+            std::fs::write(temp_dir.path().join("Cargo.toml"), manifest).unwrap();
+            ensure_template_crate(temp_dir.path())
+        };
+        let package = "[package]\nname = \"a\"\nversion = \"0.1.0\"\n\n";
+        let lib_only = format!("{package}[dependencies]\ntari_template_lib = \"0.1\"\n");
+        let error = check(&lib_only).unwrap_err();
+        assert!(error.to_string().contains("cdylib"), "got: {error}");
+
+        let no_template_lib = format!("{package}[lib]\ncrate-type = [\"cdylib\"]\n");
+        let error = check(&no_template_lib).unwrap_err();
+        assert!(error.to_string().contains("tari_template_lib"), "got: {error}");
+
+        let renamed = format!(
+            "{package}[lib]\ncrate-type = [\"cdylib\", \"lib\"]\n\n[dependencies]\ntemplate = {{ package = \"tari_template_lib\", version = \"0.1\" }}\n"
+        );
+        check(&renamed).unwrap();
+    }
+
+    #[test]
+    fn manifest_path_resolves_to_its_crate_dir() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let manifest = temp_dir.path().join("Cargo.toml");
+        assert!(crate_dir_from_manifest(&manifest).is_err());
+        std::fs::write(&manifest, "").unwrap();
+        let error = crate_dir_from_manifest(&manifest).unwrap_err();
+        assert!(error.to_string().contains(project::CONFIG_FILE_NAME), "got: {error}");
+        std::fs::write(temp_dir.path().join(project::CONFIG_FILE_NAME), "").unwrap();
+        assert_eq!(crate_dir_from_manifest(&manifest).unwrap(), temp_dir.path());
+        assert!(crate_dir_from_manifest(temp_dir.path()).is_err());
+    }
+
+    #[test]
+    fn project_build_defaults_fill_unset_flags() {
+        let project: project::ProjectConfig =
+            toml::from_str("[build]\nfeatures = [\"admin\"]\ntarget = \"wasm32v1-none\"\ncargo-opts = false\n")
+                .unwrap();
+        let mut cargo = CargoBuildArgs::default();
+        cargo.apply_project_defaults(&project);
+        assert_eq!(cargo.features, ["admin"]);
+        assert_eq!(cargo.target(), "wasm32v1-none");
+        assert!(!use_cargo_opts(false, &project));
+
+        let mut cargo = CargoBuildArgs {
+            features: vec!["debug".to_string()],
+            target: Some(DEFAULT_WASM_TARGET.to_string()),
+            ..CargoBuildArgs::default()
+        };
+        cargo.apply_project_defaults(&project);
+        assert_eq!(cargo.features, ["debug"]);
+        assert_eq!(cargo.target(), DEFAULT_WASM_TARGET);
+
+        let project: project::ProjectConfig = toml::from_str("[build]\nno-default-features = true\n").unwrap();
+        let mut cargo = CargoBuildArgs::default();
+        cargo.apply_project_defaults(&project);
+        assert!(cargo.no_default_features);
+        let mut cargo = CargoBuildArgs {
+            default_features: true,
+            ..CargoBuildArgs::default()
+        };
+        cargo.apply_project_defaults(&project);
+        assert!(!cargo.no_default_features);
+        assert!(use_cargo_opts(false, &project::ProjectConfig::default()));
+        assert!(!use_cargo_opts(true, &project::ProjectConfig::default()));
+    }
+
+    #[test]
+    fn unknown_features_skips_dependency_features() {
+        let requested = vec![
+            "burnable".to_string(),
+            " mintable ".to_string(),
+            "serde/std".to_string(),
+            "bogus".to_string(),
+        ];
+        assert_eq!(unknown_features(&requested, &["burnable", "mintable"]), vec!["bogus"]);
+    }
+
+    #[test]
+    fn detects_outdated_lockfile() {
+        assert!(is_lockfile_outdated(
+            "error: the lock file /tmp/counter/Cargo.lock needs to be updated but --locked was passed to prevent this"
+        ));
+        assert!(!is_lockfile_outdated("error: could not compile `counter`"));
+    }
+
+    #[test]
+    fn finds_installed_target() {
+        let installed = "wasm32-unknown-unknown\nx86_64-unknown-linux-gnu\n";
+        assert!(is_target_listed(installed, "wasm32-unknown-unknown"));
+        assert!(!is_target_listed(installed, "wasm32-wasip1"));
+    }
+
+    #[test]
+    fn cargo_messages_yield_first_error_and_log() {
+        let stdout = [
+            r#"{"reason":"compiler-artifact","target":{"name":"serde"}}"#,
+            r#"{"reason":"compiler-message","target":{"name":"counter"},"message":{"level":"warning","rendered":"warning: unused variable\n"}}"#,
+            r#"{"reason":"compiler-message","target":{"name":"counter"},"message":{"level":"error","rendered":"error[E0425]: cannot find value `x`\n"}}"#,
+            r#"{"reason":"compiler-message","target":{"name":"counter"},"message":{"level":"error","rendered":"error: aborting\n"}}"#,
+            "not json",
+        ]
+        .join("\n");
+
+        let (first_error, log) = parse_cargo_messages(&stdout);
+
+        assert_eq!(
+            first_error,
+            Some(CompilerError {
+                crate_name: "counter".to_string(),
+                rendered: "error[E0425]: cannot find value `x`\n".to_string(),
+            })
+        );
+        assert_eq!(
+            log,
+            "warning: unused variable\nerror[E0425]: cannot find value `x`\nerror: aborting\n"
+        );
+    }
+
+    #[test]
+    fn custom_network_passes_through_network_override_when_unset() {
+        let project = project::ProjectConfig::default();
+        let global = Config::default();
+        let network = resolve_custom_network(Some(Network::LocalNet), None, &project, &global).unwrap();
+        assert_eq!(network, Some(Network::LocalNet));
+    }
+
+    #[test]
+    fn custom_network_selects_configured_network() {
+        let project = project::ProjectConfig::default();
+        let global = Config::default();
+        let network = resolve_custom_network(None, Some("esmeralda"), &project, &global).unwrap();
+        assert_eq!(network, Some(Network::Esmeralda));
+    }
+
+    #[test]
+    fn custom_network_rejects_conflicting_network_override() {
+        let project = project::ProjectConfig::default();
+        let global = Config::default();
+        let err = resolve_custom_network(Some(Network::LocalNet), Some("esmeralda"), &project, &global).unwrap_err();
+        assert!(err.to_string().contains("conflicts with --network"), "got: {err}");
+    }
+
+    #[test]
+    fn custom_network_rejects_unknown_name() {
+        let project = project::ProjectConfig::default();
+        let global = Config::default();
+        assert!(resolve_custom_network(None, Some("bogus"), &project, &global).is_err());
+    }
+}
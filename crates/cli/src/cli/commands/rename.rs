@@ -0,0 +1,136 @@
+// Copyright 2024 The Tari Project
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, anyhow};
+use clap::Parser;
+use tokio::fs;
+
+use crate::cli::command::project_name_parser;
+
+#[derive(Clone, Parser, Debug)]
+pub struct RenameArgs {
+    /// Path to the template crate directory.
+    pub path: PathBuf,
+
+    /// New name of the template crate.
+    #[arg(value_parser = project_name_parser)]
+    pub new_name: String,
+}
+
+pub async fn handle(args: RenameArgs) -> anyhow::Result<()> {
+    let new_dir = rename_crate(&args.path, &args.new_name).await?;
+    println!(
+        "✅ Renamed template crate to **{}** at {}",
+        args.new_name,
+        new_dir.display()
+    );
+    Ok(())
+}
+
+/// Renames the crate's package (and a matching `[lib] name`) to `new_name`, preserving the rest
+/// of `Cargo.toml`. The directory is renamed too when it is named after the package.
+/// Returns the crate's (possibly new) directory.
+async fn rename_crate(crate_dir: &Path, new_name: &str) -> anyhow::Result<PathBuf> {
+    let manifest_path = crate_dir.join("Cargo.toml");
+    let content = fs::read_to_string(&manifest_path)
+        .await
+        .with_context(|| format!("reading {}", manifest_path.display()))?;
+    let mut doc = content
+        .parse::<toml_edit::DocumentMut>()
+        .with_context(|| format!("parsing {}", manifest_path.display()))?;
+
+    let old_name = doc
+        .get("package")
+        .and_then(|package| package.get("name"))
+        .and_then(|name| name.as_str())
+        .ok_or_else(|| anyhow!("No [package] name in {}", manifest_path.display()))?
+        .to_string();
+    if old_name == new_name {
+        return Err(anyhow!("Template crate is already named {new_name}"));
+    }
+
+    let new_dir = match crate_dir.file_name() {
+        Some(dir_name) if dir_name == old_name.as_str() => crate_dir.with_file_name(new_name),
+        _ => crate_dir.to_path_buf(),
+    };
+    if new_dir != crate_dir && fs::try_exists(&new_dir).await? {
+        return Err(anyhow!("{} already exists", new_dir.display()));
+    }
+
+    replace_str(&mut doc["package"]["name"], new_name);
+    if let Some(lib_name) = doc.get_mut("lib").and_then(|lib| lib.get_mut("name"))
+        && lib_name.as_str() == Some(old_name.as_str())
+    {
+        replace_str(lib_name, new_name);
+    }
+    fs::write(&manifest_path, doc.to_string())
+        .await
+        .with_context(|| format!("writing {}", manifest_path.display()))?;
+
+    if new_dir != crate_dir {
+        fs::rename(crate_dir, &new_dir)
+            .await
+            .with_context(|| format!("renaming {} to {}", crate_dir.display(), new_dir.display()))?;
+    }
+
+    Ok(new_dir)
+}
+
+/// Replaces a string value, keeping its surrounding whitespace and comments.
+fn replace_str(item: &mut toml_edit::Item, value: &str) {
+    match item.as_value_mut() {
+        Some(existing) => {
+            let decor = existing.decor().clone();
+            *existing = value.into();
+            *existing.decor_mut() = decor;
+        },
+        None => *item = toml_edit::value(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const MANIFEST: &str = r#"[package]
+name = "old_token" # keep me
+version = "0.1.0"
+
+[lib]
+name = "old_token"
+crate-type = ["cdylib", "lib"]
+"#;
+
+    #[tokio::test]
+    async fn renames_package_and_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let crate_dir = temp_dir.path().join("old_token");
+        std::fs::create_dir_all(&crate_dir).unwrap();
+        std::fs::write(crate_dir.join("Cargo.toml"), MANIFEST).unwrap();
+
+        let new_dir = rename_crate(&crate_dir, "new_token").await.unwrap();
+
+        assert_eq!(new_dir, temp_dir.path().join("new_token"));
+        assert!(!crate_dir.exists());
+        let manifest = std::fs::read_to_string(new_dir.join("Cargo.toml")).unwrap();
+        assert_eq!(manifest, MANIFEST.replace("\"old_token\"", "\"new_token\""));
+    }
+
+    #[tokio::test]
+    async fn refuses_to_overwrite_existing_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let crate_dir = temp_dir.path().join("old_token");
+        std::fs::create_dir_all(&crate_dir).unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("new_token")).unwrap();
+        std::fs::write(crate_dir.join("Cargo.toml"), MANIFEST).unwrap();
+
+        let err = rename_crate(&crate_dir, "new_token").await.unwrap_err();
+
+        assert!(err.to_string().contains("already exists"), "got: {err}");
+        let manifest = std::fs::read_to_string(crate_dir.join("Cargo.toml")).unwrap();
+        assert_eq!(manifest, MANIFEST);
+    }
+}
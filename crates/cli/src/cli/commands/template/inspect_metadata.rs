@@ -8,7 +8,8 @@ use clap::Parser;
 use dialoguer::Confirm;
 use tari_ootle_template_metadata::{FunctionDoc, TemplateMetadata};
 
-use crate::cli::commands::publish::{build_template, decode_metadata_cbor, find_metadata_cbor};
+use crate::cli::commands::publish::{DEFAULT_WASM_TARGET, build_template, decode_metadata_cbor, find_metadata_cbor};
+use crate::cli::util::{OutputFormat, Warnings};
 
 #[derive(Clone, Parser, Debug)]
 pub struct InspectMetadataArgs {
@@ -33,7 +34,7 @@ pub struct InspectMetadataArgs {
 pub async fn handle(args: InspectMetadataArgs) -> anyhow::Result<()> {
     let cbor_path = match args.path {
         Some(p) => p,
-        None => find_metadata_cbor(&args.project_dir).await?,
+        None => find_metadata_cbor(&args.project_dir, DEFAULT_WASM_TARGET).await?,
     };
 
     if !cbor_path.exists() {
@@ -42,6 +43,13 @@ pub async fn handle(args: InspectMetadataArgs) -> anyhow::Result<()> {
 
     eprintln!("📄 Reading metadata from {}", cbor_path.display());
 
+    let output = if args.json {
+        OutputFormat::Json
+    } else {
+        OutputFormat::Text
+    };
+    let mut warnings = Warnings::new(output).on_stderr();
+
     let mut cbor_bytes = std::fs::read(&cbor_path).context("reading metadata CBOR file")?;
     let mut metadata = decode_metadata_cbor(&cbor_bytes)?;
 
@@ -56,7 +64,7 @@ pub async fn handle(args: InspectMetadataArgs) -> anyhow::Result<()> {
         };
         match tari_ootle_template_metadata::from_cargo_toml(&cargo_toml_path) {
             Ok(current) if current != built_without_functions => {
-                eprintln!("⚠️  Built metadata does not match Cargo.toml (metadata may be stale)");
+                warnings.warn("Built metadata does not match Cargo.toml (metadata may be stale)");
                 // Skip the interactive prompt in JSON mode so stdout stays pipeable.
                 let rebuild = !args.json
                     && Confirm::new()
@@ -64,8 +72,8 @@ pub async fn handle(args: InspectMetadataArgs) -> anyhow::Result<()> {
                         .default(true)
                         .interact()?;
                 if rebuild {
-                    build_template(&args.project_dir, true).await?;
-                    let new_cbor_path = find_metadata_cbor(&args.project_dir).await?;
+                    build_template(&args.project_dir, true, &Default::default(), None).await?;
+                    let new_cbor_path = find_metadata_cbor(&args.project_dir, DEFAULT_WASM_TARGET).await?;
                     cbor_bytes = std::fs::read(&new_cbor_path).context("reading rebuilt metadata CBOR")?;
                     metadata = decode_metadata_cbor(&cbor_bytes)?;
                     eprintln!("✅ Metadata rebuilt");
@@ -73,7 +81,7 @@ pub async fn handle(args: InspectMetadataArgs) -> anyhow::Result<()> {
             },
             Ok(_) => {},
             Err(e) => {
-                eprintln!("⚠️  Could not read Cargo.toml metadata for freshness check: {e}");
+                warnings.warn(format!("Could not read Cargo.toml metadata for freshness check: {e}"));
             },
         }
     }
@@ -81,7 +89,7 @@ pub async fn handle(args: InspectMetadataArgs) -> anyhow::Result<()> {
     let hash = metadata.hash().context("computing metadata hash")?;
 
     if args.json {
-        let json = metadata.to_json().context("serializing to JSON")?;
+        let json = with_warnings(&metadata.to_json().context("serializing to JSON")?, warnings.into_vec())?;
         println!("{json}");
         eprintln!("\nMetadata hash: {hash}");
     } else {
@@ -94,13 +102,25 @@ pub async fn handle(args: InspectMetadataArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Adds a `warnings` array to the metadata JSON object, unless there are none.
+fn with_warnings(json: &str, warnings: Vec<String>) -> anyhow::Result<String> {
+    if warnings.is_empty() {
+        return Ok(json.to_string());
+    }
+    let mut value: serde_json::Value = serde_json::from_str(json).context("parsing metadata JSON")?;
+    if let Some(object) = value.as_object_mut() {
+        object.insert("warnings".to_string(), warnings.into());
+    }
+    Ok(serde_json::to_string_pretty(&value)?)
+}
+
 fn print_function_docs(functions: &[FunctionDoc]) {
-    use termimad::MadSkin;
     use termimad::crossterm::style::Color;
 
-    let mut skin = MadSkin::default();
-    skin.bold.set_fg(Color::Magenta);
-    skin.italic.set_fg(Color::DarkGrey);
+    let mut skin = crate::cli::color::skin();
+    if crate::cli::color::enabled() {
+        skin.italic.set_fg(Color::DarkGrey);
+    }
 
     let total = functions.len();
     let documented: Vec<&FunctionDoc> = functions.iter().filter(|f| !f.doc.is_empty()).collect();
@@ -219,4 +239,16 @@ mod tests {
         // Just ensure it doesn't panic
         print_metadata_table(&metadata, &hash);
     }
+
+    #[test]
+    fn json_warnings_are_added_to_the_metadata_object() {
+        let json = TemplateMetadata::new("test-template".to_string(), "1.0.0".to_string())
+            .to_json()
+            .unwrap();
+        assert_eq!(with_warnings(&json, vec![]).unwrap(), json);
+        let value: serde_json::Value =
+            serde_json::from_str(&with_warnings(&json, vec!["stale".to_string()]).unwrap()).unwrap();
+        assert_eq!(value["warnings"], serde_json::json!(["stale"]));
+        assert_eq!(value["name"], "test-template");
+    }
 }
@@ -22,7 +22,7 @@ pub enum TemplateCommand {
     /// Publish a template with its metadata hash.
     Publish {
         #[clap(flatten)]
-        args: TemplatePublishArgs,
+        args: Box<TemplatePublishArgs>,
     },
     /// Inspect a template metadata CBOR file.
     #[clap(alias = "inspect-metadata")]
@@ -1,29 +1,41 @@
 // Copyright 2024 The Tari Project
 // SPDX-License-Identifier: BSD-3-Clause
 
-use std::path::PathBuf;
+use std::fmt;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, anyhow};
 use clap::Parser;
 use dialoguer::Confirm;
 use ootle_network::Network;
 use tari_engine_types::published_template::PublishedTemplateAddress;
+use tari_ootle_publish_lib::Amount;
+use tari_ootle_publish_lib::CancellationToken;
 use tari_ootle_publish_lib::NetworkConfig;
+use tari_ootle_publish_lib::PublisherError;
+use tari_ootle_publish_lib::WalletClient;
 use tari_ootle_publish_lib::publisher::{CheckBalanceResult, Template, TemplatePublisher};
 use tari_ootle_publish_lib::walletd_client::ComponentAddressOrName;
+use tari_ootle_publish_lib::walletd_client::types::PublishTemplateRequest;
+use tari_ootle_template_metadata::MetadataHash;
 use tari_utilities::Hidden;
 
+use crate::cli::account_cache::AccountCache;
+use crate::cli::build_cache::BuildCache;
+use crate::cli::command::AccountArgs;
 use crate::cli::commands::metadata::publish::publish_metadata_to_server;
 use crate::cli::commands::publish::{
-    build_template, decode_metadata_cbor, find_metadata_cbor, load_project_config, resolve_active_network,
-    resolve_wallet_daemon_url,
+    CargoBuildArgs, build_template, decode_metadata_cbor, find_metadata_cbor, load_project_config, print_build_log,
+    resolve_active_network, resolve_custom_network, resolve_wallet_daemon_url, template_crate_name, use_cargo_opts,
 };
 use crate::cli::config::Config;
 use crate::cli::util;
 use crate::cli::util::get_default_metadata_server_url;
+use crate::git::repository::GitRepository;
 use crate::loading;
+use crate::project::{LockedTemplate, PUBLISH_LOCK_FILE_NAME, ProjectConfig, PublishLock};
 
-const MAX_WASM_SIZE: usize = 2 * 1000 * 1000; // 2 MB
+pub(crate) const MAX_WASM_SIZE: usize = 2 * 1000 * 1000; // 2 MB
 
 #[derive(Clone, Parser, Debug)]
 pub struct TemplatePublishArgs {
@@ -32,11 +44,11 @@ pub struct TemplatePublishArgs {
     #[arg(default_value = ".")]
     pub path: PathBuf,
 
-    /// Account to be used for publishing fees.
-    #[arg(short = 'a', long)]
-    pub account: Option<ComponentAddressOrName>,
+    #[clap(flatten)]
+    pub account: AccountArgs,
 
     /// (Optional) Custom network name.
+    /// Selects a network configured under `[networks.<name>]` in the project or global config.
     #[arg(short = 'c', long)]
     pub custom_network: Option<String>,
 
@@ -48,13 +60,43 @@ pub struct TemplatePublishArgs {
     #[arg(short = 'f', long)]
     pub max_fee: Option<u64>,
 
+    /// Multiplier applied to the estimated fee to pad the maximum fee.
+    /// Ignored when --max-fee is set.
+    #[arg(long, default_value_t = 1.0, value_parser = crate::cli::command::fee_multiplier_parser)]
+    pub fee_multiplier: f64,
+
+    /// If no account is configured and the wallet has no default account, create an account with
+    /// this name, funded with free test coins. Test networks only.
+    #[arg(long, value_name = "NAME")]
+    pub create_account: Option<String>,
+
+    /// Retry publishing up to N times when the transaction is rejected for a reason that may be
+    /// temporary (e.g. it lost a race for its inputs), re-estimating the fee each time.
+    /// Permanent rejections are never retried.
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub retry_on_reject: u32,
+
+    /// Print the publish request sent to the wallet daemon (with the binary's size and hash in
+    /// place of its bytes) before asking for confirmation. Suppressed by --quiet.
+    #[arg(long, default_value_t = false)]
+    pub show_request: bool,
+
+    /// Publish without the fee, republish and metadata prompts, as for a local development daemon.
+    /// Implied on the `localnet` network unless --no-local is given.
+    #[arg(long, default_value_t = false, conflicts_with = "no_local")]
+    pub local: bool,
+
+    /// Keep the confirmation prompts even on the `localnet` network.
+    #[arg(long, default_value_t = false)]
+    pub no_local: bool,
+
     /// (Optional) Path to a pre-compiled WASM binary.
     #[arg(long, alias = "bin")]
     pub binary: Option<PathBuf>,
 
     /// Wallet daemon JSON-RPC URL.
-    /// Overrides the value in tari.config.toml and global CLI config.
-    #[arg(long)]
+    /// Overrides the value in tari.config.toml and global CLI config for this run only.
+    #[arg(long, visible_alias = "network-url")]
     pub wallet_daemon_url: Option<url::Url>,
 
     /// After publishing, automatically submit metadata to a metadata server.
@@ -70,24 +112,106 @@ pub struct TemplatePublishArgs {
     /// By default the template is compiled with size optimizations.
     #[arg(long, default_value_t = false)]
     pub no_cargo_opts: bool,
+
+    /// Skip publishing if the binary matches the one last published from this project on the
+    /// active network, and report the existing template address instead.
+    #[arg(long, default_value_t = false)]
+    pub skip_if_exists: bool,
+
+    /// Rebuild the template and refuse to publish unless its hash matches `tari.publish.lock`.
+    #[arg(long, default_value_t = false, conflicts_with = "binary")]
+    pub from_lockfile: bool,
+
+    /// Set for a template from a bundle. Its metadata hash comes from the bundle and the project
+    /// config is neither checked nor updated, since it describes the crate at PATH.
+    #[arg(skip)]
+    pub bundled: Option<BundledMetadata>,
+
+    /// With --yes, don't print the publish summary.
+    #[arg(short = 'q', long, default_value_t = false)]
+    pub quiet: bool,
+
+    /// Verbosity level, set from the global `--verbose` flag.
+    #[arg(skip)]
+    pub verbose: u8,
+
+    #[clap(flatten)]
+    pub cargo: CargoBuildArgs,
+}
+
+/// What a bundle records about one of its templates.
+#[derive(Clone, Debug)]
+pub struct BundledMetadata {
+    pub metadata_hash: Option<MetadataHash>,
+}
+
+/// Whether to skip the publish prompts: with --local, or on `localnet` unless --no-local is given.
+/// The default wallet daemon URL is on localhost for every network, so the URL can't tell.
+fn is_local_publish(local: bool, no_local: bool, network: Network) -> bool {
+    local || (!no_local && network == Network::LocalNet)
+}
+
+/// Asks before publishing to a network other than the project's `expected-network`. With `--yes`
+/// the warning is printed and publishing continues.
+fn confirm_expected_network(project_config: &ProjectConfig, network: Network, yes: bool) -> anyhow::Result<()> {
+    let Some(expected) = project_config.expected_network() else {
+        return Ok(());
+    };
+    if expected == network {
+        return Ok(());
+    }
+    println!("⚠️  This project expects to be published to '{expected}', but the active network is '{network}'.");
+    if yes {
+        return Ok(());
+    }
+    let proceed = Confirm::new()
+        .with_prompt(format!("Publish to '{network}' anyway?"))
+        .default(false)
+        .interact()?;
+    if !proceed {
+        return Err(anyhow!(
+            "Publishing aborted. Use --network {expected} to publish to the expected network."
+        ));
+    }
+    Ok(())
 }
 
 pub async fn handle(
     config: Config,
+    base_dir: &Path,
     network_override: Option<Network>,
     api_key: Option<Hidden<String>>,
     mut args: TemplatePublishArgs,
-) -> anyhow::Result<()> {
+    cancel: CancellationToken,
+) -> anyhow::Result<PublishResult> {
+    args.account.read_account_source().await?;
     let crate_dir = &args.path;
 
     let project_config = load_project_config(crate_dir).await?;
+    args.cargo.apply_project_defaults(&project_config);
+    let network_override = resolve_custom_network(
+        network_override,
+        args.custom_network.as_deref(),
+        &project_config,
+        &config,
+    )?;
     let network = resolve_active_network(network_override, &project_config, &config);
     let wallet_daemon_url =
         resolve_wallet_daemon_url(args.wallet_daemon_url.as_ref(), &project_config, &config, network);
     println!("🌐 Network: {network}");
+    let local = is_local_publish(args.local, args.no_local, network);
+    if local && !args.yes {
+        println!("🏠 Local network: publishing without confirmation prompts (--no-local to keep them)");
+    }
+    confirm_expected_network(&project_config, network, args.yes)?;
+    let yes = args.yes || local;
 
     // Warn if template address already exists in config (republishing)
-    if let Some(existing_addr) = project_config.template_address(network) {
+    if let Some(existing_addr) = project_config.template_address(network)
+        && !args.skip_if_exists
+        && !local
+        && args.bundled.is_none()
+    {
         println!("⚠️  A template has already been published from this project: {existing_addr}");
         println!("   If the template binary is unchanged, the transaction will fail.");
         println!("   If changed, a new template address will be generated.");
@@ -101,52 +225,72 @@ pub async fn handle(
     }
 
     // Build or use provided binary
+    let prebuilt = args.binary.is_some();
     let template_bin = match args.binary.take() {
         Some(bin_path) => {
             println!("📦 Using provided WASM binary at {}", bin_path.display());
             bin_path
         },
-        None => build_template(crate_dir, !args.no_cargo_opts).await?,
+        None => build_template(
+            crate_dir,
+            use_cargo_opts(args.no_cargo_opts, &project_config),
+            &args.cargo,
+            Some(&BuildCache::new(base_dir)),
+        )
+        .await
+        .inspect_err(|e| {
+            if args.verbose > 0 {
+                print_build_log(e)
+            }
+        })?,
     };
 
     // Find and read metadata CBOR from build output
-    let metadata_hash = match find_metadata_cbor(crate_dir).await {
-        Ok(cbor_path) => {
-            println!("📄 Found metadata at {}", cbor_path.display());
-            let bytes = std::fs::read(&cbor_path).context("opening metadata CBOR file")?;
-            let metadata = decode_metadata_cbor(&bytes)?;
-            let hash = metadata.hash().context("computing metadata hash")?;
-            println!("🔑 Metadata hash: {hash}");
-            println!("   Name:        {}", metadata.name);
-            println!("   Version:     {}", metadata.version);
-            if !metadata.description.is_empty() {
-                println!("   Description: {}", metadata.description);
-            }
-            if let Some(ref category) = metadata.category {
-                println!("   Category:    {category}");
-            }
-            if !metadata.tags.is_empty() {
-                println!("   Tags:        {}", metadata.tags.join(", "));
-            }
-            if let Some(ref license) = metadata.license {
-                println!("   License:     {license}");
-            }
-            if let Some(ref commit_hash) = metadata.commit_hash {
-                println!("   Commit hash: {commit_hash}");
-            }
-            if let Some(ref supersedes) = metadata.supersedes {
-                println!("   Supersedes:  {supersedes}");
-            }
-            Some(hash)
-        },
-        Err(e) => {
-            println!("⚠️  No metadata found ({e}), publishing without metadata hash");
-            None
-        },
+    let metadata_hash = if let Some(bundled) = &args.bundled {
+        if let Some(hash) = &bundled.metadata_hash {
+            println!("🔑 Metadata hash: {hash} (from bundle)");
+        }
+        bundled.metadata_hash.clone()
+    } else {
+        match find_metadata_cbor(crate_dir, args.cargo.target()).await {
+            Ok(cbor_path) => {
+                println!("📄 Found metadata at {}", cbor_path.display());
+                let bytes = std::fs::read(&cbor_path).context("opening metadata CBOR file")?;
+                let metadata = decode_metadata_cbor(&bytes)?;
+                let hash = metadata.hash().context("computing metadata hash")?;
+                println!("🔑 Metadata hash: {hash}");
+                println!("   Name:        {}", metadata.name);
+                println!("   Version:     {}", metadata.version);
+                if !metadata.description.is_empty() {
+                    println!("   Description: {}", metadata.description);
+                }
+                if let Some(ref category) = metadata.category {
+                    println!("   Category:    {category}");
+                }
+                if !metadata.tags.is_empty() {
+                    println!("   Tags:        {}", metadata.tags.join(", "));
+                }
+                if let Some(ref license) = metadata.license {
+                    println!("   License:     {license}");
+                }
+                if let Some(ref commit_hash) = metadata.commit_hash {
+                    println!("   Commit hash: {commit_hash}");
+                }
+                if let Some(ref supersedes) = metadata.supersedes {
+                    println!("   Supersedes:  {supersedes}");
+                }
+                Some(hash)
+            },
+            Err(e) => {
+                println!("⚠️  No metadata found ({e}), publishing without metadata hash");
+                None
+            },
+        }
     };
 
     // Connect to wallet daemon
-    let publisher = TemplatePublisher::new(NetworkConfig::new(wallet_daemon_url.clone()).with_api_key(api_key));
+    let publisher = TemplatePublisher::new(NetworkConfig::new(wallet_daemon_url.clone()).with_api_key(api_key))
+        .with_cancellation_token(cancel);
     let info = publisher
         .get_wallet_info()
         .await
@@ -164,10 +308,42 @@ pub async fn handle(
         ));
     }
 
-    let account = resolve_account(&args, &config, &publisher, &project_config).await?;
+    let mut account_cache = AccountCache::load(base_dir).await.unwrap_or_else(|e| {
+        println!("⚠️  Ignoring unreadable account cache: {e:#}");
+        AccountCache::default()
+    });
+    let (account, account_source) = resolve_account(
+        args.account.selected(),
+        &config,
+        &publisher,
+        &project_config,
+        &account_cache,
+        network,
+        args.create_account.as_deref(),
+    )
+    .await?;
+    println!("🔍 Using account: {account} (from {account_source})");
+    let built_size = tokio::fs::metadata(&template_bin).await?.len() as usize;
     let template = Template::Path { path: template_bin };
 
-    let CheckBalanceResult { max_fee, binary_size } = publisher
+    if args.skip_if_exists
+        && let Some(existing_addr) = project_config.template_address(network)
+    {
+        let template_hash = publisher.template_hash(&template).await?.to_string();
+        if project_config.template_hash(network) == Some(template_hash.as_str()) {
+            println!("⏭️  Template {template_hash} is already published at {existing_addr}, skipping.");
+            return Ok(PublishResult::Skipped {
+                address: *existing_addr,
+            });
+        }
+    }
+
+    let CheckBalanceResult {
+        max_fee: estimated_fee,
+        binary_size,
+        wallet_balance,
+        template_hash,
+    } = publisher
         .check_balance_for_publish(&account, &template, metadata_hash.clone())
         .await?;
 
@@ -176,54 +352,138 @@ pub async fn handle(
     } else {
         println!("✅ WASM size: {}", util::human_bytes(binary_size));
     }
+    println!("🔑 Template hash: {template_hash}");
+    // Only binaries built from the crate here are recorded in (or checked against) the lock file.
+    let crate_name = if prebuilt {
+        None
+    } else {
+        template_crate_name(crate_dir).ok()
+    };
+    if args.from_lockfile {
+        let crate_name = crate_name
+            .as_deref()
+            .context("--from-lockfile requires a template crate")?;
+        PublishLock::load(crate_dir)
+            .await?
+            .verify(crate_name, &template_hash.to_string())?;
+        println!("🔒 Template hash matches {PUBLISH_LOCK_FILE_NAME}");
+    }
+    print_fee_breakdown(built_size, binary_size, estimated_fee, &wallet_balance);
+    let max_fee = resolve_max_fee(estimated_fee, args.max_fee, args.fee_multiplier);
+    if max_fee != estimated_fee {
+        println!("   Max fee:        {max_fee}");
+    }
+    if let Some(warning) = max_fee_warning(max_fee, estimated_fee) {
+        println!("⚠️  {warning}");
+    }
+    ensure_balance_covers(&wallet_balance, max_fee)?;
 
-    if !args.yes {
-        let confirmation = Confirm::new()
-            .with_prompt(format!(
-                "⚠️ Publishing this template costs {max_fee} (estimated), are you sure to continue?",
-            ))
-            .interact()?;
+    if !(yes && args.quiet) {
+        let summary = PublishSummary {
+            crate_name: crate_name.clone(),
+            source_commit: (!prebuilt).then(|| source_commit_label(crate_dir)).flatten(),
+            network: format!("{network} ({wallet_daemon_url})"),
+            account: account.to_string(),
+            binary_size,
+            max_fee,
+            balance_after: wallet_balance
+                .to_u64_checked()
+                .map(|balance| balance.saturating_sub(max_fee)),
+        };
+        print!("{summary}");
+    }
+
+    if args.show_request && !args.quiet {
+        let (request, _) = publisher
+            .create_publish_template_request(&account, &template, max_fee, metadata_hash.clone())
+            .await?;
+        print_publish_request(&request, &template_hash);
+    }
+
+    if !yes {
+        let confirmation = Confirm::new().with_prompt("⚠️ Publish this template?").interact()?;
         if !confirmation {
             return Err(anyhow!("💥 Publishing aborted!"));
         }
     }
 
-    let template_address = loading!(
-        "Publishing template. This may take while...",
-        publisher
-            .publish(&account, template, max_fee, metadata_hash.clone(), None)
-            .await
-    )?;
+    let (fixed_max_fee, fee_multiplier) = (args.max_fee, args.fee_multiplier);
+    let outcome = retry_transient_rejections(args.retry_on_reject, |attempt| {
+        let (publisher, account) = (&publisher, &account);
+        let template = template.clone();
+        let metadata_hash = metadata_hash.clone();
+        async move {
+            // Whatever made the last attempt fail may also have moved the fee.
+            let max_fee = if attempt == 0 {
+                max_fee
+            } else {
+                let check = publisher
+                    .check_balance_for_publish(account, &template, metadata_hash.clone())
+                    .await?;
+                let max_fee = resolve_max_fee(check.max_fee, fixed_max_fee, fee_multiplier);
+                println!("   Max fee:        {max_fee}");
+                ensure_balance_covers(&check.wallet_balance, max_fee)?;
+                max_fee
+            };
+            loading!(
+                "Publishing template. This may take while...",
+                publisher.publish(account, template, max_fee, metadata_hash, None).await
+            )
+        }
+    })
+    .await?;
 
-    let published_addr = PublishedTemplateAddress::from_template_address(template_address);
+    let published_addr = PublishedTemplateAddress::from_template_address(outcome.template_address);
     println!("⭐ Your new template's address: {published_addr}");
+    println!("   Transaction: {} ({})", outcome.transaction_id, outcome.status);
+    println!("   Fee charged: {}", outcome.fee_charged);
 
-    // Save template address to project config under [networks.<network>]
-    let config_path = crate::cli::commands::config::resolve_config_path()?;
-    if config_path.exists() {
-        let content = tokio::fs::read_to_string(&config_path)
-            .await
-            .context("reading config")?;
-        let mut doc = content.parse::<toml_edit::DocumentMut>().context("parsing config")?;
-        crate::cli::commands::config::set_dotted_key(
-            &mut doc,
-            &format!("networks.{}.template-address", network.as_key_str()),
-            &published_addr.to_string(),
-        )?;
-        tokio::fs::write(&config_path, doc.to_string())
-            .await
-            .context("writing config")?;
-        println!("📝 Saved template address to {}", config_path.display());
-    } else {
-        println!(
-            "ℹ️  Config file not found at {}. Run `tari config init` to create one.",
-            config_path.display()
-        );
+    if !args.from_lockfile
+        && let Some(crate_name) = crate_name
+        && let Err(e) = record_publish_lock(crate_dir, crate_name, template_hash.to_string()).await
+    {
+        println!("⚠️  Failed to update {PUBLISH_LOCK_FILE_NAME}: {e:#}");
+    }
+
+    account_cache.set(network, &account);
+    if let Err(e) = account_cache.save(base_dir).await {
+        println!("⚠️  Failed to remember the account used for {network}: {e:#}");
+    }
+
+    // Save template address to project config under [networks.<network>]. Skipped for bundled
+    // templates: the project config describes the crate at PATH.
+    if args.bundled.is_none() {
+        let config_path = crate::cli::commands::config::resolve_config_path()?;
+        if config_path.exists() {
+            let content = tokio::fs::read_to_string(&config_path)
+                .await
+                .context("reading config")?;
+            let mut doc = content.parse::<toml_edit::DocumentMut>().context("parsing config")?;
+            crate::cli::commands::config::set_dotted_key(
+                &mut doc,
+                &format!("networks.{}.template-address", network.as_key_str()),
+                &published_addr.to_string(),
+            )?;
+            crate::cli::commands::config::set_dotted_key(
+                &mut doc,
+                &format!("networks.{}.template-hash", network.as_key_str()),
+                &template_hash.to_string(),
+            )?;
+            tokio::fs::write(&config_path, doc.to_string())
+                .await
+                .context("writing config")?;
+            println!("📝 Saved template address to {}", config_path.display());
+        } else {
+            println!(
+                "ℹ️  Config file not found at {}. Run `tari config init` to create one.",
+                config_path.display()
+            );
+        }
     }
 
     let should_publish_metadata = if args.publish_metadata {
         metadata_hash.is_some()
-    } else if metadata_hash.is_some() {
+    } else if metadata_hash.is_some() && !local && args.bundled.is_none() {
         Confirm::new()
             .with_prompt("Publish metadata to community server?")
             .default(false)
@@ -233,7 +493,7 @@ pub async fn handle(
     };
 
     if should_publish_metadata {
-        let cbor_path = find_metadata_cbor(crate_dir).await?;
+        let cbor_path = find_metadata_cbor(crate_dir, args.cargo.target()).await?;
         let cbor_bytes = std::fs::read(&cbor_path).context("reading metadata CBOR for server publish")?;
 
         let resolved_default = get_default_metadata_server_url(network)
@@ -263,40 +523,540 @@ pub async fn handle(
         }
     }
 
+    Ok(PublishResult::Published {
+        address: published_addr,
+        fee_charged: outcome.fee_charged.to_string(),
+    })
+}
+
+/// How a publish ended, for callers reporting on several publishes.
+pub enum PublishResult {
+    Published {
+        address: PublishedTemplateAddress,
+        fee_charged: String,
+    },
+    /// `--skip-if-exists` found the binary already published at `address`.
+    Skipped { address: PublishedTemplateAddress },
+}
+
+/// What is about to be published, shown before the confirmation prompt.
+struct PublishSummary {
+    crate_name: Option<String>,
+    source_commit: Option<String>,
+    network: String,
+    account: String,
+    binary_size: usize,
+    max_fee: u64,
+    balance_after: Option<u64>,
+}
+
+impl std::fmt::Display for PublishSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "📋 Publish summary:")?;
+        writeln!(
+            f,
+            "   Crate:          {}",
+            self.crate_name.as_deref().unwrap_or("(pre-built binary)")
+        )?;
+        writeln!(
+            f,
+            "   Source commit:  {}",
+            self.source_commit.as_deref().unwrap_or("(unknown)")
+        )?;
+        writeln!(f, "   Network:        {}", self.network)?;
+        writeln!(f, "   Account:        {}", self.account)?;
+        writeln!(f, "   Binary size:    {}", util::human_bytes(self.binary_size))?;
+        writeln!(f, "   Max fee:        {}", self.max_fee)?;
+        match self.balance_after {
+            Some(balance) => writeln!(f, "   Balance after:  {balance}"),
+            None => writeln!(f, "   Balance after:  (unknown)"),
+        }
+    }
+}
+
+/// The short HEAD commit of the repository containing `crate_dir`, marked when the working tree
+/// has uncommitted changes. `None` outside a git repository.
+fn source_commit_label(crate_dir: &Path) -> Option<String> {
+    let mut repo = GitRepository::new(crate_dir.to_path_buf());
+    repo.discover().ok()?;
+    let commit = repo.head_commit_hash().ok()?;
+    let short = &commit[..commit.len().min(8)];
+    match repo.is_dirty() {
+        Ok(true) => Some(format!("{short} (dirty)")),
+        _ => Some(short.to_string()),
+    }
+}
+
+/// Records the published template in the project's publish lock file.
+async fn record_publish_lock(crate_dir: &Path, name: String, template_hash: String) -> anyhow::Result<()> {
+    let mut repo = GitRepository::new(crate_dir.to_path_buf());
+    let source_commit = repo.discover().and_then(|_| repo.head_commit_hash()).ok();
+    let mut lock = PublishLock::load(crate_dir).await?;
+    lock.upsert(LockedTemplate {
+        name,
+        source_commit,
+        template_hash,
+    });
+    lock.save(crate_dir).await?;
+    println!("📝 Recorded template in {}", PublishLock::path(crate_dir).display());
     Ok(())
 }
 
-async fn resolve_account(
-    args: &TemplatePublishArgs,
-    config: &Config,
-    publisher: &TemplatePublisher,
-    project_config: &crate::project::ProjectConfig,
-) -> anyhow::Result<ComponentAddressOrName> {
-    let account = args
-        .account
+/// Prints the fields of the publish request that `--show-request` reveals.
+fn print_publish_request(request: &PublishTemplateRequest, template_hash: &impl std::fmt::Display) {
+    let fee_account = request
+        .fee_account
         .as_ref()
-        .cloned()
+        .map_or_else(|| "(wallet default)".to_string(), ToString::to_string);
+    println!("📨 Publish request:");
+    println!("   fee_account:   {fee_account}");
+    println!("   max_fee:       {}", request.max_fee);
+    println!("   metadata:      {:?}", request.metadata);
+    println!("   detect_inputs: {}", request.detect_inputs);
+    println!("   dry_run:       {}", request.dry_run);
+    println!(
+        "   binary:        {} bytes (template hash {template_hash})",
+        request.binary.len()
+    );
+}
+
+/// Prints how the binary size drives the estimated fee. `built_size` is the size of the binary on
+/// disk, `binary_size` the size actually published (smaller when wasm-opt is enabled).
+pub(crate) fn print_fee_breakdown(
+    built_size: usize,
+    binary_size: usize,
+    max_fee: u64,
+    wallet_balance: &impl std::fmt::Display,
+) {
+    let per_kb = fee_per_kb(max_fee, binary_size);
+    println!("💰 Fee breakdown:");
+    println!("   Binary size:    {}", util::human_bytes(binary_size));
+    println!("   Fee per KB:     ~{per_kb:.0}");
+    println!("   Estimated fee:  {max_fee}");
+    if built_size > binary_size {
+        let saved = built_size - binary_size;
+        println!(
+            "   wasm-opt saved: {} (~{:.0} in fees)",
+            util::human_bytes(saved),
+            per_kb * saved as f64 / 1000.0
+        );
+    }
+    println!("   Wallet balance: {wallet_balance}");
+}
+
+/// Calls `publish` with the attempt number (0 for the first) until it succeeds, fails with
+/// anything but a transient rejection, or `retries` retries are used up.
+async fn retry_transient_rejections<T, F, Fut>(retries: u32, mut publish: F) -> Result<T, PublisherError>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<T, PublisherError>>,
+{
+    let mut attempt = 0;
+    loop {
+        match publish(attempt).await {
+            Err(error) if error.is_transient_rejection() && attempt < retries => {
+                println!("⚠️  {error}");
+                attempt += 1;
+                println!("🔁 Retrying publish ({attempt} of {retries})");
+            },
+            result => return result,
+        }
+    }
+}
+
+/// The maximum fee to attach to the publish transaction: `--max-fee` if given, otherwise the
+/// estimate padded by `--fee-multiplier`.
+fn resolve_max_fee(estimated_fee: u64, max_fee: Option<u64>, fee_multiplier: f64) -> u64 {
+    max_fee.unwrap_or_else(|| (estimated_fee as f64 * fee_multiplier).ceil() as u64)
+}
+
+/// Fails when the wallet balance can't cover `max_fee`. The wallet daemon only checks the balance
+/// against the estimate, which `--fee-multiplier` or `--max-fee` may have raised.
+fn ensure_balance_covers(wallet_balance: &Amount, max_fee: u64) -> Result<(), PublisherError> {
+    if wallet_balance
+        .to_u64_checked()
+        .is_some_and(|balance| balance >= max_fee)
+    {
+        return Ok(());
+    }
+    Err(PublisherError::InsufficientBalance {
+        current: *wallet_balance,
+        fee: max_fee,
+    })
+}
+
+/// Warns when an explicit `--max-fee` is below the estimate. The value is still used as given.
+fn max_fee_warning(max_fee: u64, estimated_fee: u64) -> Option<String> {
+    (max_fee < estimated_fee).then(|| {
+        format!(
+            "Max fee {max_fee} is below the estimated fee {estimated_fee}. \
+             The publish transaction will likely be rejected."
+        )
+    })
+}
+
+fn fee_per_kb(fee: u64, binary_size: usize) -> f64 {
+    if binary_size == 0 {
+        return 0.0;
+    }
+    fee as f64 / (binary_size as f64 / 1000.0)
+}
+
+/// Where [`resolve_account`] found the fee account.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum AccountSource {
+    /// `--account` or one of its alternatives, named by the flag.
+    Argument(&'static str),
+    ProjectConfig,
+    GlobalConfig,
+    /// The account cache of the last account used on the network.
+    LastUsed,
+    WalletDaemonDefault,
+    /// Created (and funded) with `--create-account <NAME>`.
+    Created(String),
+    /// An alias from the project's `[accounts]` table, found through `source`.
+    Alias {
+        source: Box<AccountSource>,
+        alias: String,
+    },
+}
+
+impl fmt::Display for AccountSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccountSource::Argument(flag) => write!(f, "{flag}"),
+            AccountSource::ProjectConfig => write!(f, "project config"),
+            AccountSource::GlobalConfig => write!(f, "global CLI config"),
+            AccountSource::LastUsed => write!(f, "last used on this network"),
+            AccountSource::WalletDaemonDefault => write!(f, "wallet daemon default"),
+            AccountSource::Created(name) => write!(f, "created with --create-account {name}"),
+            AccountSource::Alias { source, alias } => write!(f, "{source}, alias '{alias}'"),
+        }
+    }
+}
+
+/// Resolves the fee account and where it came from. Precedence: `--account` > project default >
+/// global default > last account used on this network > wallet daemon default.
+/// Names that are aliases in the project's `[accounts]` table are replaced by their target.
+pub(crate) async fn resolve_account<C: WalletClient>(
+    account: Option<(&ComponentAddressOrName, &'static str)>,
+    config: &Config,
+    publisher: &TemplatePublisher<C>,
+    project_config: &ProjectConfig,
+    account_cache: &AccountCache,
+    network: Network,
+    create_account: Option<&str>,
+) -> anyhow::Result<(ComponentAddressOrName, AccountSource)> {
+    let account = account
+        .map(|(account, flag)| (account.clone(), AccountSource::Argument(flag)))
         .or_else(|| {
             project_config
-                .parsed_default_account()
+                .parsed_default_account(network)
                 .expect("Malformed default account")
+                .map(|account| (account, AccountSource::ProjectConfig))
         })
-        .or(config.default_account.clone());
+        .or_else(|| {
+            config
+                .default_account
+                .clone()
+                .map(|account| (account, AccountSource::GlobalConfig))
+        })
+        .or_else(|| {
+            account_cache
+                .get(network)
+                .ok()
+                .flatten()
+                .map(|account| (account, AccountSource::LastUsed))
+        });
 
     match account {
-        Some(account) => {
-            println!("🔍 Using account: {account}");
-            Ok(account)
+        Some((account, source)) => match project_config.account_alias(&account)? {
+            Some(resolved) => Ok((
+                resolved,
+                AccountSource::Alias {
+                    source: Box::new(source),
+                    alias: account.to_string(),
+                },
+            )),
+            None => Ok((account, source)),
         },
-        None => {
-            let account = publisher.get_default_account().await?;
-            match account {
-                Some(account) => {
-                    println!("❓ No Account specified. Using default account: {account}");
-                    Ok(account)
+        None => match publisher.get_default_account().await? {
+            Some(account) => Ok((account, AccountSource::WalletDaemonDefault)),
+            None => match create_account {
+                Some(name) => {
+                    if network == Network::MainNet {
+                        return Err(anyhow!("--create-account only works on test networks, not {network}."));
+                    }
+                    let account = loading!(
+                        format!("Creating test account **{name}** with free test coins"),
+                        publisher.ensure_account(name).await
+                    )?;
+                    Ok((account, AccountSource::Created(name.to_string())))
                 },
-                None => Err(anyhow!("No account found! Please create an account first.")),
-            }
+                None => Err(anyhow!(
+                    "No account found! Please create an account first, or pass --create-account <NAME> to \
+                     create a funded test account on a test network."
+                )),
+            },
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tari_ootle_publish_lib::publisher::Result;
+    use tari_ootle_publish_lib::walletd_client::types::{
+        PublishTemplateResponse, SignTemplateMetadataRequest, SignTemplateMetadataResponse,
+        TransactionWaitResultRequest, TransactionWaitResultResponse, WalletGetInfoResponse,
+    };
+
+    /// Wallet that only knows its default account.
+    struct MockWallet {
+        default_account: Option<ComponentAddressOrName>,
+    }
+
+    /// Returned by the wallet calls that account resolution never makes.
+    fn unused() -> PublisherError {
+        PublisherError::InvalidResponse("not used in this test".to_string())
+    }
+
+    impl WalletClient for MockWallet {
+        async fn get_wallet_info(&self) -> Result<WalletGetInfoResponse> {
+            Err(unused())
+        }
+
+        async fn get_default_account(&self) -> Result<Option<ComponentAddressOrName>> {
+            Ok(self.default_account.clone())
+        }
+
+        async fn get_xtr_balance(&self, _account: &ComponentAddressOrName) -> Result<Amount> {
+            Err(unused())
+        }
+
+        async fn create_free_test_account(&self, _name: &str) -> Result<ComponentAddressOrName> {
+            Err(unused())
+        }
+
+        async fn sign_template_metadata(
+            &self,
+            _request: SignTemplateMetadataRequest,
+        ) -> Result<SignTemplateMetadataResponse> {
+            Err(unused())
+        }
+
+        async fn publish_template(&self, _request: &PublishTemplateRequest) -> Result<PublishTemplateResponse> {
+            Err(unused())
+        }
+
+        async fn wait_transaction_result(
+            &self,
+            _request: TransactionWaitResultRequest,
+        ) -> Result<TransactionWaitResultResponse> {
+            Err(unused())
+        }
+    }
+
+    fn name(name: &str) -> ComponentAddressOrName {
+        ComponentAddressOrName::Name(name.to_string())
+    }
+
+    /// Every source of an account set, each to a different name, so tests can drop them one by one.
+    struct Accounts {
+        argument: Option<ComponentAddressOrName>,
+        project_config: ProjectConfig,
+        config: Config,
+        account_cache: AccountCache,
+        publisher: TemplatePublisher<MockWallet>,
+    }
+
+    impl Accounts {
+        fn all() -> Self {
+            let config = Config {
+                default_account: Some(name("global")),
+                ..Default::default()
+            };
+            let mut account_cache = AccountCache::default();
+            account_cache.set(Network::LocalNet, &name("cached"));
+            Self {
+                argument: Some(name("argument")),
+                project_config: toml::from_str("default-account = \"project\"\n").unwrap(),
+                config,
+                account_cache,
+                publisher: TemplatePublisher::with_client(MockWallet {
+                    default_account: Some(name("daemon")),
+                }),
+            }
+        }
+
+        async fn resolve(&self) -> anyhow::Result<(String, AccountSource)> {
+            let (account, source) = resolve_account(
+                self.argument.as_ref().map(|account| (account, "--account")),
+                &self.config,
+                &self.publisher,
+                &self.project_config,
+                &self.account_cache,
+                Network::LocalNet,
+                None,
+            )
+            .await?;
+            Ok((account.to_string(), source))
+        }
+    }
+
+    #[tokio::test]
+    async fn account_sources_apply_in_precedence_order() {
+        let mut accounts = Accounts::all();
+        let expect = |account: &str, source| (account.to_string(), source);
+        assert_eq!(
+            accounts.resolve().await.unwrap(),
+            expect("argument", AccountSource::Argument("--account"))
+        );
+        accounts.argument = None;
+        assert_eq!(
+            accounts.resolve().await.unwrap(),
+            expect("project", AccountSource::ProjectConfig)
+        );
+        accounts.project_config = ProjectConfig::default();
+        assert_eq!(
+            accounts.resolve().await.unwrap(),
+            expect("global", AccountSource::GlobalConfig)
+        );
+        accounts.config.default_account = None;
+        assert_eq!(
+            accounts.resolve().await.unwrap(),
+            expect("cached", AccountSource::LastUsed)
+        );
+        accounts.account_cache = AccountCache::default();
+        assert_eq!(
+            accounts.resolve().await.unwrap(),
+            expect("daemon", AccountSource::WalletDaemonDefault)
+        );
+        accounts.publisher = TemplatePublisher::with_client(MockWallet { default_account: None });
+        let error = accounts.resolve().await.unwrap_err();
+        assert!(error.to_string().contains("No account found"), "got: {error}");
+    }
+
+    #[tokio::test]
+    async fn aliases_keep_the_source_they_were_found_through() {
+        let mut accounts = Accounts::all();
+        accounts.argument = None;
+        accounts.project_config =
+            toml::from_str("default-account = \"treasury\"\n\n[accounts]\ntreasury = \"ops\"\n").unwrap();
+        let (account, source) = accounts.resolve().await.unwrap();
+        assert_eq!(account, "ops");
+        assert_eq!(
+            source,
+            AccountSource::Alias {
+                source: Box::new(AccountSource::ProjectConfig),
+                alias: "treasury".to_string(),
+            }
+        );
+        assert_eq!(source.to_string(), "project config, alias 'treasury'");
+    }
+
+    #[test]
+    fn only_localnet_or_local_flag_skips_prompts() {
+        assert!(is_local_publish(false, false, Network::LocalNet));
+        assert!(!is_local_publish(false, true, Network::LocalNet));
+        assert!(!is_local_publish(false, false, Network::Esmeralda));
+        assert!(!is_local_publish(false, false, Network::MainNet));
+        assert!(is_local_publish(true, false, Network::Esmeralda));
+    }
+
+    #[test]
+    fn publish_summary_lists_what_will_be_published() {
+        let summary = PublishSummary {
+            crate_name: Some("counter".to_string()),
+            source_commit: Some("1a2b3c4d (dirty)".to_string()),
+            network: "esmeralda (http://127.0.0.1:5100/json_rpc)".to_string(),
+            account: "alice".to_string(),
+            binary_size: 2_000,
+            max_fee: 500,
+            balance_after: None,
+        }
+        .to_string();
+        assert!(summary.contains("Crate:          counter\n"), "got: {summary}");
+        assert!(summary.contains("Source commit:  1a2b3c4d (dirty)\n"), "got: {summary}");
+        assert!(summary.contains("Network:        esmeralda (http"), "got: {summary}");
+        assert!(summary.contains("Balance after:  (unknown)\n"), "got: {summary}");
+    }
+
+    #[test]
+    fn fee_per_kb_scales_with_size() {
+        assert_eq!(fee_per_kb(5_000, 50_000), 100.0);
+        assert_eq!(fee_per_kb(5_000, 0), 0.0);
+    }
+
+    #[test]
+    fn max_fee_defaults_to_padded_estimate() {
+        assert_eq!(resolve_max_fee(1_000, None, 1.0), 1_000);
+        assert_eq!(resolve_max_fee(1_000, None, 1.25), 1_250);
+        assert_eq!(resolve_max_fee(1_001, None, 1.5), 1_502);
+        assert_eq!(resolve_max_fee(1_000, Some(5_000), 1.25), 5_000);
+    }
+
+    #[test]
+    fn explicit_max_fee_below_estimate_is_kept_with_warning() {
+        let max_fee = resolve_max_fee(1_000, Some(500), 1.0);
+        assert_eq!(max_fee, 500);
+        let warning = max_fee_warning(max_fee, 1_000).expect("should warn");
+        assert!(warning.contains("below the estimated fee 1000"), "got: {warning}");
+        assert!(max_fee_warning(1_000, 1_000).is_none());
+    }
+
+    #[test]
+    fn balance_must_cover_the_padded_max_fee() {
+        let balance = Amount::from(1_100u64);
+        ensure_balance_covers(&balance, resolve_max_fee(1_000, None, 1.0)).unwrap();
+        let error = ensure_balance_covers(&balance, resolve_max_fee(1_000, None, 1.25)).unwrap_err();
+        assert!(
+            matches!(error, PublisherError::InsufficientBalance { fee: 1_250, .. }),
+            "got: {error}"
+        );
+    }
+
+    fn rejection(transient: bool) -> PublisherError {
+        if transient {
+            PublisherError::TransientRejection("tx_1".to_string(), "failed to lock inputs".to_string())
+        } else {
+            PublisherError::InvalidTransaction("tx_1".to_string(), "insufficient fee".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn transient_rejections_are_retried() {
+        let mut calls = 0;
+        let result = retry_transient_rejections(2, |attempt| {
+            calls += 1;
+            async move { if attempt < 2 { Err(rejection(true)) } else { Ok(attempt) } }
+        })
+        .await;
+        assert!(matches!(result, Ok(2)));
+        assert_eq!(calls, 3);
+    }
+
+    #[tokio::test]
+    async fn retries_stop_when_used_up() {
+        let mut calls = 0;
+        let result = retry_transient_rejections(1, |_| {
+            calls += 1;
+            async { Err::<(), _>(rejection(true)) }
+        })
+        .await;
+        assert!(matches!(result, Err(PublisherError::TransientRejection(..))));
+        assert_eq!(calls, 2);
+    }
+
+    #[tokio::test]
+    async fn permanent_rejections_are_not_retried() {
+        let mut calls = 0;
+        let result = retry_transient_rejections(3, |_| {
+            calls += 1;
+            async { Err::<(), _>(rejection(false)) }
+        })
+        .await;
+        assert!(matches!(result, Err(PublisherError::InvalidTransaction(..))));
+        assert_eq!(calls, 1);
+    }
+}
@@ -0,0 +1,71 @@
+// Copyright 2024 The Tari Project
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::path::Path;
+
+use clap::{Parser, Subcommand};
+
+use crate::{
+    cli::{commands::create::templates_dir, config::Config},
+    git::repository::{GitRepository, HeadState},
+    loading,
+    templates::{Collector, Template},
+};
+
+#[derive(Clone, Subcommand)]
+pub enum TemplatesCommand {
+    /// Search the starter templates by keyword in their id, name or description.
+    Search {
+        #[clap(flatten)]
+        args: SearchArgs,
+    },
+    /// Show the template repository's URL, checked out reference, commit and tree hash.
+    /// Compare the tree hash against a known-good value, or pin it with `create --expect-tree`.
+    Info,
+}
+
+#[derive(Clone, Parser, Debug)]
+pub struct SearchArgs {
+    /// Keyword to look for (case-insensitive).
+    pub query: String,
+}
+
+pub async fn handle(config: Config, template_repo: &GitRepository, command: TemplatesCommand) -> anyhow::Result<()> {
+    match command {
+        TemplatesCommand::Search { args } => handle_search(config, template_repo.local_folder(), args).await,
+        TemplatesCommand::Info => handle_info(config, template_repo),
+    }
+}
+
+fn handle_info(config: Config, template_repo: &GitRepository) -> anyhow::Result<()> {
+    let head = match template_repo.head_state()? {
+        HeadState::Branch(branch) => format!("branch {branch}"),
+        HeadState::Detached(_) => "detached HEAD".to_string(),
+    };
+    println!("📦 Repository: {}", config.template_repository.url);
+    println!("   Reference:  {} ({head})", config.template_repository.reference);
+    println!("   Commit:     {}", template_repo.head_commit_hash()?);
+    println!("   Tree:       {}", template_repo.tree_hash()?);
+    println!("   Local path: {}", template_repo.local_folder().display());
+    Ok(())
+}
+
+async fn handle_search(config: Config, template_repo_dir: &Path, args: SearchArgs) -> anyhow::Result<()> {
+    let templates_folder = templates_dir(&config, template_repo_dir)?;
+    let templates = loading!(
+        "Collecting available templates",
+        Collector::new(templates_folder).collect().await
+    )?;
+
+    let matches: Vec<&Template> = templates.iter().filter(|t| t.matches(&args.query)).collect();
+    if matches.is_empty() {
+        println!("🔎 No templates match '{}'", args.query);
+        return Ok(());
+    }
+
+    for template in matches {
+        println!("{:<20} {template}", template.id());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,293 @@
+// Copyright 2024 The Tari Project
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use cargo_generate::GenerateArgs as CargoGenerateArgs;
+use clap::Parser;
+
+use crate::cli::commands::create::{
+    cargo_template_path, resolve_template_variables, snapshot_files, template_define_parser, template_source,
+    templates_dir,
+};
+use crate::cli::commands::publish::{load_project_config, template_crate_name};
+use crate::cli::commands::template::init_metadata;
+use crate::cli::config::Config;
+use crate::loading;
+use crate::project::{CONFIG_FILE_NAME, PUBLISH_LOCK_FILE_NAME};
+use crate::templates::{Collector, Template};
+
+/// Files that record the project's own state rather than template content.
+const PROJECT_STATE_FILES: &[&str] = &[CONFIG_FILE_NAME, PUBLISH_LOCK_FILE_NAME, "Cargo.lock"];
+
+/// Unchanged lines shown around each change.
+const DIFF_CONTEXT: usize = 2;
+
+/// Files longer than this (in lines, either side) are reported as differing without a diff, as
+/// the diff takes time and memory quadratic in their length.
+const MAX_DIFF_LINES: usize = 1000;
+
+#[derive(Clone, Parser, Debug)]
+pub struct UpgradeArgs {
+    /// Path to the template crate directory.
+    /// Defaults to the current directory.
+    #[arg(default_value = ".")]
+    pub path: PathBuf,
+
+    /// Value for a variable declared in the template's `[variables]` section (KEY=VALUE), as
+    /// given to `create`. Can be repeated. Declared variables that are not set here are prompted for.
+    #[arg(short = 'd', long = "define", value_name = "KEY=VALUE", value_parser = template_define_parser)]
+    pub defines: Vec<(String, String)>,
+
+    /// Verbosity level, set from the global `--verbose` flag.
+    #[arg(skip)]
+    pub verbose: u8,
+}
+
+/// Regenerates the crate from the latest version of the template it was created from (see
+/// `[template]` in its project config) and prints how the template's files differ from the
+/// crate's. Nothing in the crate is changed.
+pub async fn handle(config: Config, template_repo_dir: PathBuf, args: UpgradeArgs) -> anyhow::Result<()> {
+    let crate_dir = &args.path;
+    let project_config = load_project_config(crate_dir).await?;
+    let recorded = project_config.template_source().cloned().ok_or_else(|| {
+        anyhow!(
+            "No [template] section found in the {CONFIG_FILE_NAME} of {}. Only crates made with `tari create` \
+             record the template they were generated from.",
+            crate_dir.display()
+        )
+    })?;
+
+    let templates = loading!(
+        "Collecting available templates",
+        Collector::new(templates_dir(&config, &template_repo_dir)?)
+            .collect()
+            .await
+    )?;
+    let template = Template::find_by_id(&templates, &recorded.id)
+        .ok_or_else(|| anyhow!("Template '{}' is no longer in the template repository", recorded.id))?;
+    let latest = template_source(&config, &template_repo_dir, template);
+    if latest.repository != recorded.repository {
+        println!(
+            "⚠️  This crate was created from {}, but the template is now taken from {}",
+            recorded.repository, latest.repository
+        );
+    }
+    println!(
+        "🔄 Comparing with template '{}' ({} → {})",
+        recorded.id,
+        short_commit(recorded.commit.as_deref()),
+        short_commit(latest.commit.as_deref())
+    );
+
+    let name = template_crate_name(crate_dir)?;
+    let defines = resolve_template_variables(template, &args.defines)?;
+    let temp_dir = tempfile::TempDir::new()?;
+    let generate_args = CargoGenerateArgs {
+        name: Some(name.clone()),
+        destination: Some(temp_dir.path().to_path_buf()),
+        template_path: cargo_template_path(template)?,
+        verbose: args.verbose > 0,
+        define: defines,
+        ..CargoGenerateArgs::default()
+    };
+    let generated_dir = loading!(
+        "Generating the latest template",
+        cargo_generate::generate(generate_args)
+    )?;
+    // `create` sets up metadata generation by default; do the same so it doesn't show up as a difference.
+    if crate_dir.join("build.rs").is_file() {
+        init_metadata::auto_init(&generated_dir).await?;
+    }
+
+    let project_files = snapshot_files(crate_dir)?;
+    let template_files = snapshot_files(&generated_dir)?;
+    let differences = compare(&project_files, &template_files);
+    if differences.is_empty() {
+        println!("✅ The crate matches the latest template");
+        return Ok(());
+    }
+
+    for difference in &differences {
+        match difference {
+            Difference::Added(path) => println!("➕ New in the template: {}", path.display()),
+            Difference::Changed(path) => {
+                println!("📝 Differs from the template: {}", path.display());
+                match (
+                    std::str::from_utf8(&project_files[path]),
+                    std::str::from_utf8(&template_files[path]),
+                ) {
+                    (Ok(ours), Ok(theirs)) => match diff_lines(ours, theirs) {
+                        Some(lines) => print!("{}", render_diff(&lines)),
+                        None => println!("   (over {MAX_DIFF_LINES} lines, not diffed)"),
+                    },
+                    _ => println!("   (binary file)"),
+                }
+            },
+        }
+    }
+    println!(
+        "ℹ️  {} file(s) differ. `-` lines are the crate's, `+` lines the template's. Nothing was changed.",
+        differences.len()
+    );
+    Ok(())
+}
+
+fn short_commit(commit: Option<&str>) -> &str {
+    commit.map_or("unknown commit", |commit| &commit[..commit.len().min(8)])
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Difference {
+    /// A file the template has and the crate doesn't.
+    Added(PathBuf),
+    /// A file both have, with different contents.
+    Changed(PathBuf),
+}
+
+/// How the template's files differ from the crate's, sorted by path. Files only the crate has
+/// (the user's own) and project state files are not differences.
+fn compare(project: &BTreeMap<PathBuf, Vec<u8>>, template: &BTreeMap<PathBuf, Vec<u8>>) -> Vec<Difference> {
+    template
+        .iter()
+        .filter(|(path, _)| !PROJECT_STATE_FILES.iter().any(|state| Path::new(state) == *path))
+        .filter_map(|(path, contents)| match project.get(path) {
+            None => Some(Difference::Added(path.clone())),
+            Some(ours) if ours != contents => Some(Difference::Changed(path.clone())),
+            Some(_) => None,
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Line diff from `old` to `new`, by longest common subsequence. `None` if either has more than
+/// [`MAX_DIFF_LINES`] lines.
+fn diff_lines<'a>(old: &'a str, new: &'a str) -> Option<Vec<Line<'a>>> {
+    let (old, new): (Vec<_>, Vec<_>) = (old.lines().collect(), new.lines().collect());
+    if old.len() > MAX_DIFF_LINES || new.len() > MAX_DIFF_LINES {
+        return None;
+    }
+    // common[i][j]: length of the longest common subsequence of old[i..] and new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::with_capacity(old.len().max(new.len()));
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(Line::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if common[i + 1][j] >= common[i][j + 1] {
+            lines.push(Line::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(Line::Added(new[j]));
+            j += 1;
+        }
+    }
+    lines.extend(old[i..].iter().copied().map(Line::Removed));
+    lines.extend(new[j..].iter().copied().map(Line::Added));
+    Some(lines)
+}
+
+/// Renders the changed lines with [`DIFF_CONTEXT`] unchanged lines around them, indented.
+fn render_diff(lines: &[Line<'_>]) -> String {
+    let is_change = |line: &Line<'_>| !matches!(line, Line::Same(_));
+    let mut out = String::new();
+    let mut skipped = false;
+    for (idx, line) in lines.iter().enumerate() {
+        let window = idx.saturating_sub(DIFF_CONTEXT)..(idx + DIFF_CONTEXT + 1).min(lines.len());
+        if !lines[window].iter().any(is_change) {
+            skipped = true;
+            continue;
+        }
+        if skipped && !out.is_empty() {
+            out.push_str("     ...\n");
+        }
+        skipped = false;
+        let (marker, text) = match line {
+            Line::Same(text) => (' ', text),
+            Line::Removed(text) => ('-', text),
+            Line::Added(text) => ('+', text),
+        };
+        out.push_str(&format!("   {marker} {text}\n"));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_lines_by_common_subsequence() {
+        assert_eq!(
+            diff_lines("a\nb\nc\n", "a\nc\nd\n"),
+            Some(vec![
+                Line::Same("a"),
+                Line::Removed("b"),
+                Line::Same("c"),
+                Line::Added("d")
+            ])
+        );
+        assert_eq!(diff_lines("", "x"), Some(vec![Line::Added("x")]));
+        assert_eq!(diff_lines(&"x\n".repeat(MAX_DIFF_LINES + 1), "x"), None);
+    }
+
+    #[test]
+    fn renders_changes_with_context() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+        let new = "1\n2\n3\n4\n5\n6\n7\n8\nnine\n";
+        assert_eq!(
+            render_diff(&diff_lines(old, new).unwrap()),
+            "     7\n     8\n   - 9\n   + nine\n"
+        );
+
+        let new = "one\n2\n3\n4\n5\n6\n7\n8\nnine\n";
+        assert_eq!(
+            render_diff(&diff_lines(old, new).unwrap()),
+            "   - 1\n   + one\n     2\n     3\n     ...\n     7\n     8\n   - 9\n   + nine\n"
+        );
+    }
+
+    #[test]
+    fn compare_ignores_user_and_state_files() {
+        let file = |path: &str, contents: &str| (PathBuf::from(path), contents.as_bytes().to_vec());
+        let project = BTreeMap::from([
+            file("Cargo.toml", "old"),
+            file("src/lib.rs", "same"),
+            file("src/extra.rs", "mine"),
+            file(CONFIG_FILE_NAME, "[template]"),
+        ]);
+        let template = BTreeMap::from([
+            file("Cargo.toml", "new"),
+            file("src/lib.rs", "same"),
+            file("README.md", "docs"),
+            file(CONFIG_FILE_NAME, ""),
+        ]);
+        assert_eq!(
+            compare(&project, &template),
+            vec![
+                Difference::Changed(PathBuf::from("Cargo.toml")),
+                Difference::Added(PathBuf::from("README.md")),
+            ]
+        );
+    }
+}
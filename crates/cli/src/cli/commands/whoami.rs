@@ -0,0 +1,115 @@
+// Copyright 2024 The Tari Project
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use ootle_network::Network;
+use tari_ootle_publish_lib::publisher::TemplatePublisher;
+use tari_ootle_publish_lib::{CancellationToken, NetworkConfig};
+use tari_utilities::Hidden;
+
+use crate::cli::account_cache::AccountCache;
+use crate::cli::command::AccountArgs;
+use crate::cli::commands::publish::{
+    load_project_config, resolve_active_network, resolve_custom_network, resolve_wallet_daemon_url,
+};
+use crate::cli::commands::template::publish::resolve_account;
+use crate::cli::config::Config;
+use crate::loading;
+
+#[derive(Clone, Parser, Debug)]
+pub struct WhoamiArgs {
+    /// Path to the template crate directory.
+    /// Defaults to the current directory.
+    #[arg(default_value = ".")]
+    pub path: PathBuf,
+
+    #[clap(flatten)]
+    pub account: AccountArgs,
+
+    /// (Optional) Custom network name.
+    /// Selects a network configured under `[networks.<name>]` in the project or global config.
+    #[arg(short = 'c', long)]
+    pub custom_network: Option<String>,
+
+    /// Wallet daemon JSON-RPC URL.
+    /// Overrides the value in tari.config.toml and global CLI config for this run only.
+    #[arg(long, visible_alias = "network-url")]
+    pub wallet_daemon_url: Option<url::Url>,
+}
+
+/// Prints what `publish` would use for this crate: the network, the wallet daemon and whether it
+/// is reachable, the daemon's default account and the resolved fee account with its source.
+/// Nothing is built, published or written.
+pub async fn handle(
+    config: Config,
+    base_dir: &Path,
+    network_override: Option<Network>,
+    api_key: Option<Hidden<String>>,
+    mut args: WhoamiArgs,
+    cancel: CancellationToken,
+) -> anyhow::Result<()> {
+    args.account.read_account_source().await?;
+    let crate_dir = &args.path;
+
+    let project_config = load_project_config(crate_dir).await?;
+    let network_override = resolve_custom_network(
+        network_override,
+        args.custom_network.as_deref(),
+        &project_config,
+        &config,
+    )?;
+    let network = resolve_active_network(network_override, &project_config, &config);
+    let wallet_daemon_url =
+        resolve_wallet_daemon_url(args.wallet_daemon_url.as_ref(), &project_config, &config, network);
+    println!("🌐 Network: {network}");
+    println!("🔗 Wallet daemon: {wallet_daemon_url}");
+
+    let publisher = TemplatePublisher::new(NetworkConfig::new(wallet_daemon_url).with_api_key(api_key))
+        .with_cancellation_token(cancel);
+    let reachable = match loading!("Connecting to the wallet daemon", publisher.get_wallet_info().await) {
+        Ok(info) => {
+            println!(
+                "✅ Wallet daemon reachable (version {}, network {})",
+                info.version, info.network
+            );
+            if info.network_byte != network.as_byte() {
+                println!(
+                    "⚠️  The wallet daemon is on network '{}', not '{network}'",
+                    info.network
+                );
+            }
+            true
+        },
+        Err(e) => {
+            println!("❌ Wallet daemon unreachable: {e}");
+            false
+        },
+    };
+
+    if reachable {
+        match publisher.get_default_account().await {
+            Ok(Some(account)) => println!("👛 Wallet daemon default account: {account}"),
+            Ok(None) => println!("👛 Wallet daemon default account: none set"),
+            Err(e) => println!("❌ Could not get the wallet daemon default account: {e}"),
+        }
+    }
+
+    let account_cache = AccountCache::load(base_dir).await.unwrap_or_default();
+    match resolve_account(
+        args.account.selected(),
+        &config,
+        &publisher,
+        &project_config,
+        &account_cache,
+        network,
+        None,
+    )
+    .await
+    {
+        Ok((account, source)) => println!("🔍 Account: {account} (from {source})"),
+        Err(e) => println!("❌ No account resolved: {e}"),
+    }
+    Ok(())
+}
@@ -6,8 +6,12 @@ use std::path::{Path, PathBuf};
 use anyhow::Context;
 use dialoguer::{Confirm, Input};
 
+use crate::cli::command::refresh_template_repository;
 use crate::cli::commands::config::{ConfigCommand, resolve_config_path};
+use crate::cli::commands::create::find_enclosing_project;
 use crate::cli::commands::template::init_metadata;
+use crate::cli::util::OutputFormat;
+use crate::loading;
 use crate::project::CONFIG_FILE_NAME;
 
 pub async fn handle() -> anyhow::Result<()> {
@@ -65,6 +69,25 @@ async fn step_template_crate(cwd: &Path) -> anyhow::Result<PathBuf> {
         return Ok(cwd.to_path_buf());
     }
 
+    // `create` refuses to nest a crate in another project without --force, so ask here instead.
+    let force = match find_enclosing_project(cwd) {
+        Some(enclosing) => {
+            let proceed = Confirm::new()
+                .with_prompt(format!(
+                    "This directory is inside {enclosing}. Cargo rejects a nested crate unless it is added to \
+                     that workspace. Create it here anyway?"
+                ))
+                .default(false)
+                .interact()?;
+            if !proceed {
+                println!("ℹ️  Skipping crate creation. Run `tari create <name> --output <DIR>` elsewhere.");
+                return Ok(cwd.to_path_buf());
+            }
+            true
+        },
+        None => false,
+    };
+
     let name: String = Input::new().with_prompt("Template crate name").interact_text()?;
 
     let name = convert_case::Casing::to_case(&name, convert_case::Case::Snake);
@@ -74,21 +97,38 @@ async fn step_template_crate(cwd: &Path) -> anyhow::Result<PathBuf> {
     let base_dir = crate::cli::command::default_base_dir();
     crate::cli::util::create_dir(&base_dir).await?;
 
-    // Minimal repo refresh inline
-    let repo_dir = refresh_template_repo(&base_dir, &config.template_repository).await?;
+    let template_repo = loading!(
+        "Refresh templates repository",
+        refresh_template_repository(
+            &base_dir,
+            &config.template_repository,
+            0,
+            std::time::Duration::from_secs(crate::cli::command::DEFAULT_GIT_TIMEOUT_SECS),
+        )
+        .await
+    )?;
 
     let args = crate::cli::commands::create::CreateArgs {
-        name: Some(name.clone()),
+        name: Some(name),
         template: None,
+        from_git: None,
+        subfolder: None,
+        from_path: None,
+        template_repo_ref: None,
+        expect_tree: None,
+        defines: vec![],
         output: cwd.to_path_buf(),
         skip_init: false,
+        init_git: None,
         skip_metadata: true, // We'll handle metadata in step 3
-        verbose: false,
+        force,
+        replace: false,
+        init: false,
+        verbose: 0,
+        output_format: OutputFormat::Text,
     };
 
-    crate::cli::commands::create::handle(config, repo_dir, args).await?;
-
-    Ok(cwd.join(&name))
+    crate::cli::commands::create::handle(config, template_repo.local_folder().clone(), args).await
 }
 
 async fn step_project_config(_crate_dir: &PathBuf) -> anyhow::Result<()> {
@@ -203,36 +243,3 @@ async fn step_metadata(crate_dir: &Path) -> anyhow::Result<()> {
 
     Ok(())
 }
-
-async fn refresh_template_repo(
-    base_dir: &Path,
-    template_repo: &crate::cli::config::TemplateRepository,
-) -> anyhow::Result<PathBuf> {
-    use crate::cli::util;
-    use crate::git::repository::GitRepository;
-
-    let repos_dir = base_dir.join("template_repositories");
-    util::create_dir(&repos_dir).await?;
-
-    let repo_url_parts: Vec<&str> = template_repo.url.split("/").collect();
-    let repo_name = repo_url_parts.last().context("Failed to get repo name from URL")?;
-    let repo_user = repo_url_parts
-        .get(repo_url_parts.len() - 2)
-        .context("Failed to get repo owner from URL")?;
-    let repo_path = repos_dir.join(repo_user).join(repo_name);
-    let mut repo = GitRepository::new(repo_path.clone());
-
-    if util::dir_exists(&repo_path).await? {
-        repo.load()?;
-        let current_branch = repo.current_branch_name()?;
-        if current_branch != template_repo.branch {
-            repo.pull_changes(Some(template_repo.branch.clone()))?;
-        } else {
-            repo.pull_changes(None)?;
-        }
-    } else {
-        repo.clone_and_checkout(template_repo.url.as_str(), template_repo.branch.as_str())?;
-    }
-
-    Ok(repo.local_folder().join(&template_repo.folder))
-}
@@ -2,9 +2,12 @@
 // SPDX-License-Identifier: BSD-3-Clause
 
 use std::collections::HashMap;
-use std::{path::PathBuf, string::ToString};
+use std::{
+    path::{Path, PathBuf},
+    string::ToString,
+};
 
-use anyhow::anyhow;
+use anyhow::{Context, anyhow};
 use ootle_network::Network;
 use serde::{Deserialize, Serialize};
 use tari_ootle_publish_lib::walletd_client::ComponentAddressOrName;
@@ -16,18 +19,31 @@ use crate::project::{
 
 pub const VALID_OVERRIDE_KEYS: &[&str] = &[
     "template_repository.url",
+    "template_repository.reference",
     "template_repository.branch",
     "template_repository.folder",
     "default_account",
     "default_network",
+    "telemetry",
+    "telemetry_endpoint",
 ];
 
 const VALID_NETWORK_OVERRIDE_FIELDS: &[&str] = &["wallet-daemon-url", "metadata-server-url"];
 
+/// Format version of the config file written by this CLI. When a change would make older files
+/// fail to parse, bump it and append the step upgrading the previous version to [`MIGRATIONS`].
+pub const CONFIG_VERSION: u32 = 1;
+
+/// `MIGRATIONS[n]` upgrades a config file of version `n` to version `n + 1`.
+const MIGRATIONS: &[fn(&mut toml_edit::DocumentMut)] = &[migrate_v0_to_v1];
+
 /// CLI configuration.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct Config {
+    /// File format version, see [`CONFIG_VERSION`]. Files written before versioning have none.
+    #[serde(default)]
+    pub version: u32,
     pub template_repository: TemplateRepository,
     pub default_account: Option<ComponentAddressOrName>,
     /// Default network used when no project config or CLI flag selects one.
@@ -35,6 +51,12 @@ pub struct Config {
     /// Per-network defaults (wallet daemon URL, metadata server URL).
     #[serde(default)]
     pub networks: HashMap<Network, CliNetworkSettings>,
+    /// Send the name of each command run (and nothing else) to `telemetry_endpoint`. Off by
+    /// default, and ignored by builds without the `telemetry` feature.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub telemetry: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub telemetry_endpoint: Option<url::Url>,
 }
 
 /// Per-network CLI defaults used when the project config is absent or does not
@@ -51,8 +73,13 @@ pub struct CliNetworkSettings {
 #[serde(rename_all = "kebab-case")]
 pub struct TemplateRepository {
     pub url: String,
-    pub branch: String,
+    /// Branch, tag or commit to check out. Older configs call this `branch`.
+    #[serde(alias = "branch")]
+    pub reference: String,
     pub folder: String,
+    /// Alternative URLs of the same repository, tried in order when `url` can't be reached.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<String>,
 }
 
 impl Default for Config {
@@ -76,22 +103,71 @@ impl Default for Config {
             },
         );
         Self {
+            version: CONFIG_VERSION,
             template_repository: TemplateRepository {
                 url: "https://github.com/tari-project/wasm-template".to_string(),
-                branch: "main".to_string(),
+                reference: "main".to_string(),
                 folder: "wasm_templates".to_string(),
+                mirrors: Vec::new(),
             },
             default_account: None,
             default_network: Some(Network::Esmeralda),
             networks,
+            telemetry: false,
+            telemetry_endpoint: None,
         }
     }
 }
 
 impl Config {
+    /// Loads the config, expanding `${ENV_VAR}` references in string values.
     pub async fn open(path: &PathBuf) -> anyhow::Result<Self> {
         let content = fs::read_to_string(path).await?;
-        Ok(toml::from_str(content.as_str())?)
+        crate::cli::util::parse_toml_with_env(&content)
+    }
+
+    /// Upgrades `content`, written by an older CLI, to the current format and returns the upgraded
+    /// TOML. Values and comments are kept as written (including `${ENV_VAR}` references). Fails if
+    /// the file is not valid TOML or is not older than [`CONFIG_VERSION`].
+    pub fn migrate(content: &str) -> anyhow::Result<String> {
+        let mut doc = content.parse::<toml_edit::DocumentMut>()?;
+        let version = match doc.get("version") {
+            None => 0,
+            Some(version) => version
+                .as_integer()
+                .and_then(|version| u32::try_from(version).ok())
+                .ok_or_else(|| anyhow!("Invalid config version: {version}"))?,
+        };
+        if version >= CONFIG_VERSION {
+            return Err(anyhow!(
+                "Config version {version} is not older than the current version {CONFIG_VERSION}, nothing to migrate"
+            ));
+        }
+        for migration in &MIGRATIONS[version as usize..] {
+            migration(&mut doc);
+        }
+        doc.insert("version", toml_edit::value(i64::from(CONFIG_VERSION)));
+        Ok(doc.to_string())
+    }
+
+    /// Copies the config file at `path` next to it as `<name>.bak` (or `<name>.bak.<n>` if that
+    /// exists already) and returns the backup's path.
+    pub async fn backup_file(path: &Path) -> anyhow::Result<PathBuf> {
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| anyhow!("Invalid config file path: {}", path.display()))?
+            .to_string_lossy()
+            .into_owned();
+        let mut backup = path.with_file_name(format!("{file_name}.bak"));
+        let mut n = 1;
+        while fs::try_exists(&backup).await? {
+            backup = path.with_file_name(format!("{file_name}.bak.{n}"));
+            n += 1;
+        }
+        fs::copy(path, &backup)
+            .await
+            .with_context(|| format!("backing up {} to {}", path.display(), backup.display()))?;
+        Ok(backup)
     }
 
     pub async fn write_to_file(&self, path: &PathBuf) -> anyhow::Result<()> {
@@ -119,6 +195,11 @@ impl Config {
         false
     }
 
+    /// Whether a `[networks.<network>]` section is configured.
+    pub fn has_network(&self, network: Network) -> bool {
+        self.networks.contains_key(&network)
+    }
+
     pub fn wallet_daemon_url(&self, network: Network) -> Option<&url::Url> {
         self.networks.get(&network).and_then(|n| n.wallet_daemon_url.as_ref())
     }
@@ -136,18 +217,24 @@ impl Config {
             "template_repository.url" => {
                 self.template_repository.url = value.to_string();
             },
-            "template_repository.branch" => {
-                self.template_repository.branch = value.to_string();
+            "template_repository.reference" | "template_repository.branch" => {
+                self.template_repository.reference = value.to_string();
             },
             "template_repository.folder" => {
                 self.template_repository.folder = value.to_string();
             },
             "default_account" => {
-                self.default_account = Some(value.parse()?);
+                self.default_account = Some(crate::cli::command::account_parser(value).map_err(|e| anyhow!(e))?);
             },
             "default_network" => {
                 self.default_network = Some(value.parse().map_err(|e| anyhow!("Invalid network: {e}"))?);
             },
+            "telemetry" => {
+                self.telemetry = value.parse().map_err(|e| anyhow!("Invalid telemetry value: {e}"))?;
+            },
+            "telemetry_endpoint" => {
+                self.telemetry_endpoint = Some(value.parse().map_err(|e| anyhow!("Invalid URL: {e}"))?);
+            },
             _ => self.apply_network_override(key, value)?,
         }
 
@@ -168,3 +255,104 @@ impl Config {
         Ok(())
     }
 }
+
+/// Pre-versioned files called the template reference `branch`.
+fn migrate_v0_to_v1(doc: &mut toml_edit::DocumentMut) {
+    let Some(repository) = doc
+        .get_mut("template-repository")
+        .and_then(toml_edit::Item::as_table_like_mut)
+    else {
+        return;
+    };
+    if !repository.contains_key("reference")
+        && let Some(branch) = repository.remove("branch")
+    {
+        repository.insert("reference", branch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn telemetry_is_off_by_default() {
+        let config = Config::default();
+        assert!(!config.telemetry);
+        assert!(config.telemetry_endpoint.is_none());
+        let serialized = toml::to_string(&config).unwrap();
+        assert!(!serialized.contains("telemetry"), "got: {serialized}");
+
+        let mut config = config;
+        config.override_data("telemetry", "true").unwrap();
+        assert!(config.telemetry);
+        assert!(config.override_data("telemetry", "yes").is_err());
+    }
+
+    #[test]
+    fn old_format_config_is_migrated_not_lost() {
+        let old = r#"
+# my account
+default-account = { Name = "alice" }
+
+[template-repository]
+url = "https://example.com/templates"
+branch = "v1.2.0"
+folder = "wasm_templates"
+"#;
+        let migrated = Config::migrate(old).unwrap();
+        assert!(migrated.contains("# my account\n"), "got: {migrated}");
+        assert!(!migrated.contains("branch"), "got: {migrated}");
+        let config: Config = toml::from_str(&migrated).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.template_repository.url, "https://example.com/templates");
+        assert_eq!(config.template_repository.reference, "v1.2.0");
+        assert_eq!(config.default_account.unwrap().to_string(), "alice");
+
+        assert!(Config::migrate(&migrated).is_err());
+    }
+
+    #[tokio::test]
+    async fn backup_does_not_overwrite_earlier_backups() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("tari.config.toml");
+        std::fs::write(&path, "first").unwrap();
+        let first = Config::backup_file(&path).await.unwrap();
+        std::fs::write(&path, "second").unwrap();
+        let second = Config::backup_file(&path).await.unwrap();
+
+        assert_eq!(first, temp_dir.path().join("tari.config.toml.bak"));
+        assert_eq!(second, temp_dir.path().join("tari.config.toml.bak.1"));
+        assert_eq!(std::fs::read_to_string(first).unwrap(), "first");
+        assert_eq!(std::fs::read_to_string(second).unwrap(), "second");
+    }
+
+    #[test]
+    fn template_repository_accepts_legacy_branch_key() {
+        let repo: TemplateRepository = toml::from_str(
+            "url = \"https://example.com/templates\"\nbranch = \"v1.2.0\"\nfolder = \"wasm_templates\"\n",
+        )
+        .expect("legacy config should parse");
+        assert_eq!(repo.reference, "v1.2.0");
+    }
+
+    #[test]
+    fn template_repository_mirrors_are_optional() {
+        let repo: TemplateRepository = toml::from_str(
+            "url = \"https://github.com/org/templates\"\nreference = \"main\"\nfolder = \"wasm_templates\"\n\
+             mirrors = [\"https://gitlab.com/org/templates\"]\n",
+        )
+        .unwrap();
+        assert_eq!(repo.mirrors, ["https://gitlab.com/org/templates"]);
+        assert!(!toml::to_string(&Config::default()).unwrap().contains("mirrors"));
+    }
+
+    #[test]
+    fn reference_override_accepts_both_keys() {
+        let mut config = Config::default();
+        config.override_data("template_repository.reference", "v1.2.0").unwrap();
+        assert_eq!(config.template_repository.reference, "v1.2.0");
+        config.override_data("template_repository.branch", "dev").unwrap();
+        assert_eq!(config.template_repository.reference, "dev");
+    }
+}
@@ -0,0 +1,80 @@
+// Copyright 2024 The Tari Project
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::backtrace::BacktraceStatus;
+use std::fmt::Write;
+
+use tari_ootle_publish_lib::PublisherError;
+
+/// Renders `error` for the terminal: the top-level message followed by an indented
+/// `Caused by:` list. Well-known publish failures get a tailored message instead of the chain.
+/// The backtrace is only included when one was captured (`RUST_BACKTRACE` is set).
+pub fn render_error(error: &anyhow::Error) -> String {
+    let mut out = match error.chain().find_map(tailored_message) {
+        Some(message) => message,
+        None => render_chain(error),
+    };
+
+    let backtrace = error.backtrace();
+    if backtrace.status() == BacktraceStatus::Captured {
+        let _ = write!(out, "\n\nBacktrace:\n{backtrace}");
+    }
+    out
+}
+
+fn render_chain(error: &anyhow::Error) -> String {
+    let mut out = error.to_string();
+    let mut causes = error.chain().skip(1).peekable();
+    if causes.peek().is_some() {
+        out.push_str("\n\nCaused by:");
+        for cause in causes {
+            let _ = write!(out, "\n    {}", cause.to_string().replace('\n', "\n    "));
+        }
+    }
+    out
+}
+
+fn tailored_message(cause: &(dyn std::error::Error + 'static)) -> Option<String> {
+    match cause.downcast_ref::<PublisherError>()? {
+        PublisherError::InsufficientBalance { current, fee } => Some(format!(
+            "Insufficient balance to publish: the account holds {current} but the fee may be up to {fee}.\n\
+             Top up the account, pick another one with `--account`, or lower `--max-fee` or `--fee-multiplier`."
+        )),
+        PublisherError::WaitForTransactionTimeout(transaction_id) => Some(format!(
+            "Timed out waiting for transaction {transaction_id} to finalize.\n\
+             It may still be accepted; check its status in the wallet before publishing again."
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{Context, anyhow};
+
+    #[test]
+    fn renders_causes_on_indented_lines() {
+        let error = Err::<(), _>(anyhow!("connection refused"))
+            .context("Failed to connect to the wallet")
+            .unwrap_err();
+        assert_eq!(
+            render_chain(&error),
+            "Failed to connect to the wallet\n\nCaused by:\n    connection refused"
+        );
+        assert_eq!(render_chain(&anyhow!("plain")), "plain");
+    }
+
+    #[test]
+    fn tailors_known_publish_errors() {
+        let error = Err::<(), _>(PublisherError::WaitForTransactionTimeout("tx_1".to_string()))
+            .context("Publishing template")
+            .unwrap_err();
+        let rendered = render_error(&error);
+        assert!(
+            rendered.starts_with("Timed out waiting for transaction tx_1"),
+            "got: {rendered}"
+        );
+        assert!(!rendered.contains("Caused by"), "got: {rendered}");
+    }
+}
@@ -0,0 +1,97 @@
+// Copyright 2024 The Tari Project
+// SPDX-License-Identifier: BSD-3-Clause
+
+use tari_ootle_publish_lib::PublisherError;
+
+use crate::cli::command::{ConfigLoadError, GitTimeoutError, WalletDaemonAuthError};
+use crate::cli::commands::create::CreateHandlerError;
+use crate::cli::commands::publish::BuildError;
+use crate::git::repository::Error as GitError;
+
+/// Process exit codes, so scripts can tell error classes apart.
+/// Documented in `docs/03-reference/cli-commands.md`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitCode {
+    /// Any error not covered below.
+    Other = 1,
+    /// Invalid arguments or input, including an unreadable config file (clap also uses 2 for
+    /// argument errors).
+    Usage = 2,
+    /// The wallet daemon or a remote git repository could not be reached or rejected the request.
+    Network = 3,
+    /// Building the template failed.
+    Build = 4,
+    /// The network rejected the publish transaction, or the account cannot pay for it.
+    PublishRejected = 5,
+}
+
+impl ExitCode {
+    /// Classifies `error` by the first cause in its chain that has a known type.
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        error.chain().find_map(Self::classify).unwrap_or(Self::Other)
+    }
+
+    fn classify(cause: &(dyn std::error::Error + 'static)) -> Option<Self> {
+        if let Some(error) = cause.downcast_ref::<PublisherError>() {
+            return match error {
+                PublisherError::WalletDaemonClient(_)
+                | PublisherError::Grpc(_)
+                | PublisherError::WaitForTransactionTimeout(_) => Some(Self::Network),
+                PublisherError::InsufficientBalance { .. }
+                | PublisherError::InvalidTransaction(..)
+                | PublisherError::TransientRejection(..) => Some(Self::PublishRejected),
+                _ => None,
+            };
+        }
+        if cause.is::<WalletDaemonAuthError>() || cause.is::<GitTimeoutError>() {
+            return Some(Self::Network);
+        }
+        if cause.is::<BuildError>() {
+            return Some(Self::Build);
+        }
+        if cause.is::<CreateHandlerError>() || cause.is::<ConfigLoadError>() {
+            return Some(Self::Usage);
+        }
+        if let Some(error) = cause.downcast_ref::<GitError>() {
+            return error.is_network().then_some(Self::Network);
+        }
+        None
+    }
+
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{Context, anyhow};
+
+    #[test]
+    fn classifies_errors_through_context() {
+        let rejected = Err::<(), _>(PublisherError::InvalidTransaction(
+            "tx_1".to_string(),
+            "rejected".to_string(),
+        ))
+        .context("Publishing template")
+        .unwrap_err();
+        assert_eq!(ExitCode::from_error(&rejected), ExitCode::PublishRejected);
+
+        let build = anyhow::Error::from(BuildError::MissingBinary {
+            crate_name: "counter".to_string(),
+            path: "counter.wasm".into(),
+            log: String::new(),
+        });
+        assert_eq!(ExitCode::from_error(&build), ExitCode::Build);
+
+        let fetch = anyhow::Error::from(GitError::Git2(git2::Error::new(
+            git2::ErrorCode::GenericError,
+            git2::ErrorClass::Net,
+            "could not resolve host",
+        )));
+        assert_eq!(ExitCode::from_error(&fetch), ExitCode::Network);
+
+        assert_eq!(ExitCode::from_error(&anyhow!("something else")), ExitCode::Other);
+    }
+}
@@ -3,46 +3,33 @@
 
 #[macro_export]
 macro_rules! loading {
-    ( $text:literal, $call:expr ) => {{
-        let mut skin = termimad::MadSkin::default();
-        skin.bold.set_fg(termimad::crossterm::style::Color::Magenta);
-        let mut loader = spinners::Spinner::new(spinners::Spinners::Dots, skin.inline($text).to_string());
-        let result = match $call {
-            Ok(res) => {
-                loader.stop_with_symbol("✅");
-                Ok(res)
-            },
-            Err(error) => {
-                loader.stop_with_symbol("❌");
-                Err(error)
-            },
-        };
-        result
-    }};
-    ( $text:expr, $call:expr ) => {{
-        let mut skin = termimad::MadSkin::default();
-        skin.bold.set_fg(termimad::crossterm::style::Color::Magenta);
-        let mut loader = spinners::Spinner::new(spinners::Spinners::Dots, skin.inline($text.as_str()).to_string());
-        let result = match $call {
-            Ok(res) => {
-                loader.stop_with_symbol("✅");
-                Ok(res)
-            },
-            Err(error) => {
-                loader.stop_with_symbol("❌");
-                Err(error)
-            },
-        };
+    // Same as below, but draws the spinner on the given `spinners::Stream`.
+    ( @stream $stream:expr, $text:expr, $call:expr ) => {{
+        let skin = $crate::cli::color::skin();
+        let text = $text;
+        let text: &str = text.as_ref();
+        let message = skin.inline(text).to_string();
+        let started = std::time::Instant::now();
+        let mut loader =
+            spinners::Spinner::with_timer_and_stream(spinners::Spinners::Dots, message.clone(), $stream);
+        let result = $call;
+        let symbol = if result.is_ok() { "✅" } else { "❌" };
+        loader.stop_and_persist(
+            symbol,
+            format!("{message} ({:.1}s)", started.elapsed().as_secs_f64()),
+        );
         result
     }};
+    // Shows a spinner with the elapsed time while `$call` runs, then the phase's total time.
+    ( $text:expr, $call:expr ) => {
+        $crate::loading!(@stream spinners::Stream::Stdout, $text, $call)
+    };
 }
 
 #[macro_export]
 macro_rules! md_println {
     ( $text:literal, $($args:tt)* ) => {{
-        let mut skin = termimad::MadSkin::default();
-        skin.bold.set_fg(termimad::crossterm::style::Color::Magenta);
-        skin.print_inline(format!($text, $($args)*).as_str());
+        $crate::cli::color::skin().print_inline(format!($text, $($args)*).as_str());
         println!();
     }};
 }
@@ -1,8 +1,16 @@
 // Copyright 2024 The Tari Project
 // SPDX-License-Identifier: BSD-3-Clause
 
+pub mod account_cache;
+pub mod build_cache;
+pub mod build_info;
+pub mod color;
 pub mod command;
 pub mod commands;
 pub mod config;
+pub mod error_report;
+pub mod exit_code;
 pub mod macros;
+#[cfg(feature = "telemetry")]
+pub mod telemetry;
 pub mod util;
@@ -0,0 +1,92 @@
+// Copyright 2024 The Tari Project
+// SPDX-License-Identifier: BSD-3-Clause
+
+//! Opt-in anonymous usage counting, only compiled with the `telemetry` feature. Nothing is sent
+//! unless the CLI config sets both `telemetry = true` and a `telemetry-endpoint`. The report holds
+//! the command name and the CLI version: no paths, accounts, addresses or arguments.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::task::JoinHandle;
+
+use crate::cli::command::Command;
+use crate::cli::config::Config;
+
+/// Marker in the base directory recording that the notice was shown.
+const NOTICE_FILE_NAME: &str = "telemetry_notice_shown";
+/// The report is dropped if the endpoint doesn't answer within this time.
+const REPORT_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Serialize)]
+struct UsageReport {
+    command: &'static str,
+    version: &'static str,
+}
+
+/// Reports that `command` ran, if the config enables telemetry. The request runs in the
+/// background while the command does; pass the returned handle to [`finish_report`] once the
+/// command is done. Failures are ignored.
+pub fn report_command(config: &Config, base_dir: &Path, command: &Command) -> Option<JoinHandle<()>> {
+    let endpoint = config.telemetry_endpoint.clone().filter(|_| config.telemetry)?;
+    show_notice_once(base_dir, &endpoint);
+    let report = UsageReport {
+        command: command_name(command),
+        version: env!("CARGO_PKG_VERSION"),
+    };
+    Some(tokio::spawn(async move {
+        let Ok(client) = reqwest::Client::builder().timeout(REPORT_TIMEOUT).build() else {
+            return;
+        };
+        let _ = client.post(endpoint).json(&report).send().await;
+    }))
+}
+
+/// Waits, for at most [`REPORT_TIMEOUT`], for a report started by [`report_command`] to be sent.
+/// The process exits right after the command, which would otherwise drop a report still in flight.
+pub async fn finish_report(report: Option<JoinHandle<()>>) {
+    if let Some(report) = report {
+        let _ = tokio::time::timeout(REPORT_TIMEOUT, report).await;
+    }
+}
+
+/// The file recording that the telemetry notice was shown for `base_dir`.
+pub fn notice_marker_path(base_dir: &Path) -> PathBuf {
+    base_dir.join(NOTICE_FILE_NAME)
+}
+
+/// Tells the user, once per base directory, what is sent and how to turn it off. Printed on
+/// stderr so JSON output stays clean.
+fn show_notice_once(base_dir: &Path, endpoint: &url::Url) {
+    let marker = notice_marker_path(base_dir);
+    if marker.exists() {
+        return;
+    }
+    eprintln!(
+        "ℹ️  Telemetry is enabled: the name of each command run and the CLI version are sent to {endpoint}. \
+         Nothing else is sent. Set `telemetry = false` in the CLI config (or pass `-e telemetry=false`) to stop."
+    );
+    let _ = std::fs::write(marker, "");
+}
+
+fn command_name(command: &Command) -> &'static str {
+    match command {
+        Command::Init { .. } => "init",
+        Command::Create { .. } => "create",
+        Command::Upgrade { .. } => "upgrade",
+        Command::Rename { .. } => "rename",
+        Command::Build { .. } => "build",
+        Command::Bundle { .. } => "bundle",
+        Command::Inspect { .. } => "inspect",
+        Command::Fee { .. } => "fee",
+        Command::Publish { .. } => "publish",
+        Command::Whoami { .. } => "whoami",
+        Command::Templates { .. } => "templates",
+        Command::Template { .. } => "template",
+        Command::Metadata { .. } => "metadata",
+        Command::Clean { .. } => "clean",
+        Command::Config { .. } => "config",
+        Command::Version => "version",
+    }
+}
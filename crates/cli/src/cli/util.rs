@@ -1,13 +1,19 @@
 // Copyright 2024 The Tari Project
 // SPDX-License-Identifier: BSD-3-Clause
 
-use std::{fs::Metadata, io, path::PathBuf};
+use std::{
+    fs::Metadata,
+    io,
+    path::{Path, PathBuf},
+};
 
+use anyhow::anyhow;
 use dialoguer::FuzzySelect;
 use ootle_network::Network;
+use serde::de::DeserializeOwned;
 use tokio::fs;
 
-pub async fn create_dir(dir: &PathBuf) -> io::Result<()> {
+pub async fn create_dir(dir: &Path) -> io::Result<()> {
     fs::create_dir_all(dir).await
 }
 
@@ -15,14 +21,39 @@ pub async fn file_exists(file: &PathBuf) -> io::Result<bool> {
     Ok(fs::try_exists(file).await? && path_metadata(file).await?.is_file())
 }
 
-pub async fn dir_exists(dir: &PathBuf) -> io::Result<bool> {
+pub async fn dir_exists(dir: &Path) -> io::Result<bool> {
     Ok(fs::try_exists(dir).await? && path_metadata(dir).await?.is_dir())
 }
 
-pub async fn path_metadata(path: &PathBuf) -> io::Result<Metadata> {
+pub async fn path_metadata(path: &Path) -> io::Result<Metadata> {
     fs::metadata(path).await
 }
 
+/// Reads a single value (an account, an API key) from a file, so it stays out of process
+/// listings and shell history. Surrounding whitespace, such as a trailing newline, is trimmed.
+pub async fn read_value_file(path: &PathBuf) -> anyhow::Result<String> {
+    let content = fs::read_to_string(path)
+        .await
+        .map_err(|e| anyhow!("Failed to read {}: {e}", path.display()))?;
+    non_empty_value(content, &path.display().to_string())
+}
+
+/// Same as [`read_value_file`], reading standard input to its end.
+pub fn read_value_stdin() -> anyhow::Result<String> {
+    let mut content = String::new();
+    io::Read::read_to_string(&mut io::stdin(), &mut content)
+        .map_err(|e| anyhow!("Failed to read standard input: {e}"))?;
+    non_empty_value(content, "standard input")
+}
+
+fn non_empty_value(content: String, source: &str) -> anyhow::Result<String> {
+    let value = content.trim();
+    if value.is_empty() {
+        return Err(anyhow!("{source} is empty"));
+    }
+    Ok(value.to_string())
+}
+
 pub fn cli_select<'a, T: std::fmt::Display>(prompt: &str, items: &'a [T]) -> anyhow::Result<&'a T> {
     let selection = FuzzySelect::new()
         .with_prompt(prompt)
@@ -33,6 +64,70 @@ pub fn cli_select<'a, T: std::fmt::Display>(prompt: &str, items: &'a [T]) -> any
     Ok(&items[selection])
 }
 
+/// How a command reports its result.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable progress and summary.
+    #[default]
+    Text,
+    /// A single JSON document on stdout. Progress goes to stderr.
+    Json,
+}
+
+impl OutputFormat {
+    pub fn is_json(self) -> bool {
+        self == Self::Json
+    }
+
+    /// Stream for progress spinners, keeping stdout clean for JSON output.
+    pub fn progress_stream(self) -> spinners::Stream {
+        match self {
+            Self::Text => spinners::Stream::Stdout,
+            Self::Json => spinners::Stream::Stderr,
+        }
+    }
+}
+
+/// Collects non-fatal warnings for a command. In text mode each warning is printed as it happens
+/// (on stdout, or stderr after [`Warnings::on_stderr`]); in JSON mode they are kept for the
+/// `warnings` array of the final report, so automation can see them.
+#[derive(Debug, Default)]
+pub struct Warnings {
+    output: OutputFormat,
+    stderr: bool,
+    collected: Vec<String>,
+}
+
+impl Warnings {
+    pub fn new(output: OutputFormat) -> Self {
+        Self {
+            output,
+            stderr: false,
+            collected: vec![],
+        }
+    }
+
+    /// Prints text-mode warnings on stderr, for commands whose stdout is meant to be captured.
+    pub fn on_stderr(mut self) -> Self {
+        self.stderr = true;
+        self
+    }
+
+    pub fn warn(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        match self.output {
+            OutputFormat::Text if self.stderr => eprintln!("⚠️  {message}"),
+            OutputFormat::Text => println!("⚠️  {message}"),
+            OutputFormat::Json => self.collected.push(message),
+        }
+    }
+
+    /// The warnings collected in JSON mode (empty in text mode, where they were already printed).
+    pub fn into_vec(self) -> Vec<String> {
+        self.collected
+    }
+}
+
 pub fn human_bytes(n: usize) -> String {
     human_bytes::human_bytes(n as f64)
 }
@@ -44,3 +139,191 @@ pub fn get_default_metadata_server_url(network: Network) -> Option<&'static str>
         _ => None,
     }
 }
+
+/// Maximum number of "did you mean" suggestions returned by [`suggest_similar`].
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Returns up to three candidates that are close (by edit distance) to `input`, closest first.
+///
+/// Candidates further away than roughly a third of the input's length are discarded so that
+/// completely unrelated values are not suggested.
+pub fn suggest_similar<'a, I>(input: &str, candidates: I) -> Vec<String>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let input = input.to_lowercase();
+    let threshold = (input.chars().count() / 3).max(2);
+    let mut scored: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(&input, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= threshold)
+        .collect();
+    scored.sort_by(|(a_dist, a), (b_dist, b)| a_dist.cmp(b_dist).then_with(|| a.cmp(b)));
+    scored.dedup_by(|(_, a), (_, b)| a == b);
+    scored
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate.to_string())
+        .collect()
+}
+
+/// Levenshtein distance between two strings, counted in chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+    for (i, a_char) in a.chars().enumerate() {
+        curr[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(a_char != *b_char);
+            curr[j + 1] = substitution.min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[b.len()]
+}
+
+/// Parses TOML `content`, first replacing `${NAME}` in string values with the environment
+/// variable `NAME`. Fails if a referenced variable is not set.
+pub fn parse_toml_with_env<T: DeserializeOwned>(content: &str) -> anyhow::Result<T> {
+    parse_toml_with_vars(content, &|name| std::env::var(name).ok())
+}
+
+fn parse_toml_with_vars<T: DeserializeOwned>(
+    content: &str,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> anyhow::Result<T> {
+    // Keep toml's line/column error reporting for the common case of no variables.
+    if !content.contains("${") {
+        return Ok(toml::from_str(content)?);
+    }
+    let mut value = toml::Value::Table(toml::from_str::<toml::Table>(content)?);
+    expand_value(&mut value, lookup)?;
+    Ok(value.try_into()?)
+}
+
+fn expand_value(value: &mut toml::Value, lookup: &dyn Fn(&str) -> Option<String>) -> anyhow::Result<()> {
+    match value {
+        toml::Value::String(s) => *s = expand_env_vars(s, lookup)?,
+        toml::Value::Array(items) => items.iter_mut().try_for_each(|item| expand_value(item, lookup))?,
+        toml::Value::Table(table) => table.iter_mut().try_for_each(|(_, item)| expand_value(item, lookup))?,
+        _ => {},
+    }
+    Ok(())
+}
+
+/// Replaces every `${NAME}` in `input` with `lookup(NAME)`.
+fn expand_env_vars(input: &str, lookup: &dyn Fn(&str) -> Option<String>) -> anyhow::Result<String> {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| anyhow!("Unterminated `${{` in config value '{input}'"))?;
+        let name = &after[..end];
+        if name.is_empty() {
+            return Err(anyhow!("Empty `${{}}` in config value '{input}'"));
+        }
+        let value = lookup(name)
+            .ok_or_else(|| anyhow!("Environment variable {name} is referenced in the config but is not set"))?;
+        out.push_str(&value);
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(name: &str) -> Option<String> {
+        match name {
+            "TARI_WALLET_URL" => Some("http://wallet:5100/json_rpc".to_string()),
+            "TARI_ACCOUNT" => Some("alice".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn expands_set_variables_in_string_values() {
+        #[derive(serde::Deserialize)]
+        struct Settings {
+            url: url::Url,
+            accounts: Vec<String>,
+        }
+        let settings: Settings = parse_toml_with_vars(
+            "url = \"${TARI_WALLET_URL}\"\naccounts = [\"${TARI_ACCOUNT}-2\", \"$plain\"]",
+            &vars,
+        )
+        .unwrap();
+        assert_eq!(settings.url.as_str(), "http://wallet:5100/json_rpc");
+        assert_eq!(settings.accounts, ["alice-2", "$plain"]);
+    }
+
+    #[test]
+    fn unset_variable_is_an_error() {
+        let err = parse_toml_with_vars::<toml::Table>("url = \"${TARI_MISSING}\"", &vars).unwrap_err();
+        assert!(err.to_string().contains("TARI_MISSING"), "got: {err}");
+        assert!(expand_env_vars("${TARI_ACCOUNT", &vars).is_err());
+    }
+
+    #[test]
+    fn edit_distance_counts_operations() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("abc", ""), 3);
+        assert_eq!(edit_distance("fungable", "fungible"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn suggests_closest_candidates_first() {
+        let candidates = ["fungible", "non_fungible", "meme_coin", "counter"];
+        let suggestions = suggest_similar("fungable", candidates);
+        assert_eq!(suggestions.first().map(String::as_str), Some("fungible"));
+        assert!(!suggestions.contains(&"counter".to_string()));
+    }
+
+    #[test]
+    fn suggestions_are_case_insensitive_and_capped() {
+        let candidates = ["aaa", "aab", "aac", "aad"];
+        let suggestions = suggest_similar("AAA", candidates);
+        assert_eq!(suggestions.len(), MAX_SUGGESTIONS);
+        assert_eq!(suggestions[0], "aaa");
+    }
+
+    #[test]
+    fn no_suggestions_for_unrelated_input() {
+        assert!(suggest_similar("zzzzzzzz", ["fungible", "meme_coin"]).is_empty());
+    }
+
+    #[test]
+    fn json_warnings_are_collected() {
+        let mut warnings = Warnings::new(OutputFormat::Json);
+        warnings.warn("WASM binary size exceeded");
+        warnings.warn(format!("Overwrote {}", "Cargo.toml"));
+        assert_eq!(
+            warnings.into_vec(),
+            ["WASM binary size exceeded", "Overwrote Cargo.toml"]
+        );
+
+        let mut warnings = Warnings::new(OutputFormat::Text);
+        warnings.warn("printed, not collected");
+        assert!(warnings.into_vec().is_empty());
+    }
+
+    #[tokio::test]
+    async fn value_files_are_trimmed_and_must_not_be_empty() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("account");
+        fs::write(&path, "  alice\n").await.unwrap();
+        assert_eq!(read_value_file(&path).await.unwrap(), "alice");
+
+        fs::write(&path, "\n").await.unwrap();
+        let err = read_value_file(&path).await.unwrap_err();
+        assert!(err.to_string().contains("is empty"), "got: {err}");
+        assert!(read_value_file(&temp_dir.path().join("missing")).await.is_err());
+    }
+}
@@ -2,13 +2,58 @@
 // SPDX-License-Identifier: BSD-3-Clause
 
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use git2::{Repository, build::RepoBuilder};
+use git2::{BranchType, RemoteCallbacks, Repository, RepositoryOpenFlags, build::RepoBuilder};
 use thiserror::Error;
 
 pub struct GitRepository {
     repository: Option<Repository>,
     local_folder: PathBuf,
+    verbosity: u8,
+    transfer: TransferControl,
+}
+
+/// Shared with the thread running a fetch or clone, so another task can abort it and see when it
+/// last received data. git2 checks for an abort whenever it reports transfer progress.
+#[derive(Clone, Debug, Default)]
+pub struct TransferControl {
+    aborted: Arc<AtomicBool>,
+    last_progress: Arc<Mutex<Option<Instant>>>,
+}
+
+impl TransferControl {
+    /// Makes the running (or next) transfer fail at its next progress report.
+    pub fn abort(&self) {
+        self.aborted.store(true, Ordering::Relaxed);
+    }
+
+    /// Time since the transfer last received data, or `None` if it never did.
+    pub fn since_last_progress(&self) -> Option<Duration> {
+        self.last_progress
+            .lock()
+            .ok()
+            .and_then(|last| last.map(|instant| instant.elapsed()))
+    }
+
+    fn callbacks(&self) -> RemoteCallbacks<'static> {
+        let aborted = self.aborted.clone();
+        let last_progress = self.last_progress.clone();
+        let mut received = 0;
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.transfer_progress(move |stats| {
+            if stats.received_bytes() != received {
+                received = stats.received_bytes();
+                if let Ok(mut last) = last_progress.lock() {
+                    *last = Some(Instant::now());
+                }
+            }
+            !aborted.load(Ordering::Relaxed)
+        });
+        callbacks
+    }
 }
 
 #[derive(Error, Debug)]
@@ -19,20 +64,91 @@ pub enum Error {
     RepositoryNotInitialized,
     #[error("Invalid branch name!")]
     InvalidBranchName,
-    #[error("Current reference is not a branch!")]
-    RefIsNotBranch,
+    #[error("Checked out tree {actual} does not match the expected tree {expected}!")]
+    TreeMismatch { expected: String, actual: String },
+}
+
+impl Error {
+    /// Whether the remote could not be reached or refused the connection.
+    pub fn is_network(&self) -> bool {
+        matches!(
+            self,
+            Error::Git2(error) if matches!(
+                error.class(),
+                git2::ErrorClass::Net | git2::ErrorClass::Http | git2::ErrorClass::Ssh
+            )
+        )
+    }
+}
+
+/// What HEAD currently points at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HeadState {
+    /// HEAD is on a local branch.
+    Branch(String),
+    /// HEAD is detached at a commit (e.g. after checking out a tag or a pinned commit).
+    Detached(String),
+}
+
+impl HeadState {
+    /// Returns true if HEAD is detached at the commit `reference` abbreviates. Branch and tag names
+    /// never match, since only a commit hash (at least 7 hex digits) can identify a commit here.
+    pub fn is_detached_at(&self, reference: &str) -> bool {
+        match self {
+            HeadState::Detached(sha) => {
+                reference.len() >= 7
+                    && reference.chars().all(|c| c.is_ascii_hexdigit())
+                    && sha.starts_with(&reference.to_ascii_lowercase())
+            },
+            HeadState::Branch(_) => false,
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Length of a full tree hash in hex digits.
+pub const TREE_HASH_LEN: usize = 40;
+/// Shortest tree hash prefix accepted by [`GitRepository::verify_tree`], as for abbreviated
+/// commit hashes.
+pub const MIN_TREE_HASH_PREFIX_LEN: usize = 7;
+
+/// Directories that repository discovery must not move up into: those in
+/// `GIT_CEILING_DIRECTORIES` (as git honors it) and the user's home directory, so a repository
+/// in `~` (e.g. for dotfiles) is never taken for the project's repository.
+pub fn ceiling_dirs() -> Vec<PathBuf> {
+    let mut ceilings: Vec<PathBuf> = std::env::var_os("GIT_CEILING_DIRECTORIES")
+        .map(|dirs| std::env::split_paths(&dirs).filter(|dir| dir.is_absolute()).collect())
+        .unwrap_or_default();
+    ceilings.extend(dirs_next::home_dir());
+    ceilings
+        .into_iter()
+        .map(|dir| dir.canonicalize().unwrap_or(dir))
+        .collect()
+}
+
 impl GitRepository {
     pub fn new(local_folder: PathBuf) -> Self {
         Self {
             repository: None,
             local_folder,
+            verbosity: 0,
+            transfer: TransferControl::default(),
         }
     }
 
+    /// Uses `transfer` for fetches and clones, so they can be aborted from another task.
+    pub fn with_transfer_control(mut self, transfer: TransferControl) -> Self {
+        self.transfer = transfer;
+        self
+    }
+
+    /// At level 2 and above, network operations report what they transferred on stderr.
+    pub fn with_verbosity(mut self, verbosity: u8) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
     /// Initializes a git repository in [`local_folder`].
     pub fn init(&mut self) -> Result<()> {
         self.repository = Some(Repository::init(&self.local_folder)?);
@@ -45,45 +161,89 @@ impl GitRepository {
         Ok(())
     }
 
-    /// Does a clone and checkout operation in [`local_folder`] based on the given repository [`url`] and [`branch`].
-    pub fn clone_and_checkout(&mut self, url: &str, branch: &str) -> Result<()> {
+    /// Loads the git repository containing [`local_folder`], searching parent directories up to
+    /// the [`ceiling_dirs`] and without crossing filesystems.
+    pub fn discover(&mut self) -> Result<()> {
+        let repository = Repository::open_ext(&self.local_folder, RepositoryOpenFlags::empty(), ceiling_dirs())
+            .map_err(Error::Git2)?;
+        self.repository = Some(repository);
+        Ok(())
+    }
+
+    /// Does a clone in [`local_folder`] of the given repository [`url`] and checks out [`reference`]
+    /// (a branch, tag or commit, see [`Self::checkout_reference`]).
+    pub fn clone_and_checkout(&mut self, url: &str, reference: &str) -> Result<()> {
+        if self.verbosity >= 2 {
+            eprintln!("ℹ️  Cloning {url} into {}", self.local_folder.display());
+        }
+        let mut fetch_opts = git2::FetchOptions::new();
+        fetch_opts.remote_callbacks(self.transfer.callbacks());
         self.repository = Some(
             RepoBuilder::new()
-                .branch(branch)
+                .fetch_options(fetch_opts)
                 .clone(url, &self.local_folder)
                 .map_err(Error::Git2)?,
         );
+        self.checkout_reference(reference)
+    }
 
+    /// Points `origin` at `url`.
+    pub fn set_origin_url(&self, url: &str) -> Result<()> {
+        self.repository()?.remote_set_url("origin", url)?;
         Ok(())
     }
 
-    /// Pulling latest changes on an optional branch (default is the current one).
+    /// Fetches from `origin` and checks out an optional reference (default is the current branch).
+    /// When no reference is given and HEAD is detached (a pinned tag or commit), only fetches.
     /// Note: this method always force checkout to latest head.
-    pub fn pull_changes(&self, branch: Option<String>) -> Result<()> {
+    pub fn pull_changes(&self, reference: Option<String>) -> Result<()> {
         let repo = self.repository()?;
-        let current_branch_name = if let Some(branch) = branch {
-            branch
-        } else {
-            self.current_branch_name()?
-        };
         let mut remote = repo.find_remote("origin")?;
 
-        // fetch
+        // fetch all branches (configured refspecs) and tags
         let mut fetch_opts = git2::FetchOptions::new();
         fetch_opts.download_tags(git2::AutotagOption::All);
-        remote.fetch(&[current_branch_name.as_str()], Some(&mut fetch_opts), None)?;
-        let fetch_head = repo.find_reference("FETCH_HEAD")?;
-        let fetch_commit = repo.reference_to_annotated_commit(&fetch_head)?;
-
-        // pull changes
-        let refname = format!("refs/heads/{current_branch_name}");
-        repo.reference(
-            &refname,
-            fetch_commit.id(),
-            true,
-            &format!("Setting {} to {}", current_branch_name, fetch_commit.id()),
-        )?;
-        repo.set_head(&refname)?;
+        fetch_opts.remote_callbacks(self.transfer.callbacks());
+        remote.fetch::<&str>(&[], Some(&mut fetch_opts), None)?;
+        if self.verbosity >= 2 {
+            let stats = remote.stats();
+            eprintln!(
+                "ℹ️  Fetched {} objects ({} bytes) from {}",
+                stats.received_objects(),
+                stats.received_bytes(),
+                remote.url().unwrap_or("origin")
+            );
+        }
+
+        let reference = match reference {
+            Some(reference) => reference,
+            None => match self.head_state()? {
+                HeadState::Branch(branch) => branch,
+                HeadState::Detached(_) => return Ok(()),
+            },
+        };
+        self.checkout_reference(&reference)
+    }
+
+    /// Force checks out `reference`. A remote branch (`origin/<reference>`) is checked out as the
+    /// local branch of the same name, moved to the fetched commit; anything else (a tag, a commit
+    /// hash or any other revspec) is resolved with `git rev-parse` and checked out as a detached HEAD.
+    pub fn checkout_reference(&self, reference: &str) -> Result<()> {
+        let repo = self.repository()?;
+        if let Ok(remote_branch) = repo.find_branch(&format!("origin/{reference}"), BranchType::Remote) {
+            let commit = remote_branch.get().peel_to_commit()?;
+            let refname = format!("refs/heads/{reference}");
+            repo.reference(
+                &refname,
+                commit.id(),
+                true,
+                &format!("Setting {} to {}", reference, commit.id()),
+            )?;
+            repo.set_head(&refname)?;
+        } else {
+            let commit = repo.revparse_single(reference)?.peel_to_commit()?;
+            repo.set_head_detached(commit.id())?;
+        }
         repo.checkout_head(Some(
             git2::build::CheckoutBuilder::default()
                 .allow_conflicts(false)
@@ -104,22 +264,115 @@ impl GitRepository {
         Ok(self.repository.as_ref().unwrap())
     }
 
-    /// Returns current branch name.
-    pub fn current_branch_name(&self) -> Result<String> {
+    /// Returns the current branch name, or the commit HEAD is detached at.
+    pub fn head_state(&self) -> Result<HeadState> {
         let repo = self.repository()?;
         let head = repo.head()?;
         if head.is_branch() {
-            if let Ok(name) = head.name() {
-                Ok(name.to_string().replace("refs/heads/", ""))
-            } else {
-                Err(Error::InvalidBranchName)
-            }
+            let name = head.shorthand().map_err(|_| Error::InvalidBranchName)?;
+            Ok(HeadState::Branch(name.to_string()))
         } else {
-            Err(Error::RefIsNotBranch)
+            Ok(HeadState::Detached(head.peel_to_commit()?.id().to_string()))
         }
     }
 
+    /// Returns the commit hash HEAD points to.
+    pub fn head_commit_hash(&self) -> Result<String> {
+        let repo = self.repository()?;
+        let commit = repo.head()?.peel_to_commit()?;
+        Ok(commit.id().to_string())
+    }
+
+    /// Whether the working tree has uncommitted changes, including untracked (but not ignored) files.
+    pub fn is_dirty(&self) -> Result<bool> {
+        let repo = self.repository()?;
+        let mut options = git2::StatusOptions::new();
+        options.include_untracked(true).include_ignored(false);
+        Ok(!repo.statuses(Some(&mut options))?.is_empty())
+    }
+
+    /// Returns the hash of the tree HEAD points to. Unlike the commit hash it only depends on the
+    /// checked out content, so it stays the same across rebases or force-pushes that keep the files.
+    pub fn tree_hash(&self) -> Result<String> {
+        let repo = self.repository()?;
+        let tree = repo.head()?.peel_to_tree()?;
+        Ok(tree.id().to_string())
+    }
+
+    /// Fails unless HEAD's tree hash starts with `expected` (ignoring case), which must be at
+    /// least [`MIN_TREE_HASH_PREFIX_LEN`] hex digits.
+    pub fn verify_tree(&self, expected: &str) -> Result<()> {
+        let actual = self.tree_hash()?;
+        let valid = expected.len() >= MIN_TREE_HASH_PREFIX_LEN && expected.chars().all(|c| c.is_ascii_hexdigit());
+        if !valid || !actual.starts_with(&expected.to_ascii_lowercase()) {
+            return Err(Error::TreeMismatch {
+                expected: expected.to_string(),
+                actual,
+            });
+        }
+        Ok(())
+    }
+
     pub fn local_folder(&self) -> &PathBuf {
         &self.local_folder
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn commit_empty_tree(repo: &Repository) -> git2::Oid {
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        let tree_id = repo.index().unwrap().write_tree().unwrap();
+        let tree = repo.find_tree(tree_id).unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "initial", &tree, &[])
+            .unwrap()
+    }
+
+    #[test]
+    fn head_state_reports_branch_and_detached_head() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut repo = GitRepository::new(temp_dir.path().to_path_buf());
+        repo.init().unwrap();
+        let commit = commit_empty_tree(repo.repository().unwrap());
+        let branch = match repo.head_state().unwrap() {
+            HeadState::Branch(name) => name,
+            state => panic!("expected a branch, got {state:?}"),
+        };
+
+        repo.checkout_reference(&commit.to_string()[..10]).unwrap();
+        let state = repo.head_state().unwrap();
+        assert_eq!(state, HeadState::Detached(commit.to_string()));
+        assert!(state.is_detached_at(&commit.to_string()[..7]));
+        assert!(!state.is_detached_at(&branch));
+    }
+
+    #[test]
+    fn is_dirty_detects_untracked_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut repo = GitRepository::new(temp_dir.path().to_path_buf());
+        repo.init().unwrap();
+        commit_empty_tree(repo.repository().unwrap());
+        assert!(!repo.is_dirty().unwrap());
+
+        std::fs::write(temp_dir.path().join("lib.rs"), "fn a() {}").unwrap();
+        assert!(repo.is_dirty().unwrap());
+    }
+
+    #[test]
+    fn verify_tree_compares_head_tree_hash() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut repo = GitRepository::new(temp_dir.path().to_path_buf());
+        repo.init().unwrap();
+        commit_empty_tree(repo.repository().unwrap());
+
+        let tree = repo.tree_hash().unwrap();
+        assert!(repo.verify_tree(&tree).is_ok());
+        assert!(repo.verify_tree(&tree[..12].to_uppercase()).is_ok());
+        assert!(matches!(repo.verify_tree("0000000"), Err(Error::TreeMismatch { .. })));
+        assert!(repo.verify_tree(&tree[..1]).is_err());
+        assert!(repo.verify_tree("").is_err());
+    }
+}
@@ -1,21 +1,47 @@
 // Copyright 2024 The Tari Project
 // SPDX-License-Identifier: BSD-3-Clause
 
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches};
 use std::process::exit;
+use std::time::Duration;
+use tari_ootle_publish_lib::CancellationToken;
 
+use crate::cli::color;
 use crate::cli::command::Cli;
+use crate::cli::error_report::render_error;
+use crate::cli::exit_code::ExitCode;
 
 mod cli;
 mod git;
 mod project;
 mod templates;
 
+/// How long a command gets to wind down after Ctrl-C before we exit anyway.
+const CANCEL_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
 #[tokio::main]
 async fn main() {
-    if let Err(error) = Cli::parse().handle_command().await {
-        println!("❌ {error:?}");
-        exit(1);
+    let cancel = CancellationToken::new();
+    let color = color::choice_from_args(std::env::args_os());
+    let matches = Cli::command().color(color).get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let command = cli.handle_command(cancel.clone());
+    tokio::pin!(command);
+
+    let result = tokio::select! {
+        result = &mut command => result,
+        _ = tokio::signal::ctrl_c() => {
+            cancel.cancel();
+            match tokio::time::timeout(CANCEL_GRACE_PERIOD, command).await {
+                Ok(result) => result,
+                Err(_) => Err(anyhow::anyhow!("Interrupted")),
+            }
+        },
+    };
+
+    if let Err(error) = result {
+        println!("❌ {}", render_error(&error));
+        exit(ExitCode::from_error(&error).code());
     }
 
     exit(0);
@@ -3,6 +3,7 @@
 
 use std::collections::HashMap;
 
+use anyhow::Context;
 use ootle_network::Network;
 use serde::{Deserialize, Serialize};
 use tari_engine_types::published_template::PublishedTemplateAddress;
@@ -19,17 +20,67 @@ pub const DEFAULT_METADATA_SERVER_URL_LOCALNET: &str = "http://localhost:3000";
 pub struct ProjectConfig {
     default_network: Option<Network>,
     default_account: Option<String>,
+    /// The network this project is meant to be published to. Publishing anywhere else asks for
+    /// confirmation first.
+    #[serde(default)]
+    expected_network: Option<Network>,
+    /// Account aliases (alias -> account name or component address), usable wherever an account
+    /// name is accepted.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    accounts: HashMap<String, String>,
     #[serde(default)]
     networks: HashMap<Network, ProjectNetworkSettings>,
+    /// The template this project was generated from, recorded by `tari create`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    template: Option<TemplateSource>,
+    /// Build defaults for this project. Command-line flags take precedence.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    build: Option<BuildSettings>,
+}
+
+/// Project build defaults (`[build]`), used by the commands that build the template.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BuildSettings {
+    /// Cargo features to enable, unless `--features` is given.
+    #[serde(default)]
+    pub features: Vec<String>,
+    /// Do not enable the crate's `default` feature, unless `--default-features` is given.
+    #[serde(default)]
+    pub no_default_features: bool,
+    /// Target triple, unless `--target` is given.
+    pub target: Option<String>,
+    /// Apply the size-optimizing release profile overrides. Defaults to `true`; `--no-cargo-opts`
+    /// turns them off regardless.
+    pub cargo_opts: Option<bool>,
+    /// Run wasm-opt over binaries built with `tari build`, as `--optimize` does.
+    #[serde(default)]
+    pub wasm_opt: bool,
+}
+
+/// Where a project was generated from.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TemplateSource {
+    /// Template id, as passed to `create --template`.
+    pub id: String,
+    /// Git URL of the template repository.
+    pub repository: String,
+    /// Commit of the template repository, when known.
+    pub commit: Option<String>,
 }
 
 /// Per-network project settings.
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct ProjectNetworkSettings {
+    /// Account used on this network, overriding the top-level `default-account`.
+    pub default_account: Option<String>,
     pub wallet_daemon_url: Option<Url>,
     pub metadata_server_url: Option<Url>,
     pub template_address: Option<PublishedTemplateAddress>,
+    /// Hash of the binary last published to `template_address`.
+    pub template_hash: Option<String>,
 }
 
 impl ProjectConfig {
@@ -37,6 +88,23 @@ impl ProjectConfig {
         self.default_network
     }
 
+    pub fn expected_network(&self) -> Option<Network> {
+        self.expected_network
+    }
+
+    pub fn template_source(&self) -> Option<&TemplateSource> {
+        self.template.as_ref()
+    }
+
+    pub fn build_settings(&self) -> Option<&BuildSettings> {
+        self.build.as_ref()
+    }
+
+    /// Whether a `[networks.<network>]` section is configured.
+    pub fn has_network(&self, network: Network) -> bool {
+        self.networks.contains_key(&network)
+    }
+
     pub fn wallet_daemon_url(&self, network: Network) -> Option<&Url> {
         self.networks.get(&network).and_then(|n| n.wallet_daemon_url.as_ref())
     }
@@ -49,10 +117,38 @@ impl ProjectConfig {
         self.networks.get(&network).and_then(|n| n.template_address.as_ref())
     }
 
-    pub fn parsed_default_account(&self) -> anyhow::Result<Option<ComponentAddressOrName>> {
-        let acc = self.default_account.as_ref().map(|s| s.parse()).transpose()?;
+    pub fn template_hash(&self, network: Network) -> Option<&str> {
+        self.networks.get(&network).and_then(|n| n.template_hash.as_deref())
+    }
+
+    /// The default account for `network`: its `[networks.<network>]` `default-account`, falling
+    /// back to the top-level one.
+    pub fn parsed_default_account(&self, network: Network) -> anyhow::Result<Option<ComponentAddressOrName>> {
+        let acc = self
+            .networks
+            .get(&network)
+            .and_then(|n| n.default_account.as_ref())
+            .or(self.default_account.as_ref())
+            .map(|s| s.parse())
+            .transpose()?;
         Ok(acc)
     }
+
+    /// Looks `account` up in the `[accounts]` alias table. Returns `None` for component addresses
+    /// and for names that are not aliases.
+    pub fn account_alias(&self, account: &ComponentAddressOrName) -> anyhow::Result<Option<ComponentAddressOrName>> {
+        let ComponentAddressOrName::Name(name) = account else {
+            return Ok(None);
+        };
+        let Some(target) = self.accounts.get(name) else {
+            return Ok(None);
+        };
+        let resolved = target
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid account '{target}' for alias '{name}' in [accounts]"))?;
+        Ok(Some(resolved))
+    }
 }
 
 impl Default for ProjectConfig {
@@ -63,23 +159,31 @@ impl Default for ProjectConfig {
         networks.insert(
             Network::Esmeralda,
             ProjectNetworkSettings {
+                default_account: None,
                 wallet_daemon_url: wallet_url(),
                 metadata_server_url: metadata_url(DEFAULT_METADATA_SERVER_URL_ESMERALDA),
                 template_address: None,
+                template_hash: None,
             },
         );
         networks.insert(
             Network::LocalNet,
             ProjectNetworkSettings {
+                default_account: None,
                 wallet_daemon_url: wallet_url(),
                 metadata_server_url: metadata_url(DEFAULT_METADATA_SERVER_URL_LOCALNET),
                 template_address: None,
+                template_hash: None,
             },
         );
         Self {
             default_network: Some(Network::Esmeralda),
             default_account: None,
+            expected_network: None,
+            accounts: HashMap::new(),
             networks,
+            template: None,
+            build: None,
         }
     }
 }
@@ -124,5 +228,70 @@ wallet-daemon-url = "http://localhost:9999/json_rpc"
         );
         assert!(cfg.template_address(Network::Esmeralda).is_some());
         assert!(cfg.template_address(Network::LocalNet).is_none());
+        assert!(cfg.template_hash(Network::Esmeralda).is_none());
+        assert_eq!(cfg.expected_network(), None);
+    }
+
+    #[test]
+    fn account_aliases_resolve_through_the_table() {
+        let address = "component_0000000000000000000000000000000000000000000000000000000000000000";
+        let toml_str = format!(
+            r#"
+[accounts]
+treasury = "{address}"
+ops = "operations"
+"#
+        );
+        let cfg: ProjectConfig = toml::from_str(&toml_str).expect("parse");
+
+        let name = |s: &str| ComponentAddressOrName::Name(s.to_string());
+        let treasury = cfg.account_alias(&name("treasury")).unwrap().expect("alias");
+        assert!(matches!(treasury, ComponentAddressOrName::ComponentAddress(_)));
+        assert_eq!(treasury.to_string(), address);
+        assert!(matches!(
+            cfg.account_alias(&name("ops")).unwrap(),
+            Some(ComponentAddressOrName::Name(n)) if n == "operations"
+        ));
+        assert!(cfg.account_alias(&name("alice")).unwrap().is_none());
+        assert!(cfg.account_alias(&treasury).unwrap().is_none());
+    }
+
+    #[test]
+    fn per_network_default_account_overrides_top_level() {
+        let toml_str = r#"
+default-account = "alice"
+
+[networks.localnet]
+default-account = "dev"
+"#;
+        let cfg: ProjectConfig = toml::from_str(toml_str).expect("parse");
+        let account = |network| cfg.parsed_default_account(network).unwrap().map(|a| a.to_string());
+        assert_eq!(account(Network::LocalNet), Some("dev".to_string()));
+        assert_eq!(account(Network::Esmeralda), Some("alice".to_string()));
+    }
+
+    #[test]
+    fn build_section_parses() {
+        let toml_str = r#"
+[build]
+features = ["admin"]
+target = "wasm32v1-none"
+cargo-opts = false
+wasm-opt = true
+"#;
+        let cfg: ProjectConfig = toml::from_str(toml_str).expect("parse");
+        let build = cfg.build_settings().expect("build section");
+        assert_eq!(build.features, ["admin"]);
+        assert!(!build.no_default_features);
+        assert_eq!(build.target.as_deref(), Some("wasm32v1-none"));
+        assert_eq!(build.cargo_opts, Some(false));
+        assert!(build.wasm_opt);
+        assert!(ProjectConfig::default().build_settings().is_none());
+    }
+
+    #[test]
+    fn expected_network_parses() {
+        let cfg: ProjectConfig = toml::from_str("expected-network = \"localnet\"\n").expect("parse");
+        assert_eq!(cfg.expected_network(), Some(Network::LocalNet));
     }
 }
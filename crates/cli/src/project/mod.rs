@@ -3,5 +3,7 @@
 
 pub const CONFIG_FILE_NAME: &str = "tari.config.toml";
 mod config;
+mod publish_lock;
 
 pub use config::*;
+pub use publish_lock::*;
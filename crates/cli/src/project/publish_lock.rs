@@ -0,0 +1,126 @@
+// Copyright 2024 The Tari Project
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+pub const PUBLISH_LOCK_FILE_NAME: &str = "tari.publish.lock";
+
+/// Records what was published from a project, so a later publish can prove that it ships the
+/// same binary. Stored next to the template crate's `Cargo.toml`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PublishLock {
+    #[serde(default, rename = "template")]
+    templates: Vec<LockedTemplate>,
+}
+
+/// A single published template.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LockedTemplate {
+    /// Crate name of the template.
+    pub name: String,
+    /// Git commit the template was built from, if the project is in a git repository.
+    pub source_commit: Option<String>,
+    /// Hash of the published binary.
+    pub template_hash: String,
+}
+
+impl PublishLock {
+    pub fn path(project_dir: &Path) -> PathBuf {
+        project_dir.join(PUBLISH_LOCK_FILE_NAME)
+    }
+
+    /// Loads the lock file from `project_dir`. A missing file yields an empty lock.
+    pub async fn load(project_dir: &Path) -> anyhow::Result<Self> {
+        let path = Self::path(project_dir);
+        if !fs::try_exists(&path).await? {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("reading {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    pub async fn save(&self, project_dir: &Path) -> anyhow::Result<()> {
+        let path = Self::path(project_dir);
+        fs::write(&path, toml::to_string(self)?)
+            .await
+            .with_context(|| format!("writing {}", path.display()))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&LockedTemplate> {
+        self.templates.iter().find(|t| t.name == name)
+    }
+
+    /// Inserts `template`, replacing any existing entry with the same name.
+    pub fn upsert(&mut self, template: LockedTemplate) {
+        match self.templates.iter_mut().find(|t| t.name == template.name) {
+            Some(existing) => *existing = template,
+            None => self.templates.push(template),
+        }
+    }
+
+    /// Fails unless `template_hash` matches the locked hash for `name`.
+    pub fn verify(&self, name: &str, template_hash: &str) -> anyhow::Result<()> {
+        let locked = self
+            .get(name)
+            .ok_or_else(|| anyhow!("Template '{name}' is not recorded in {PUBLISH_LOCK_FILE_NAME}"))?;
+        if locked.template_hash != template_hash {
+            return Err(anyhow!(
+                "Built template hash {template_hash} does not match the locked hash {} in {PUBLISH_LOCK_FILE_NAME}{}. \
+                 Refusing to publish.",
+                locked.template_hash,
+                locked
+                    .source_commit
+                    .as_ref()
+                    .map(|commit| format!(" (built from commit {commit})"))
+                    .unwrap_or_default(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn locked(name: &str, hash: &str) -> LockedTemplate {
+        LockedTemplate {
+            name: name.to_string(),
+            source_commit: Some("abc123".to_string()),
+            template_hash: hash.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn lock_roundtrips_and_upsert_replaces() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut lock = PublishLock::load(temp_dir.path()).await.unwrap();
+        assert!(lock.get("counter").is_none());
+
+        lock.upsert(locked("counter", "aaaa"));
+        lock.upsert(locked("counter", "bbbb"));
+        lock.save(temp_dir.path()).await.unwrap();
+
+        let loaded = PublishLock::load(temp_dir.path()).await.unwrap();
+        assert_eq!(loaded.get("counter"), Some(&locked("counter", "bbbb")));
+    }
+
+    #[test]
+    fn verify_rejects_mismatched_or_missing_hash() {
+        let mut lock = PublishLock::default();
+        lock.upsert(locked("counter", "aaaa"));
+        assert!(lock.verify("counter", "aaaa").is_ok());
+        let err = lock.verify("counter", "bbbb").unwrap_err();
+        assert!(err.to_string().contains("abc123"), "got: {err}");
+        assert!(lock.verify("other", "aaaa").is_err());
+    }
+}
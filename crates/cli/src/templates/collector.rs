@@ -1,10 +1,12 @@
 // Copyright 2024 The Tari Project
 // SPDX-License-Identifier: BSD-3-Clause
 
+use std::collections::HashSet;
 use std::path::PathBuf;
 
 use convert_case::{Case, Casing};
 use thiserror::Error;
+use tokio::task::JoinSet;
 use tokio::{fs, io};
 
 use crate::templates::{Template, TemplateFile};
@@ -17,10 +19,19 @@ pub enum Error {
     IO(#[from] io::Error),
     #[error("Failed to deserialize TOML: {0}")]
     TomlDeserialize(#[from] toml::de::Error),
+    #[error("Invalid default for variable `{name}` in {path}: {reason}")]
+    InvalidVariable {
+        path: PathBuf,
+        name: String,
+        reason: String,
+    },
 }
 
 pub type CollectorResult<T> = Result<T, Error>;
 
+/// How many `template.toml` files are read and parsed at once.
+const MAX_CONCURRENT_READS: usize = 16;
+
 pub struct Collector {
     local_folder: PathBuf,
 }
@@ -30,63 +41,99 @@ impl Collector {
         Self { local_folder }
     }
 
-    /// Collect and return all templates from [`Collector::local_folder`].
+    /// Collect and return all templates from [`Collector::local_folder`], ordered by the path of
+    /// their descriptor.
     pub async fn collect(&self) -> CollectorResult<Vec<Template>> {
-        let mut result = vec![];
-        Self::collect_templates(&self.local_folder, &mut result).await?;
+        let mut descriptors = vec![];
+        Self::find_descriptors(&self.local_folder, &mut HashSet::new(), &mut descriptors).await?;
+        descriptors.sort();
 
-        Ok(result)
+        let mut loading = JoinSet::new();
+        let mut loaded = Vec::with_capacity(descriptors.len());
+        for (index, descriptor) in descriptors.into_iter().enumerate() {
+            if loading.len() >= MAX_CONCURRENT_READS
+                && let Some(joined) = loading.join_next().await
+            {
+                loaded.push(joined.map_err(io::Error::other)??);
+            }
+            loading.spawn(async move { Self::load_template(descriptor).await.map(|template| (index, template)) });
+        }
+        while let Some(joined) = loading.join_next().await {
+            loaded.push(joined.map_err(io::Error::other)??);
+        }
+
+        loaded.sort_by_key(|(index, _)| *index);
+        Ok(loaded.into_iter().map(|(_, template)| template).collect())
     }
 
-    /// Collecting recursively all the templates from a starting folder `dir`.
+    /// Collecting recursively the paths of all template descriptors from a starting folder `dir`.
     /// All the results will be pushed into `result`.
-    async fn collect_templates(dir: &PathBuf, result: &mut Vec<Template>) -> CollectorResult<()> {
-        if dir.is_dir() {
+    /// Directories already in `visited` (by canonical path) are skipped, so symlinks pointing back
+    /// at an ancestor don't recurse forever.
+    async fn find_descriptors(
+        dir: &PathBuf,
+        visited: &mut HashSet<PathBuf>,
+        result: &mut Vec<PathBuf>,
+    ) -> CollectorResult<()> {
+        if dir.is_dir() && visited.insert(fs::canonicalize(dir).await?) {
             let mut entries_stream = fs::read_dir(dir).await?;
             while let Some(entry) = entries_stream.next_entry().await? {
                 if entry.path().is_dir() {
-                    Box::pin(Self::collect_templates(&entry.path(), result)).await?;
+                    Box::pin(Self::find_descriptors(&entry.path(), visited, result)).await?;
                     continue;
                 }
 
                 if let Some(file_name) = entry.file_name().to_str()
                     && file_name == TEMPLATE_DESCRIPTOR_FILE_NAME
                 {
-                    let toml_content = fs::read_to_string(&entry.path()).await?;
-                    let template_file: TemplateFile =
-                        toml::from_str(toml_content.as_str()).map_err(Error::TomlDeserialize)?;
-
-                    let template_id = entry
-                        .path()
-                        .parent()
-                        .filter(|dir| dir.is_dir())
-                        .and_then(|dir| dir.file_name())
-                        .and_then(|dir_name| dir_name.to_str().map(|dir_name| dir_name.to_case(Case::Snake)))
-                        .unwrap_or_else(|| template_file.name.to_case(Case::Snake));
-
-                    let path = entry
-                        .path()
-                        .parent()
-                        .map(|curr_path| curr_path.to_path_buf())
-                        .unwrap_or_else(|| entry.path());
-
-                    result.push(Template::new(
-                        path,
-                        template_id,
-                        template_file.name,
-                        template_file.description,
-                        template_file.extra.unwrap_or_default(),
-                    ));
+                    result.push(entry.path());
                 }
             }
         }
         Ok(())
     }
+
+    /// Reads and parses the template descriptor at `descriptor`.
+    async fn load_template(descriptor: PathBuf) -> CollectorResult<Template> {
+        let toml_content = fs::read_to_string(&descriptor).await?;
+        let template_file: TemplateFile = toml::from_str(toml_content.as_str()).map_err(Error::TomlDeserialize)?;
+        for (name, variable) in &template_file.variables {
+            variable.default_value().map_err(|reason| Error::InvalidVariable {
+                path: descriptor.clone(),
+                name: name.clone(),
+                reason,
+            })?;
+        }
+
+        let template_id = descriptor
+            .parent()
+            .filter(|dir| dir.is_dir())
+            .and_then(|dir| dir.file_name())
+            .and_then(|dir_name| dir_name.to_str().map(|dir_name| dir_name.to_case(Case::Snake)))
+            .unwrap_or_else(|| template_file.name.to_case(Case::Snake));
+
+        let path = descriptor
+            .parent()
+            .map(|curr_path| curr_path.to_path_buf())
+            .unwrap_or_else(|| descriptor.clone());
+
+        let source = template_file.remote_source();
+        Ok(Template::new(
+            path,
+            template_id,
+            template_file.name,
+            template_file.description,
+            template_file.extra.unwrap_or_default(),
+            template_file.variables,
+        )
+        .with_source(source))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::templates::{RemoteSource, TemplateVariableType};
     use std::collections::HashMap;
     use std::path::Path;
     use tempfile::TempDir;
@@ -183,4 +230,132 @@ mod tests {
             }
         }
     }
+
+    #[tokio::test]
+    async fn test_collect_variables() {
+        let temp_dir = TempDir::new().unwrap();
+        let template_dir = temp_dir.path().join("typed");
+        fs::create_dir_all(&template_dir).await.unwrap();
+        fs::write(
+            template_dir.join(TEMPLATE_DESCRIPTOR_FILE_NAME),
+            r#"
+            name = "typed"
+            description = "Template with variables"
+
+            [variables.initial_supply]
+            type = "int"
+            default = 100
+            required = true
+
+            [variables.symbol]
+            description = "Token symbol"
+            "#,
+        )
+        .await
+        .unwrap();
+
+        let result = Collector::new(temp_dir.path().to_path_buf()).collect().await.unwrap();
+        assert_eq!(result.len(), 1);
+        let variables = result[0].variables();
+        assert_eq!(variables["initial_supply"].kind, TemplateVariableType::Int);
+        assert!(variables["initial_supply"].required);
+        assert_eq!(variables["symbol"].kind, TemplateVariableType::String);
+    }
+
+    #[tokio::test]
+    async fn test_collect_remote_source() {
+        let temp_dir = TempDir::new().unwrap();
+        let template_dir = temp_dir.path().join("remote");
+        fs::create_dir_all(&template_dir).await.unwrap();
+        fs::write(
+            template_dir.join(TEMPLATE_DESCRIPTOR_FILE_NAME),
+            r#"
+            name = "remote"
+            description = "Scaffold hosted elsewhere"
+            source_url = "https://github.com/example/scaffolds"
+            source_subfolder = "nft"
+            "#,
+        )
+        .await
+        .unwrap();
+
+        let result = Collector::new(temp_dir.path().to_path_buf()).collect().await.unwrap();
+        assert_eq!(
+            result[0].source(),
+            Some(&RemoteSource {
+                git: "https://github.com/example/scaffolds".to_string(),
+                subfolder: Some("nft".to_string()),
+                branch: None,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_collect_rejects_mistyped_variable_default() {
+        let temp_dir = TempDir::new().unwrap();
+        let template_dir = temp_dir.path().join("broken");
+        fs::create_dir_all(&template_dir).await.unwrap();
+        fs::write(
+            template_dir.join(TEMPLATE_DESCRIPTOR_FILE_NAME),
+            r#"
+            name = "broken"
+            description = "Template with a bad default"
+
+            [variables.enabled]
+            type = "bool"
+            default = 3
+            "#,
+        )
+        .await
+        .unwrap();
+
+        let result = Collector::new(temp_dir.path().to_path_buf()).collect().await;
+        assert!(matches!(result, Err(Error::InvalidVariable { .. })));
+    }
+
+    #[tokio::test]
+    async fn collects_many_templates_in_path_order() {
+        let temp_dir = TempDir::new().unwrap();
+        for group in 0..4 {
+            for i in 0..(MAX_CONCURRENT_READS * 2) {
+                let name = format!("template_{i:02}");
+                let template = TemplateToGenerate::new(&name, "description", None);
+                generate_template(&temp_dir.path().join(format!("group_{group}")), &template).await;
+            }
+        }
+
+        let result = Collector::new(temp_dir.path().to_path_buf()).collect().await.unwrap();
+        assert_eq!(result.len(), 4 * MAX_CONCURRENT_READS * 2);
+        let paths = result
+            .iter()
+            .map(|template| template.path().clone())
+            .collect::<Vec<_>>();
+        let mut sorted = paths.clone();
+        sorted.sort();
+        assert_eq!(paths, sorted);
+        assert_eq!(result[0].id(), "template_00");
+        assert!(result[0].path().ends_with("group_0/template_00"));
+        assert!(
+            result
+                .last()
+                .unwrap()
+                .path()
+                .ends_with(format!("group_3/template_{:02}", MAX_CONCURRENT_READS * 2 - 1))
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn symlink_cycles_are_not_followed_twice() {
+        let temp_dir = TempDir::new().unwrap();
+        let template_dir = generate_template(
+            &temp_dir.path().join("nested"),
+            &TemplateToGenerate::new("template1", "description1", None),
+        )
+        .await;
+        std::os::unix::fs::symlink(temp_dir.path(), template_dir.join("loop")).unwrap();
+
+        let result = Collector::new(temp_dir.path().to_path_buf()).collect().await.unwrap();
+        assert_eq!(result.len(), 1);
+    }
 }
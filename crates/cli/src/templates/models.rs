@@ -1,10 +1,15 @@
 // Copyright 2024 The Tari Project
 // SPDX-License-Identifier: BSD-3-Clause
 
-use std::{collections::HashMap, fmt::Display, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    path::PathBuf,
+};
 
 use serde::{Deserialize, Serialize};
-use termimad::{MadSkin, crossterm::style::Color};
+
+use crate::cli::color;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Template {
@@ -13,12 +18,13 @@ pub struct Template {
     name: String,
     description: String,
     extra: HashMap<String, String>,
+    variables: BTreeMap<String, TemplateVariable>,
+    source: Option<RemoteSource>,
 }
 
 impl Display for Template {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut skin = MadSkin::default();
-        skin.bold.set_fg(Color::Magenta);
+        let skin = color::skin();
         let formatted_name = skin.inline(format!("**{}**", self.name).as_str()).to_string();
         let formatted_description = skin.inline(self.description.as_str()).to_string();
         write!(f, "{formatted_name} - {formatted_description}")
@@ -26,38 +32,79 @@ impl Display for Template {
 }
 
 impl Template {
-    pub fn new(path: PathBuf, id: String, name: String, description: String, extra: HashMap<String, String>) -> Self {
+    pub fn new(
+        path: PathBuf,
+        id: String,
+        name: String,
+        description: String,
+        extra: HashMap<String, String>,
+        variables: BTreeMap<String, TemplateVariable>,
+    ) -> Self {
         Self {
             path,
             id,
             name,
             description,
             extra,
+            variables,
+            source: None,
         }
     }
 
-    pub fn path(&self) -> &PathBuf {
-        &self.path
+    /// Sets where the scaffold is fetched from when it is not next to `template.toml`.
+    pub fn with_source(mut self, source: Option<RemoteSource>) -> Self {
+        self.source = source;
+        self
     }
 
-    pub fn name(&self) -> &str {
-        &self.name
+    pub fn path(&self) -> &PathBuf {
+        &self.path
     }
 
     pub fn id(&self) -> &str {
         &self.id
     }
 
+    /// Finds a template by its id (the snake-cased directory name), ignoring ASCII case.
+    /// If several templates share the same id, the last one collected wins.
+    pub fn find_by_id<'a>(templates: &'a [Template], id: &str) -> Option<&'a Template> {
+        templates.iter().rfind(|t| t.id.eq_ignore_ascii_case(id))
+    }
+
     #[allow(dead_code)]
     pub fn extra(&self) -> &HashMap<String, String> {
         &self.extra
     }
 
+    /// The remote scaffold to generate from instead of [`Self::path`], if any.
+    pub fn source(&self) -> Option<&RemoteSource> {
+        self.source.as_ref()
+    }
+
+    /// Typed variables declared in the `[variables]` section of `template.toml`, ordered by name.
+    pub fn variables(&self) -> &BTreeMap<String, TemplateVariable> {
+        &self.variables
+    }
+
+    // Only currently used in tests
+    #[cfg(test)]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     // Only currently used in tests
     #[cfg(test)]
     pub fn description(&self) -> &str {
         &self.description
     }
+
+    /// Returns true if `query` appears in the id, name or description, ignoring case.
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        [&self.id, &self.name, &self.description]
+            .iter()
+            .any(|field| field.to_lowercase().contains(&query))
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -65,4 +112,190 @@ pub struct TemplateFile {
     pub name: String,
     pub description: String,
     pub extra: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub variables: BTreeMap<String, TemplateVariable>,
+    /// Git repository holding the actual cargo-generate scaffold, for descriptor-only templates.
+    pub source_url: Option<String>,
+    /// Directory of the scaffold inside `source_url`.
+    pub source_subfolder: Option<String>,
+    /// Branch of `source_url` to use (default: its default branch).
+    pub source_branch: Option<String>,
+}
+
+impl TemplateFile {
+    pub fn remote_source(&self) -> Option<RemoteSource> {
+        self.source_url.as_ref().map(|git| RemoteSource {
+            git: git.clone(),
+            subfolder: self.source_subfolder.clone(),
+            branch: self.source_branch.clone(),
+        })
+    }
+}
+
+/// A cargo-generate scaffold hosted in a git repository, referenced from `template.toml`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RemoteSource {
+    pub git: String,
+    pub subfolder: Option<String>,
+    pub branch: Option<String>,
+}
+
+/// Type of a template variable declared in `template.toml`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateVariableType {
+    #[default]
+    String,
+    Bool,
+    Int,
+}
+
+impl Display for TemplateVariableType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::String => write!(f, "string"),
+            Self::Bool => write!(f, "bool"),
+            Self::Int => write!(f, "int"),
+        }
+    }
+}
+
+/// A variable declared in the `[variables]` section of `template.toml`.
+/// Resolved values are passed to cargo-generate as defines.
+///
+/// ```toml
+/// [variables.initial_supply]
+/// type = "int"
+/// description = "Initial token supply"
+/// default = 1000000
+/// required = true
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateVariable {
+    #[serde(rename = "type", default)]
+    pub kind: TemplateVariableType,
+    pub description: Option<String>,
+    pub default: Option<toml::Value>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+impl TemplateVariable {
+    /// Validates `raw` against the declared type and returns its normalized form.
+    pub fn parse_value(&self, raw: &str) -> Result<String, String> {
+        let raw = raw.trim();
+        match self.kind {
+            TemplateVariableType::String => Ok(raw.to_string()),
+            TemplateVariableType::Bool => raw
+                .parse::<bool>()
+                .map(|value| value.to_string())
+                .map_err(|_| format!("expected `true` or `false`, got `{raw}`")),
+            TemplateVariableType::Int => raw
+                .parse::<i64>()
+                .map(|value| value.to_string())
+                .map_err(|_| format!("expected an integer, got `{raw}`")),
+        }
+    }
+
+    /// Returns the declared default (if any), validated against the declared type.
+    pub fn default_value(&self) -> Result<Option<String>, String> {
+        let Some(default) = &self.default else {
+            return Ok(None);
+        };
+        let raw = match default {
+            toml::Value::String(value) => value.clone(),
+            other => other.to_string(),
+        };
+        self.parse_value(&raw).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(dir: &str, id: &str, name: &str) -> Template {
+        Template::new(
+            PathBuf::from(dir),
+            id.to_string(),
+            name.to_string(),
+            String::new(),
+            HashMap::new(),
+            BTreeMap::new(),
+        )
+    }
+
+    #[test]
+    fn find_by_id_ignores_case() {
+        let templates = vec![
+            template("a", "fungible", "Fungible"),
+            template("b", "meme_coin", "Meme Coin"),
+        ];
+        let found = Template::find_by_id(&templates, "MEME_COIN").expect("template should be found");
+        assert_eq!(found.id(), "meme_coin");
+    }
+
+    #[test]
+    fn find_by_id_does_not_match_on_name() {
+        let templates = vec![template("a", "meme_coin", "Meme Coin")];
+        assert!(Template::find_by_id(&templates, "Meme Coin").is_none());
+    }
+
+    #[test]
+    fn find_by_id_prefers_last_duplicate() {
+        let templates = vec![
+            template("first", "counter", "Counter"),
+            template("second", "counter", "Counter"),
+        ];
+        let found = Template::find_by_id(&templates, "counter").expect("template should be found");
+        assert_eq!(found.path(), &PathBuf::from("second"));
+    }
+
+    #[test]
+    fn matches_searches_id_name_and_description_ignoring_case() {
+        let mut meme = template("a", "meme_coin", "Meme Coin");
+        meme.description = "A fungible token with a fixed supply".to_string();
+        assert!(meme.matches("MEME"));
+        assert!(meme.matches("coin"));
+        assert!(meme.matches("Fixed Supply"));
+        assert!(!meme.matches("nft"));
+    }
+
+    #[test]
+    fn variable_values_are_validated_against_type() {
+        let int_var = TemplateVariable {
+            kind: TemplateVariableType::Int,
+            ..TemplateVariable::default()
+        };
+        assert_eq!(int_var.parse_value(" 42 ").unwrap(), "42");
+        assert!(int_var.parse_value("forty-two").is_err());
+
+        let bool_var = TemplateVariable {
+            kind: TemplateVariableType::Bool,
+            ..TemplateVariable::default()
+        };
+        assert_eq!(bool_var.parse_value("true").unwrap(), "true");
+        assert!(bool_var.parse_value("yes").is_err());
+
+        assert_eq!(TemplateVariable::default().parse_value("anything").unwrap(), "anything");
+    }
+
+    #[test]
+    fn variable_defaults_must_match_type() {
+        let vars: BTreeMap<String, TemplateVariable> = toml::from_str(
+            r#"
+            [supply]
+            type = "int"
+            default = 1000
+
+            [burnable]
+            type = "bool"
+            default = "maybe"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(vars["supply"].default_value().unwrap().as_deref(), Some("1000"));
+        assert!(vars["burnable"].default_value().is_err());
+    }
 }
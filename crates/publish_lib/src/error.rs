@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: BSD-3-Clause
 
 use std::io;
+use std::path::PathBuf;
 use tari_engine::template::TemplateLoaderError;
 use tari_ootle_walletd_client::error::WalletDaemonClientError;
 use tari_template_lib_types::Amount;
@@ -19,14 +20,20 @@ pub enum Error {
     InvalidTemplate(#[from] TemplateLoaderError),
     #[error("Invalid template: {0}")]
     IO(#[from] io::Error),
+    #[error("Failed to read template binary {}: {source}", .path.display())]
+    ReadTemplateBinary { path: PathBuf, source: io::Error },
     #[error("Invalid hash error: {0}")]
     InvalidHash(#[from] HashParseError),
-    #[error("Insufficient balance in Tari L2 wallet! Current balance: {current}, Estimated Fee: {fee}")]
+    #[error("Insufficient balance in Tari L2 wallet! Current balance: {current}, Fee: {fee}")]
     InsufficientBalance { current: Amount, fee: u64 },
     #[error("Waiting for transaction timed out! Transaction ID: {0}")]
     WaitForTransactionTimeout(String),
+    #[error("Waiting for the transaction was cancelled! Transaction {0} may still finalize.")]
+    Cancelled(String),
     #[error("Invalid transaction: {0}!\n{1}")]
     InvalidTransaction(String, String),
+    #[error("Transaction {0} was rejected for a reason that may be temporary!\n{1}")]
+    TransientRejection(String, String),
     #[error("Missing transaction result: {0}")]
     MissingTransactionResult(String),
     #[error("Missing published template in substates!")]
@@ -44,4 +51,10 @@ impl Error {
     pub fn is_unauthorized(&self) -> bool {
         matches!(self, Self::WalletDaemonClient(e) if e.is_unauthorized())
     }
+
+    /// Returns `true` if the transaction was rejected for a reason that may not recur, so
+    /// publishing again can succeed.
+    pub fn is_transient_rejection(&self) -> bool {
+        matches!(self, Self::TransientRejection(..))
+    }
 }
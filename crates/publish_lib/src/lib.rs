@@ -6,11 +6,16 @@
 mod config;
 mod error;
 pub mod publisher;
+mod wallet_client;
 #[cfg(feature = "wasm-opt")]
-pub(crate) mod wasm_opt;
+pub mod wasm_opt;
 
 pub use config::*;
 pub use error::Error as PublisherError;
+pub use wallet_client::{WalletClient, WalletDaemonConnector};
 
 // Re-export
 pub use tari_ootle_walletd_client as walletd_client;
+/// Used in [`WalletClient`] signatures.
+pub use tari_template_lib_types::Amount;
+pub use tokio_util::sync::CancellationToken;
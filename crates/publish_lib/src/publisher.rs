@@ -2,38 +2,50 @@
 // SPDX-License-Identifier: BSD-3-Clause
 
 use crate::error::Error;
+use crate::wallet_client::{WalletClient, WalletDaemonConnector};
 use crate::{NetworkConfig, PublisherError};
 use serde::Serialize;
 use std::borrow::Cow;
-use std::path::PathBuf;
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tari_engine::template::LoadedTemplate;
 use tari_engine::wasm::WasmModule;
-use tari_engine_types::commit_result::TransactionResult;
+use tari_engine_types::commit_result::{RejectReason, TransactionResult};
 use tari_engine_types::hashing::template_hasher32;
 use tari_engine_types::substate::SubstateId;
-use tari_ootle_common_types::optional::Optional;
 use tari_ootle_template_metadata::MetadataHash;
 use tari_ootle_template_metadata::TemplateMetadata;
+use tari_ootle_wallet_sdk::models::TransactionStatus;
 use tari_ootle_walletd_client::types::{
-    AccountsGetBalancesRequest, EncodedJwtString, PublishTemplateMetadata, PublishTemplateRequest,
-    SignTemplateMetadataRequest, SignTemplateMetadataResponse, TransactionWaitResultRequest, WalletGetInfoResponse,
+    PublishTemplateMetadata, PublishTemplateRequest, SignTemplateMetadataRequest, SignTemplateMetadataResponse,
+    TransactionWaitResultRequest, WalletGetInfoResponse,
 };
 use tari_ootle_walletd_client::{ComponentAddressOrName, WalletDaemonClient};
 use tari_template_lib_types::Hash32;
-use tari_template_lib_types::constants::TARI_TOKEN;
 use tari_template_lib_types::{Amount, TemplateAddress};
 use tokio::fs;
+use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
 /// Tari template publisher.
 /// You can use this struct to easily publish a Tari template project to the target network.
 /// Note: This is the entry point to use this library crate.
-pub struct TemplatePublisher {
-    network: NetworkConfig,
+///
+/// By default it talks to a wallet daemon ([`TemplatePublisher::new`]); use
+/// [`TemplatePublisher::with_client`] to supply any other [`WalletClient`] (e.g. a mock).
+pub struct TemplatePublisher<C = WalletDaemonConnector> {
+    client: C,
+    cancellation: CancellationToken,
+    requests: Semaphore,
 }
 
+/// Default for [`TemplatePublisher::with_max_concurrent_requests`].
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 4;
+
 /// Provided template to publish.
 #[derive(Clone)]
 pub enum Template {
@@ -45,10 +57,53 @@ pub enum Template {
 
 impl TemplatePublisher {
     pub fn new(network: NetworkConfig) -> Self {
-        Self { network }
+        Self::with_client(WalletDaemonConnector::new(network))
+    }
+
+    /// Returns a new wallet daemon client for the configured network.
+    /// See [`WalletDaemonConnector::connect`].
+    pub async fn wallet_daemon_client(&self) -> Result<WalletDaemonClient> {
+        self.client.connect()
     }
+}
 
-    /// Publishes the given compiled template to the configured network ([`TemplatePublisher::network`]).
+impl<C: WalletClient> TemplatePublisher<C> {
+    pub fn with_client(client: C) -> Self {
+        Self {
+            client,
+            cancellation: CancellationToken::new(),
+            requests: Semaphore::new(DEFAULT_MAX_CONCURRENT_REQUESTS),
+        }
+    }
+
+    /// Caps how many wallet requests this publisher has in flight at once, so sharing it between
+    /// tasks can't overwhelm the daemon. Defaults to [`DEFAULT_MAX_CONCURRENT_REQUESTS`].
+    pub fn with_max_concurrent_requests(mut self, max: usize) -> Self {
+        self.requests = Semaphore::new(max.max(1));
+        self
+    }
+
+    /// Aborts waiting for a submitted transaction with [`Error::Cancelled`] once `token` is cancelled.
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    pub fn client(&self) -> &C {
+        &self.client
+    }
+
+    /// Runs a wallet request once a slot is free (see [`TemplatePublisher::with_max_concurrent_requests`]).
+    async fn limited<T>(&self, request: impl Future<Output = Result<T>>) -> Result<T> {
+        let _permit = self
+            .requests
+            .acquire()
+            .await
+            .expect("request semaphore is never closed");
+        request.await
+    }
+
+    /// Publishes the given compiled template using the configured [`WalletClient`].
     pub async fn publish(
         &self,
         account: &ComponentAddressOrName,
@@ -56,8 +111,8 @@ impl TemplatePublisher {
         max_fee: u64,
         metadata_hash: Option<MetadataHash>,
         wait_timeout: Option<Duration>,
-    ) -> Result<TemplateAddress> {
-        let publish_template_request = self
+    ) -> Result<PublishOutcome> {
+        let (publish_template_request, _) = self
             .create_publish_template_request(account, &template, max_fee, metadata_hash.clone())
             .await?;
         self.check_balance_for_publish(account, &template, metadata_hash)
@@ -69,26 +124,42 @@ impl TemplatePublisher {
         .await
     }
 
+    /// Same as [`TemplatePublisher::publish`], but only returns the new template's address.
+    pub async fn publish_address(
+        &self,
+        account: &ComponentAddressOrName,
+        template: Template,
+        max_fee: u64,
+        metadata_hash: Option<MetadataHash>,
+        wait_timeout: Option<Duration>,
+    ) -> Result<TemplateAddress> {
+        let outcome = self
+            .publish(account, template, max_fee, metadata_hash, wait_timeout)
+            .await?;
+        Ok(outcome.template_address)
+    }
+
     /// Get publish fee.
     /// It does not publish anything, just gets the calculated fee for the template.
     pub async fn publish_fee(&self, account: &ComponentAddressOrName, template: &Template) -> Result<u64> {
-        let mut request = self
+        let (mut request, _) = self
             .create_publish_template_request(account, template, 1_000_000, None)
             .await?;
         self.get_publish_fee(&mut request).await
     }
 
     pub async fn get_default_account(&self) -> Result<Option<ComponentAddressOrName>> {
-        let mut client = self.wallet_daemon_client().await?;
-        let account = client.accounts_get_default().await.optional()?;
-        let address = account.map(|a| *a.account.component_address());
-        Ok(address.map(Into::into))
+        self.limited(self.client.get_default_account()).await
+    }
+
+    /// Makes sure the wallet has a funded account called `name`, creating it with free test coins
+    /// if needed, and returns it. Only works on test networks.
+    pub async fn ensure_account(&self, name: &str) -> Result<ComponentAddressOrName> {
+        self.limited(self.client.create_free_test_account(name)).await
     }
 
     pub async fn get_wallet_info(&self) -> Result<WalletGetInfoResponse> {
-        let mut client = self.wallet_daemon_client().await?;
-        let info = client.get_wallet_info().await?;
-        Ok(info)
+        self.limited(self.client.get_wallet_info()).await
     }
 
     /// Signs template metadata using the wallet daemon's key management.
@@ -96,9 +167,7 @@ impl TemplatePublisher {
         &self,
         request: SignTemplateMetadataRequest,
     ) -> Result<SignTemplateMetadataResponse> {
-        let mut client = self.wallet_daemon_client().await?;
-        let response = client.sign_template_metadata(request).await?;
-        Ok(response)
+        self.limited(self.client.sign_template_metadata(request)).await
     }
 
     /// Higher-level helper: sign metadata for a template using the default account key.
@@ -110,8 +179,6 @@ impl TemplatePublisher {
         template_address: TemplateAddress,
         metadata: TemplateMetadata,
     ) -> Result<SignedMetadataPayload> {
-        use tari_ootle_walletd_client::types::SignTemplateMetadataRequest;
-
         let key_id =
             tari_ootle_wallet_sdk::models::KeyId::derived(tari_ootle_wallet_sdk::models::KeyBranch::Account, key_index);
 
@@ -134,9 +201,8 @@ impl TemplatePublisher {
 
     /// Get publish fee based on a [`PublishTemplateRequest`].
     async fn get_publish_fee(&self, request: &mut PublishTemplateRequest) -> Result<u64> {
-        let mut client = self.wallet_daemon_client().await?;
         request.dry_run = true;
-        let response = client.publish_template(request).await?;
+        let response = self.limited(self.client.publish_template(request)).await?;
         let fee = response.dry_run_fee.ok_or_else(|| {
             PublisherError::InvalidResponse("Wallet daemon returned an empty dry run fee".to_string())
         })?;
@@ -150,12 +216,12 @@ impl TemplatePublisher {
         template: &Template,
         metadata_hash: Option<MetadataHash>,
     ) -> Result<CheckBalanceResult> {
-        let mut request = self
+        let (mut request, template_hash) = self
             .create_publish_template_request(account, template, 1_000_000, metadata_hash)
             .await?;
         let bin_size = request.binary.len();
         let max_fee = self.get_publish_fee(&mut request).await?;
-        let wallet_balance = self.wallet_xtr_balance(account).await?;
+        let wallet_balance = self.limited(self.client.get_xtr_balance(account)).await?;
         if wallet_balance < max_fee {
             return Err(Error::InsufficientBalance {
                 current: wallet_balance,
@@ -165,6 +231,8 @@ impl TemplatePublisher {
         Ok(CheckBalanceResult {
             max_fee,
             binary_size: bin_size,
+            wallet_balance,
+            template_hash,
         })
     }
 
@@ -173,16 +241,19 @@ impl TemplatePublisher {
         &self,
         request: PublishTemplateRequest,
         tx_finalize_timeout: Option<Duration>,
-    ) -> Result<TemplateAddress> {
-        let mut client = self.wallet_daemon_client().await?;
-        let response = client.publish_template(request).await?;
+    ) -> Result<PublishOutcome> {
+        let response = self.limited(self.client.publish_template(&request)).await?;
 
-        let tx_resp = client
-            .wait_transaction_result(TransactionWaitResultRequest {
-                transaction_id: response.transaction_id,
-                timeout_secs: tx_finalize_timeout.map(|duration| duration.as_secs()),
-            })
-            .await?;
+        let wait = self.limited(self.client.wait_transaction_result(TransactionWaitResultRequest {
+            transaction_id: response.transaction_id,
+            timeout_secs: tx_finalize_timeout.map(|duration| duration.as_secs()),
+        }));
+        let tx_resp = tokio::select! {
+            result = wait => result?,
+            _ = self.cancellation.cancelled() => {
+                return Err(Error::Cancelled(response.transaction_id.to_string()));
+            },
+        };
 
         if tx_resp.timed_out {
             return Err(Error::WaitForTransactionTimeout(response.transaction_id.to_string()));
@@ -192,16 +263,19 @@ impl TemplatePublisher {
             .result
             .ok_or(Error::MissingTransactionResult(response.transaction_id.to_string()))?;
         if !matches!(finalize_result.result, TransactionResult::Accept(_)) {
-            let error_status = match finalize_result.result {
-                TransactionResult::AcceptFeeRejectRest(_, reason) | TransactionResult::Reject(reason) => {
-                    format!("⚠️ Status: {}\n⚠️ Reason: {}", tx_resp.status, reason)
-                },
-                TransactionResult::Accept(_) => String::new(), // does not happen here
+            let (error_status, transient) = match finalize_result.result {
+                TransactionResult::AcceptFeeRejectRest(_, reason) | TransactionResult::Reject(reason) => (
+                    format!("⚠️ Status: {}\n⚠️ Reason: {}", tx_resp.status, reason),
+                    is_transient_reject_reason(&reason),
+                ),
+                TransactionResult::Accept(_) => (String::new(), false), // does not happen here
             };
-            return Err(Error::InvalidTransaction(
-                response.transaction_id.to_string(),
-                error_status,
-            ));
+            let transaction_id = response.transaction_id.to_string();
+            return Err(if transient {
+                Error::TransientRejection(transaction_id, error_status)
+            } else {
+                Error::InvalidTransaction(transaction_id, error_status)
+            });
         }
 
         // look for the new UP template substate
@@ -215,97 +289,160 @@ impl TemplatePublisher {
             }
         }
 
-        result.ok_or(Error::MissingPublishedTemplate)
+        let template_address = result.ok_or(Error::MissingPublishedTemplate)?;
+        Ok(PublishOutcome {
+            template_address,
+            transaction_id: response.transaction_id.to_string(),
+            fee_charged: tx_resp.final_fee.into(),
+            status: tx_resp.status,
+        })
     }
 
-    async fn create_publish_template_request(
+    /// Builds the request [`TemplatePublisher::publish`] would submit, and the template hash.
+    /// Nothing is sent to the wallet.
+    pub async fn create_publish_template_request(
         &self,
         account: &ComponentAddressOrName,
         template: &Template,
         max_fee: u64,
         metadata_hash: Option<MetadataHash>,
-    ) -> Result<PublishTemplateRequest> {
-        let (binary, _, _) = self.validate_and_load_wasm_template(template).await?;
-        Ok(PublishTemplateRequest {
+    ) -> Result<(PublishTemplateRequest, Hash32)> {
+        let (binary, _, template_hash) = validate_and_load_wasm_template(template).await?;
+        let request = PublishTemplateRequest {
             binary: binary.into_owned(),
             fee_account: Some(account.clone()),
             max_fee,
             metadata: metadata_hash.map(PublishTemplateMetadata::Hash),
             detect_inputs: true,
             dry_run: false,
-        })
-    }
-
-    /// Validating provided wasm template on the given path.
-    async fn validate_and_load_wasm_template<'a>(
-        &self,
-        params: &'a Template,
-    ) -> Result<(Cow<'a, [u8]>, LoadedTemplate, Hash32)> {
-        let mut wasm_code: Cow<'_, [u8]> = match params {
-            Template::Path { path } => {
-                let bin = fs::read(path).await?;
-                Cow::Owned(bin)
-            },
-            Template::Binary { bin } => Cow::Borrowed(bin),
         };
-        wasm_code = Self::optimize_wasm_template(wasm_code).await?;
-
-        let template = WasmModule::load_template_from_code(wasm_code.as_ref())?;
-        let wasm_hash: Hash32 = template_hasher32().chain(&wasm_code).result();
-        Ok((wasm_code, template, wasm_hash))
+        Ok((request, template_hash))
     }
 
-    async fn optimize_wasm_template<'a>(wasm_code: Cow<'a, [u8]>) -> Result<Cow<'a, [u8]>> {
-        #[cfg(feature = "wasm-opt")]
-        {
-            let optimized = crate::wasm_opt::optimize_wasm_template(wasm_code.as_ref()).await?;
-            Ok(Cow::Owned(optimized))
-        }
-        #[cfg(not(feature = "wasm-opt"))]
-        {
-            Ok(wasm_code)
-        }
+    /// Hash of the template binary as it would be published (after optimization, if enabled).
+    pub async fn template_hash(&self, template: &Template) -> Result<Hash32> {
+        let (_, _, template_hash) = validate_and_load_wasm_template(template).await?;
+        Ok(template_hash)
     }
+}
 
-    /// Get available wallet TARI_TOKEN balance.
-    async fn wallet_xtr_balance(&self, account: &ComponentAddressOrName) -> Result<Amount> {
-        let mut client = self.wallet_daemon_client().await?;
-        let balances_response = client
-            .get_account_balances(AccountsGetBalancesRequest {
-                account: Some(account.clone()),
-                refresh: false,
-            })
-            .await?;
-        let balance = balances_response
-            .balances
-            .iter()
-            .find(|b| b.resource_address == TARI_TOKEN)
-            .map(|b| b.balance)
-            .unwrap_or_default();
-
-        Ok(balance)
+/// Whether a rejection may not recur on a later attempt: the transaction lost a race for its
+/// inputs or outputs to another transaction. Anything about the transaction itself (its fee, the
+/// template, a failed execution) is permanent.
+fn is_transient_reject_reason(reason: &RejectReason) -> bool {
+    matches!(
+        reason,
+        RejectReason::FailedToLockInputs(_) | RejectReason::FailedToLockOutputs(_)
+    )
+}
+
+/// A template binary loaded offline by [`inspect_template`], as it would be published.
+#[derive(Debug, Clone)]
+pub struct TemplateInspection {
+    pub template_name: String,
+    /// Size of the binary that would be published (after optimization, if enabled).
+    pub binary_size: usize,
+    /// Hash of the binary that would be published.
+    pub template_hash: Hash32,
+    /// Functions from the template ABI.
+    pub functions: Vec<TemplateFunction>,
+}
+
+/// A function from a template ABI, with types rendered as text.
+#[derive(Debug, Clone)]
+pub struct TemplateFunction {
+    pub name: String,
+    /// Argument names and types.
+    pub arguments: Vec<(String, String)>,
+    pub output: String,
+    pub is_mut: bool,
+}
+
+/// Loads and validates a template binary the same way publishing does, without a wallet daemon.
+pub async fn inspect_template(template: &Template) -> Result<TemplateInspection> {
+    let (binary, loaded, template_hash) = validate_and_load_wasm_template(template).await?;
+    let functions = loaded
+        .template_def()
+        .functions()
+        .iter()
+        .map(|function| TemplateFunction {
+            name: function.name.clone(),
+            arguments: function
+                .arguments
+                .iter()
+                .map(|arg| (arg.name.clone(), format!("{:?}", arg.arg_type)))
+                .collect(),
+            output: format!("{:?}", function.output),
+            is_mut: function.is_mut,
+        })
+        .collect();
+    Ok(TemplateInspection {
+        template_name: loaded.template_name().to_string(),
+        binary_size: binary.len(),
+        template_hash,
+        functions,
+    })
+}
+
+/// Validating provided wasm template on the given path.
+async fn validate_and_load_wasm_template(params: &Template) -> Result<(Cow<'_, [u8]>, LoadedTemplate, Hash32)> {
+    let mut wasm_code: Cow<'_, [u8]> = match params {
+        Template::Path { path } => Cow::Owned(read_template_binary(path).await?),
+        Template::Binary { bin } => Cow::Borrowed(bin),
+    };
+    wasm_code = optimize_wasm_template(wasm_code).await?;
+
+    let template = WasmModule::load_template_from_code(wasm_code.as_ref())?;
+    let wasm_hash: Hash32 = template_hasher32().chain(&wasm_code).result();
+    Ok((wasm_code, template, wasm_hash))
+}
+
+/// Reads a template binary, naming the path in any error.
+async fn read_template_binary(path: &Path) -> Result<Vec<u8>> {
+    let read_error = |source| Error::ReadTemplateBinary {
+        path: path.to_path_buf(),
+        source,
+    };
+    let metadata = fs::metadata(path).await.map_err(read_error)?;
+    if !metadata.is_file() {
+        return Err(read_error(io::Error::new(io::ErrorKind::InvalidInput, "not a file")));
     }
+    fs::read(path).await.map_err(read_error)
+}
 
-    /// Returns a new wallet daemon client.
-    ///
-    /// When an API key is configured it is sent as the `Authorization: Bearer`
-    /// token on every request — no `auth.request` round-trip is performed. The
-    /// key must be minted with at least the `templates:read`, `templates:create`,
-    /// `accounts:read` and `transactions:read` permissions for publishing to
-    /// succeed (publishing waits on the transaction result to confirm).
-    pub async fn wallet_daemon_client(&self) -> Result<WalletDaemonClient> {
-        let token = self
-            .network
-            .api_key()
-            .map(|key| EncodedJwtString::from(key.to_string()));
-        let client = WalletDaemonClient::connect(self.network.wallet_daemon_jrpc_address().clone(), token)?;
-        Ok(client)
+async fn optimize_wasm_template(wasm_code: Cow<'_, [u8]>) -> Result<Cow<'_, [u8]>> {
+    #[cfg(feature = "wasm-opt")]
+    {
+        let optimized = crate::wasm_opt::optimize_wasm_template(wasm_code.as_ref()).await?;
+        Ok(Cow::Owned(optimized))
+    }
+    #[cfg(not(feature = "wasm-opt"))]
+    {
+        Ok(wasm_code)
     }
 }
 
+/// Result of a successful [`TemplatePublisher::publish`].
+#[derive(Debug, Clone)]
+pub struct PublishOutcome {
+    /// Address of the newly published template.
+    pub template_address: TemplateAddress,
+    /// ID of the publish transaction.
+    pub transaction_id: String,
+    /// Fee actually charged for the transaction.
+    pub fee_charged: Amount,
+    /// Final status of the transaction.
+    pub status: TransactionStatus,
+}
+
 pub struct CheckBalanceResult {
     pub max_fee: u64,
+    /// Size of the binary that will be published (after optimization, if enabled).
     pub binary_size: usize,
+    /// TARI balance of the fee account at the time of the check.
+    pub wallet_balance: Amount,
+    /// Hash of the binary that will be published.
+    pub template_hash: Hash32,
 }
 
 /// All fields needed to POST signed metadata to the community server.
@@ -321,3 +458,22 @@ pub struct SignedMetadataPayload {
     pub public_key: tari_template_lib_types::crypto::RistrettoPublicKeyBytes,
     pub metadata_hash: MetadataHash,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn unreadable_binary_error_names_the_path() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let missing = temp_dir.path().join("missing.wasm");
+        for path in [missing, temp_dir.path().to_path_buf()] {
+            let error = inspect_template(&Template::Path { path: path.clone() })
+                .await
+                .err()
+                .unwrap();
+            assert!(matches!(error, Error::ReadTemplateBinary { .. }), "got: {error:?}");
+            assert!(error.to_string().contains(&path.display().to_string()), "got: {error}");
+        }
+    }
+}
@@ -0,0 +1,153 @@
+// Copyright 2024 The Tari Project
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::future::Future;
+
+use tari_ootle_common_types::optional::Optional;
+use tari_ootle_walletd_client::types::{
+    AccountsCreateFreeTestCoinsRequest, AccountsGetBalancesRequest, EncodedJwtString, PublishTemplateRequest,
+    PublishTemplateResponse, SignTemplateMetadataRequest, SignTemplateMetadataResponse, TransactionWaitResultRequest,
+    TransactionWaitResultResponse, WalletGetInfoResponse,
+};
+use tari_ootle_walletd_client::{ComponentAddressOrName, WalletDaemonClient};
+use tari_template_lib_types::Amount;
+use tari_template_lib_types::constants::TARI_TOKEN;
+
+use crate::NetworkConfig;
+use crate::publisher::Result;
+
+/// The wallet operations [`crate::publisher::TemplatePublisher`] depends on.
+///
+/// [`WalletDaemonConnector`] implements this against a real wallet daemon. Implement it yourself
+/// to mock the wallet in tests or to route requests through your own client.
+pub trait WalletClient: Send + Sync {
+    /// Returns information about the wallet (version, network).
+    fn get_wallet_info(&self) -> impl Future<Output = Result<WalletGetInfoResponse>> + Send;
+
+    /// Returns the wallet's default account, if one is set.
+    fn get_default_account(&self) -> impl Future<Output = Result<Option<ComponentAddressOrName>>> + Send;
+
+    /// Returns the TARI balance of `account`.
+    fn get_xtr_balance(&self, account: &ComponentAddressOrName) -> impl Future<Output = Result<Amount>> + Send;
+
+    /// Creates the account `name` (if the wallet doesn't have it yet) and funds it with free test
+    /// coins. Only test networks support this.
+    fn create_free_test_account(&self, name: &str) -> impl Future<Output = Result<ComponentAddressOrName>> + Send;
+
+    /// Signs template metadata using the wallet's key management.
+    fn sign_template_metadata(
+        &self,
+        request: SignTemplateMetadataRequest,
+    ) -> impl Future<Output = Result<SignTemplateMetadataResponse>> + Send;
+
+    /// Submits (or dry-runs) a template publish transaction.
+    fn publish_template(
+        &self,
+        request: &PublishTemplateRequest,
+    ) -> impl Future<Output = Result<PublishTemplateResponse>> + Send;
+
+    /// Waits for a submitted transaction to be finalized.
+    fn wait_transaction_result(
+        &self,
+        request: TransactionWaitResultRequest,
+    ) -> impl Future<Output = Result<TransactionWaitResultResponse>> + Send;
+}
+
+/// Max fee of the transaction that funds an account created by
+/// [`WalletClient::create_free_test_account`], the same bound the publish fee estimate runs with.
+const FREE_TEST_COINS_MAX_FEE: u64 = 1_000_000;
+
+/// [`WalletClient`] that connects a fresh [`WalletDaemonClient`] for every request.
+pub struct WalletDaemonConnector {
+    network: NetworkConfig,
+}
+
+impl WalletDaemonConnector {
+    pub fn new(network: NetworkConfig) -> Self {
+        Self { network }
+    }
+
+    pub fn network(&self) -> &NetworkConfig {
+        &self.network
+    }
+
+    /// Returns a new wallet daemon client.
+    ///
+    /// When an API key is configured it is sent as the `Authorization: Bearer`
+    /// token on every request — no `auth.request` round-trip is performed. The
+    /// key must be minted with at least the `templates:read`, `templates:create`,
+    /// `accounts:read` and `transactions:read` permissions for publishing to
+    /// succeed (publishing waits on the transaction result to confirm).
+    pub fn connect(&self) -> Result<WalletDaemonClient> {
+        let token = self
+            .network
+            .api_key()
+            .map(|key| EncodedJwtString::from(key.to_string()));
+        let client = WalletDaemonClient::connect(self.network.wallet_daemon_jrpc_address().clone(), token)?;
+        Ok(client)
+    }
+}
+
+impl WalletClient for WalletDaemonConnector {
+    async fn get_wallet_info(&self) -> Result<WalletGetInfoResponse> {
+        let mut client = self.connect()?;
+        Ok(client.get_wallet_info().await?)
+    }
+
+    async fn get_default_account(&self) -> Result<Option<ComponentAddressOrName>> {
+        let mut client = self.connect()?;
+        let account = client.accounts_get_default().await.optional()?;
+        let address = account.map(|a| *a.account.component_address());
+        Ok(address.map(Into::into))
+    }
+
+    async fn get_xtr_balance(&self, account: &ComponentAddressOrName) -> Result<Amount> {
+        let mut client = self.connect()?;
+        let balances_response = client
+            .get_account_balances(AccountsGetBalancesRequest {
+                account: Some(account.clone()),
+                refresh: false,
+            })
+            .await?;
+        let balance = balances_response
+            .balances
+            .iter()
+            .find(|b| b.resource_address == TARI_TOKEN)
+            .map(|b| b.balance)
+            .unwrap_or_default();
+
+        Ok(balance)
+    }
+
+    async fn create_free_test_account(&self, name: &str) -> Result<ComponentAddressOrName> {
+        let mut client = self.connect()?;
+        let response = client
+            .create_free_test_coins(AccountsCreateFreeTestCoinsRequest {
+                account: ComponentAddressOrName::Name(name.to_string()),
+                max_fee: FREE_TEST_COINS_MAX_FEE,
+            })
+            .await?;
+        Ok((*response.account.component_address()).into())
+    }
+
+    async fn sign_template_metadata(
+        &self,
+        request: SignTemplateMetadataRequest,
+    ) -> Result<SignTemplateMetadataResponse> {
+        let mut client = self.connect()?;
+        Ok(client.sign_template_metadata(request).await?)
+    }
+
+    async fn publish_template(&self, request: &PublishTemplateRequest) -> Result<PublishTemplateResponse> {
+        let mut client = self.connect()?;
+        Ok(client.publish_template(request).await?)
+    }
+
+    async fn wait_transaction_result(
+        &self,
+        request: TransactionWaitResultRequest,
+    ) -> Result<TransactionWaitResultResponse> {
+        let mut client = self.connect()?;
+        Ok(client.wait_transaction_result(request).await?)
+    }
+}